@@ -0,0 +1,61 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use dexter_core::{mock::FixtureMiddleware, ArchiveDownload, Client, Request};
+
+static CHAPTER_ID: &str = "07bf2a09-f30d-410f-aba1-025e2d27a88f";
+static CHAPTER_HASH: &str = "3c1e0b9f5d7a4e2b8c6d0f1a2b3c4d5e";
+static PAGE_SIZE: usize = 512 * 1024;
+
+/// Pseudo random bytes, so that the pages don't compress better than real images would
+fn page(seed: usize) -> Vec<u8> {
+    let mut state = seed as u64 + 1;
+    (0..PAGE_SIZE)
+        .map(|_| {
+            state = state
+                .wrapping_mul(6_364_136_223_846_793_005)
+                .wrapping_add(1_442_695_040_888_963_407);
+            (state >> 56) as u8
+        })
+        .collect()
+}
+
+/// Serves a chapter of `pages` pages from memory, so that only the download and packing loop is measured
+fn fixtures(pages: usize) -> FixtureMiddleware {
+    let filenames = (1..=pages)
+        .map(|index| format!("{index}-{index:012x}.jpg"))
+        .collect::<Vec<_>>();
+    let data = filenames
+        .iter()
+        .map(|filename| format!("\"{filename}\""))
+        .collect::<Vec<_>>()
+        .join(",");
+    let at_home = format!(
+        r#"{{"result":"ok","baseUrl":"https://uploads.mangadex.org","chapter":{{"hash":"{CHAPTER_HASH}","data":[{data}],"dataSaver":[]}}}}"#
+    );
+
+    filenames.iter().enumerate().fold(
+        FixtureMiddleware::new().with_fixture(format!("/at-home/server/{CHAPTER_ID}"), at_home),
+        |fixtures, (index, filename)| {
+            fixtures.with_fixture(format!("/data/{CHAPTER_HASH}/{filename}"), page(index))
+        },
+    )
+}
+
+fn archive_download(c: &mut Criterion) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let mut group = c.benchmark_group("archive_download");
+    group.sample_size(10);
+
+    for pages in [10, 50] {
+        let client = Client::new().with_middleware(fixtures(pages));
+        group.throughput(Throughput::Bytes((pages * PAGE_SIZE) as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(pages), &client, |b, client| {
+            b.to_async(&runtime)
+                .iter(|| ArchiveDownload::new(CHAPTER_ID).request_with(client));
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, archive_download);
+criterion_main!(benches);
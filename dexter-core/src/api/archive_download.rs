@@ -1,24 +1,82 @@
-use std::io::Cursor;
+use std::{
+    collections::HashMap,
+    future::Future,
+    io::Cursor,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
 
-use async_trait::async_trait;
+use bytes::Bytes;
 use camino::Utf8Path;
 use eco_cbz::CbzWriter;
 use futures::{stream, StreamExt, TryStreamExt};
-use reqwest_middleware::ClientBuilder;
-use reqwest_retry::{policies::ExponentialBackoff, RetryTransientMiddleware};
-use tokio::sync::{mpsc, Mutex};
-use tracing::{error, info};
+use reqwest::StatusCode;
+use reqwest_middleware::ClientWithMiddleware;
+use tokio::{
+    sync::{mpsc, Mutex, RwLock},
+    task::{JoinError, JoinHandle},
+};
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
 
-use crate::{Error, GetImageLinks, Request, Result};
+use crate::{
+    page_store::PageStore,
+    progress::{BoundedSender, NoProgress, ProgressSink},
+    transform::{Page, PageTransform, Transforms},
+    Client, Error, GetImageLinks, Request, Result,
+};
+
+use super::{
+    download_image::{read_image_body, with_stall_timeout, DEFAULT_MAX_IMAGE_SIZE},
+    get_image_links,
+};
 
 pub static DEFAULT_MAX_PARALLEL_DOWNLOAD: usize = 10;
 pub static DEFAULT_MAX_DOWNLOAD_RETRIES: u32 = 10;
+pub static DEFAULT_MAX_CONSECUTIVE_FAILURES: usize = 3;
+pub static DEFAULT_STALL_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How many times a single page can be retried against a new MD@Home node
+static MAX_FAILOVERS_PER_PAGE: usize = 2;
+
+/// Aborts the page download task when dropped, dropping a bare [`JoinHandle`] would leave it running
+/// after the chapter download is cancelled, or has failed
+struct AbortOnDrop<T>(JoinHandle<T>);
+
+impl<T> Drop for AbortOnDrop<T> {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+impl<T> Future for AbortOnDrop<T> {
+    type Output = Result<T, JoinError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.0).poll(cx)
+    }
+}
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Event {
     Init(usize),
+    /// Amount of bytes received for an image, failed attempts included
+    Progress(usize),
     Download,
     Zip,
+    /// Several `Download`, `Zip`, and `Progress` events merged together, see [`crate::progress::BoundedSender`]
+    Coalesced {
+        downloads: usize,
+        zips: usize,
+        bytes: usize,
+    },
+    /// The original of the page with this filename is missing, its data saver version was downloaded instead
+    DataSaver(String),
     Done,
 }
 
@@ -28,18 +86,30 @@ pub struct ArchiveDownload {
     chapter_id: String,
     max_parallel_download: usize,
     max_download_retries: u32,
-    sender: mpsc::UnboundedSender<Event>,
+    max_consecutive_failures: usize,
+    max_image_size: u64,
+    stall_timeout: Option<Duration>,
+    deadline: Option<Duration>,
+    progress: Arc<dyn ProgressSink<Event>>,
+    page_store: Option<Arc<PageStore>>,
+    transforms: Transforms,
+    cancellation_token: CancellationToken,
 }
 
 impl ArchiveDownload {
     pub fn new(chapter_id: impl Into<String>) -> Self {
-        let (tx, _rx) = mpsc::unbounded_channel();
-
         Self {
             chapter_id: chapter_id.into(),
             max_parallel_download: DEFAULT_MAX_PARALLEL_DOWNLOAD,
             max_download_retries: DEFAULT_MAX_DOWNLOAD_RETRIES,
-            sender: tx,
+            max_consecutive_failures: DEFAULT_MAX_CONSECUTIVE_FAILURES,
+            max_image_size: DEFAULT_MAX_IMAGE_SIZE,
+            stall_timeout: Some(DEFAULT_STALL_TIMEOUT),
+            deadline: None,
+            progress: Arc::new(NoProgress),
+            page_store: None,
+            transforms: Transforms::default(),
+            cancellation_token: CancellationToken::new(),
         }
     }
 
@@ -55,58 +125,151 @@ impl ArchiveDownload {
         self
     }
 
+    /// After this many pages failed in a row, a new MD@Home node is requested and the failed pages are retried against it.
+    ///
+    /// Below that, a failing page is retried once on the same node before moving to a new one,
+    /// and the chapter fails if the page can't be downloaded from any node.
     #[must_use]
-    pub fn set_sender(mut self, sender: mpsc::UnboundedSender<Event>) -> Self {
-        self.sender = sender;
+    pub fn set_max_consecutive_failures(mut self, max_consecutive_failures: usize) -> Self {
+        self.max_consecutive_failures = max_consecutive_failures;
         self
     }
-}
 
-#[async_trait]
-impl Request for ArchiveDownload {
-    type Response = CbzWriter<Cursor<Vec<u8>>>;
+    /// Size in bytes above which an image download is aborted
+    #[must_use]
+    pub fn set_max_image_size(mut self, max_image_size: u64) -> Self {
+        self.max_image_size = max_image_size;
+        self
+    }
+
+    /// An image download receiving no bytes for this long is aborted, and retried on a new MD@Home node,
+    /// `None` waiting forever
+    #[must_use]
+    pub fn set_stall_timeout(mut self, stall_timeout: Option<Duration>) -> Self {
+        self.stall_timeout = stall_timeout;
+        self
+    }
+
+    /// The request fails with [`Error::DeadlineExceeded`] if the whole chapter isn't downloaded within this time
+    #[must_use]
+    pub fn set_deadline(mut self, deadline: Option<Duration>) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    /// Receives the progress events of the download, see [`crate::progress`] for the provided sinks
+    #[must_use]
+    pub fn set_progress(mut self, progress: Arc<dyn ProgressSink<Event>>) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Sends the progress events to the channel, shorthand for [`Self::set_progress`]
+    #[must_use]
+    pub fn set_sender(self, sender: mpsc::UnboundedSender<Event>) -> Self {
+        self.set_progress(Arc::new(sender))
+    }
+
+    /// Sends the progress events to a bounded channel, merging them while the receiver lags behind,
+    /// shorthand for [`Self::set_progress`] with a [`BoundedSender`]
+    #[must_use]
+    pub fn set_bounded_sender(self, sender: mpsc::Sender<Event>) -> Self {
+        self.set_progress(Arc::new(BoundedSender::new(sender)))
+    }
+
+    /// Pages found in the store are not downloaded, and the downloaded pages are added to it
+    #[must_use]
+    pub fn set_page_store(mut self, page_store: Arc<PageStore>) -> Self {
+        self.page_store = Some(page_store);
+        self
+    }
 
-    async fn request(self) -> Result<Self::Response> {
-        let retry_policy =
-            ExponentialBackoff::builder().build_with_max_retries(self.max_download_retries);
-        let client = ClientBuilder::new(reqwest::Client::new())
-            .with(RetryTransientMiddleware::new_with_policy(retry_policy))
-            .build();
+    /// Transforms the pages before they're packed, the page store keeps the pages as downloaded
+    #[must_use]
+    pub fn set_transforms(mut self, transforms: Transforms) -> Self {
+        self.transforms = transforms;
+        self
+    }
+
+    /// Cancelling the token aborts the download, and the request fails with [`Error::Cancelled`]
+    #[must_use]
+    pub fn set_cancellation_token(mut self, cancellation_token: CancellationToken) -> Self {
+        self.cancellation_token = cancellation_token;
+        self
+    }
+
+    async fn download(self, client: &Client) -> Result<CbzWriter<Cursor<Vec<u8>>>> {
+        let image_links = GetImageLinks::new(&self.chapter_id)
+            .request_with(client)
+            .await?;
+        // External chapters have no pages, there would be nothing to download
+        if image_links.is_empty() {
+            return Err(Error::Unavailable(self.chapter_id));
+        }
+        let node = Arc::new(AtHomeNode::new(
+            client.clone(),
+            self.chapter_id,
+            &image_links,
+            self.max_consecutive_failures,
+            self.max_image_size,
+            self.stall_timeout,
+        ));
+        let client = client.http_with_retries(self.max_download_retries);
         let cbz_writer = Mutex::new(CbzWriter::default());
-        let image_links = GetImageLinks::new(self.chapter_id).request().await?;
         let len = image_links.len();
 
-        self.sender.send(Event::Init(len))?;
+        self.progress.on_event(&Event::Init(len));
 
         stream::iter(image_links)
             .map(|description| {
                 let client = client.clone();
-                let tx = self.sender.clone();
-                tokio::spawn(async move {
-                    info!("Downloading {}", description.url);
+                let node = Arc::clone(&node);
+                let progress = Arc::clone(&self.progress);
+                let page_store = self.page_store.clone();
+                let transforms = self.transforms.clone();
+                AbortOnDrop(tokio::spawn(async move {
+                    let stored = match &page_store {
+                        Some(page_store) => page_store.get(&description.filename).await,
+                        None => None,
+                    };
+                    let (filename, bytes) = if let Some(bytes) = stored {
+                        (description.filename, bytes)
+                    } else {
+                        let (filename, bytes) = node
+                            .download(&client, &description.filename, progress.as_ref())
+                            .await?;
+                        // Data saver versions are not stored, the original may be served again later
+                        if let (Some(page_store), true) =
+                            (&page_store, filename == description.filename)
+                        {
+                            page_store.insert(&filename, &bytes).await;
+                        }
+                        (filename, bytes)
+                    };
 
-                    let response = client.get(description.url).send().await?;
+                    progress.on_event(&Event::Download);
 
-                    let bytes = response.bytes().await?;
-
-                    tx.send(Event::Download)?;
+                    if transforms.is_empty() {
+                        return Ok((filename, bytes));
+                    }
+                    let page = tokio::task::spawn_blocking(move || {
+                        transforms.apply(Page { filename, bytes })
+                    })
+                    .await??;
 
-                    Ok::<_, Error>((description.filename, bytes))
-                })
+                    Ok::<_, Error>((page.filename, page.bytes))
+                }))
             })
-            .buffered(len.min(self.max_parallel_download))
+            .buffered(len.min(self.max_parallel_download).max(1))
             .map_err(|err| {
                 error!("join handle error: {err}");
                 Error::from(err)
             })
             .try_for_each(|res| async {
-                let (filename, bytes) = match res {
-                    Ok(ok) => ok,
-                    Err(err) => {
-                        error!("impossible to pack image, skipping: {err}");
-                        return Ok(());
-                    }
-                };
+                // A missing page fails the whole chapter, an incomplete archive would pass for a complete one
+                let (filename, bytes) = res.inspect_err(|err| {
+                    error!("impossible to pack image: {err}");
+                })?;
 
                 info!("Packing {filename}");
 
@@ -123,17 +286,235 @@ impl Request for ArchiveDownload {
                     })?;
                 drop(cbz_writer_guard);
 
-                self.sender.send(Event::Zip).map_err(|err| {
-                    error!("failed to send message to channel");
-                    Error::from(err)
-                })?;
+                self.progress.on_event(&Event::Zip);
 
                 Ok(())
             })
             .await?;
 
-        self.sender.send(Event::Done)?;
+        self.progress.on_event(&Event::Done);
 
         Ok(cbz_writer.into_inner())
     }
 }
+
+impl Request for ArchiveDownload {
+    type Response = CbzWriter<Cursor<Vec<u8>>>;
+
+    async fn request_with(self, client: &Client) -> Result<Self::Response> {
+        let cancellation_token = self.cancellation_token.clone();
+        let deadline = self.deadline;
+        let download = async {
+            match deadline {
+                Some(deadline) => tokio::time::timeout(deadline, self.download(client))
+                    .await
+                    .map_err(|_| Error::DeadlineExceeded(deadline))?,
+                None => self.download(client).await,
+            }
+        };
+
+        tokio::select! {
+            res = download => res,
+            () = cancellation_token.cancelled() => Err(Error::Cancelled),
+        }
+    }
+}
+
+/// Urls of the original and of the data saver version of a page
+#[derive(Debug, Clone)]
+struct PageUrls {
+    url: String,
+    data_saver_url: Option<String>,
+}
+
+impl From<get_image_links::Description> for PageUrls {
+    fn from(description: get_image_links::Description) -> Self {
+        Self {
+            url: description.url,
+            data_saver_url: description.data_saver_url,
+        }
+    }
+}
+
+/// Image urls served by the current MD@Home node, a new node is requested when the current one stops answering.
+#[derive(Debug)]
+struct AtHomeNode {
+    client: Client,
+    chapter_id: String,
+    max_consecutive_failures: usize,
+    max_image_size: u64,
+    stall_timeout: Option<Duration>,
+    /// The urls are tagged with a generation, incremented on each failover,
+    /// so that concurrent failures only trigger one new node request
+    urls: RwLock<(usize, HashMap<String, PageUrls>)>,
+    consecutive_failures: AtomicUsize,
+}
+
+impl AtHomeNode {
+    fn new(
+        client: Client,
+        chapter_id: String,
+        image_links: &[get_image_links::Description],
+        max_consecutive_failures: usize,
+        max_image_size: u64,
+        stall_timeout: Option<Duration>,
+    ) -> Self {
+        let urls = image_links
+            .iter()
+            .map(|description| (description.filename.clone(), description.clone().into()))
+            .collect();
+
+        Self {
+            client,
+            chapter_id,
+            max_consecutive_failures,
+            max_image_size,
+            stall_timeout,
+            urls: RwLock::new((0, urls)),
+            consecutive_failures: AtomicUsize::new(0),
+        }
+    }
+
+    async fn urls(&self, filename: &str) -> (usize, Option<PageUrls>) {
+        let urls = self.urls.read().await;
+        (urls.0, urls.1.get(filename).cloned())
+    }
+
+    /// Requests a new node, unless another page already did it since `generation`
+    async fn failover(&self, generation: usize) -> Result<()> {
+        let mut urls = self.urls.write().await;
+        if urls.0 != generation {
+            return Ok(());
+        }
+
+        warn!(
+            "MD@Home node is failing, requesting a new one for chapter {}",
+            self.chapter_id
+        );
+
+        let image_links = GetImageLinks::new(&self.chapter_id)
+            .request_with(&self.client)
+            .await?;
+        *urls = (
+            generation + 1,
+            image_links
+                .into_iter()
+                .map(|description| (description.filename.clone(), description.into()))
+                .collect(),
+        );
+        self.consecutive_failures.store(0, Ordering::Relaxed);
+
+        Ok(())
+    }
+
+    /// Downloads the page, returns the name of the downloaded file along with its content,
+    /// which is the name of the data saver version if the original is missing
+    async fn download(
+        &self,
+        client: &ClientWithMiddleware,
+        filename: &str,
+        progress: &dyn ProgressSink<Event>,
+    ) -> Result<(String, Bytes)> {
+        let mut failovers = 0;
+        let mut retried = false;
+
+        loop {
+            let (generation, urls) = self.urls(filename).await;
+            let Some(PageUrls {
+                url,
+                data_saver_url,
+            }) = urls
+            else {
+                return Err(Error::MissingImage(filename.to_string()));
+            };
+
+            info!("Downloading {url}");
+
+            let err = match fetch(
+                client,
+                &url,
+                self.max_image_size,
+                self.stall_timeout,
+                progress,
+            )
+            .await
+            {
+                Ok(bytes) => {
+                    self.consecutive_failures.store(0, Ordering::Relaxed);
+                    return Ok((filename.to_string(), bytes));
+                }
+                Err(err) => err,
+            };
+
+            let not_found =
+                matches!(&err, Error::Reqwest(err) if err.status() == Some(StatusCode::NOT_FOUND));
+            if let (true, Some(data_saver_url)) = (not_found, data_saver_url) {
+                return self
+                    .download_data_saver(client, filename, &data_saver_url, progress)
+                    .await;
+            }
+            if failovers >= MAX_FAILOVERS_PER_PAGE {
+                return Err(err);
+            }
+
+            let failures = self.consecutive_failures.fetch_add(1, Ordering::Relaxed) + 1;
+            // A stalled node is unlikely to recover, the page is retried elsewhere straight away
+            let node_changed = self.urls.read().await.0 != generation;
+            let stalled = matches!(err, Error::Stalled(_));
+            if !retried && !node_changed && !stalled && failures < self.max_consecutive_failures {
+                warn!("failed to download {url}, retrying: {err}");
+                retried = true;
+                continue;
+            }
+
+            warn!("failed to download {url}, retrying on another node: {err}");
+            self.failover(generation).await?;
+            failovers += 1;
+        }
+    }
+
+    /// Downloads the data saver version of a page whose original is missing
+    async fn download_data_saver(
+        &self,
+        client: &ClientWithMiddleware,
+        filename: &str,
+        data_saver_url: &str,
+        progress: &dyn ProgressSink<Event>,
+    ) -> Result<(String, Bytes)> {
+        warn!("{filename} not found, downloading its data saver version {data_saver_url}");
+
+        let bytes = fetch(
+            client,
+            data_saver_url,
+            self.max_image_size,
+            self.stall_timeout,
+            progress,
+        )
+        .await?;
+        progress.on_event(&Event::DataSaver(filename.to_string()));
+        let data_saver_filename = data_saver_url
+            .rsplit('/')
+            .next()
+            .unwrap_or(filename)
+            .to_string();
+
+        Ok((data_saver_filename, bytes))
+    }
+}
+
+async fn fetch(
+    client: &ClientWithMiddleware,
+    url: &str,
+    max_image_size: u64,
+    stall_timeout: Option<Duration>,
+    progress: &dyn ProgressSink<Event>,
+) -> Result<Bytes> {
+    let response = with_stall_timeout(stall_timeout, client.get(url).send())
+        .await??
+        .error_for_status()?;
+
+    read_image_body(response, max_image_size, stall_timeout, |len| {
+        progress.on_event(&Event::Progress(len));
+    })
+    .await
+}
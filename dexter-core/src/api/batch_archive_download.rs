@@ -0,0 +1,284 @@
+use std::{io::Cursor, sync::Arc, time::Duration};
+
+use eco_cbz::CbzWriter;
+use futures::{future, stream, stream::BoxStream, StreamExt};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info};
+
+use crate::{
+    api::archive_download::{
+        self, DEFAULT_MAX_DOWNLOAD_RETRIES, DEFAULT_MAX_PARALLEL_DOWNLOAD, DEFAULT_STALL_TIMEOUT,
+    },
+    page_store::PageStore,
+    progress::{BoundedSender, Coalesce, NoProgress, ProgressSink},
+    transform::Transforms,
+    ArchiveDownload, Client, Request, Result,
+};
+
+pub static DEFAULT_MAX_PARALLEL_CHAPTERS: usize = 3;
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Event {
+    /// Sent once with the amount of chapters to download
+    Init(usize),
+    /// Forwards the events of one chapter download, tagged with the chapter id
+    Chapter(String, archive_download::Event),
+    /// One chapter has been downloaded and packed
+    ChapterDone(String),
+    /// One chapter download failed, the other chapters are not affected
+    ChapterFailed(String),
+    /// All chapters have been processed
+    Done,
+}
+
+impl Event {
+    /// The chapter the event is about, `None` for the events of the whole batch
+    fn chapter_id(&self) -> Option<&str> {
+        match self {
+            Self::Chapter(chapter_id, _)
+            | Self::ChapterDone(chapter_id)
+            | Self::ChapterFailed(chapter_id) => Some(chapter_id),
+            Self::Init(_) | Self::Done => None,
+        }
+    }
+}
+
+/// Each chapter is a stream, its events are merged even when the chapters download concurrently, see [`BoundedSender`]
+impl Coalesce for Event {
+    fn same_stream(&self, other: &Self) -> bool {
+        self.chapter_id() == other.chapter_id()
+    }
+
+    fn coalesce(&self, next: &Self) -> Option<Self> {
+        match (self, next) {
+            (Self::Chapter(last_id, last), Self::Chapter(next_id, next)) if last_id == next_id => {
+                Some(Self::Chapter(last_id.clone(), last.coalesce(next)?))
+            }
+            _ => None,
+        }
+    }
+}
+
+pub type Response = BoxStream<'static, (String, Result<CbzWriter<Cursor<Vec<u8>>>>)>;
+
+/// Downloads several chapters concurrently, and yields one archive per chapter as soon as it's ready.
+#[derive(Debug, Clone)]
+pub struct BatchArchiveDownload {
+    chapter_ids: Vec<String>,
+    max_parallel_chapters: usize,
+    max_parallel_download: usize,
+    max_download_retries: u32,
+    stall_timeout: Option<Duration>,
+    chapter_deadline: Option<Duration>,
+    progress: Arc<dyn ProgressSink<Event>>,
+    page_store: Option<Arc<PageStore>>,
+    transforms: Transforms,
+    cancellation_token: CancellationToken,
+}
+
+impl BatchArchiveDownload {
+    pub fn new(chapter_ids: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            chapter_ids: chapter_ids.into_iter().map(Into::into).collect(),
+            max_parallel_chapters: DEFAULT_MAX_PARALLEL_CHAPTERS,
+            max_parallel_download: DEFAULT_MAX_PARALLEL_DOWNLOAD,
+            max_download_retries: DEFAULT_MAX_DOWNLOAD_RETRIES,
+            stall_timeout: Some(DEFAULT_STALL_TIMEOUT),
+            chapter_deadline: None,
+            progress: Arc::new(NoProgress),
+            page_store: None,
+            transforms: Transforms::default(),
+            cancellation_token: CancellationToken::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn set_max_parallel_chapters(mut self, max_parallel_chapters: usize) -> Self {
+        self.max_parallel_chapters = max_parallel_chapters;
+        self
+    }
+
+    #[must_use]
+    pub fn set_max_parallel_download(mut self, max_parallel_download: usize) -> Self {
+        self.max_parallel_download = max_parallel_download;
+        self
+    }
+
+    #[must_use]
+    pub fn set_max_download_retries(mut self, max_download_retries: u32) -> Self {
+        self.max_download_retries = max_download_retries;
+        self
+    }
+
+    /// See [`ArchiveDownload::set_stall_timeout`]
+    #[must_use]
+    pub fn set_stall_timeout(mut self, stall_timeout: Option<Duration>) -> Self {
+        self.stall_timeout = stall_timeout;
+        self
+    }
+
+    /// Each chapter fails with [`crate::Error::DeadlineExceeded`] if it isn't downloaded within this time,
+    /// without affecting the other chapters
+    #[must_use]
+    pub fn set_chapter_deadline(mut self, chapter_deadline: Option<Duration>) -> Self {
+        self.chapter_deadline = chapter_deadline;
+        self
+    }
+
+    /// Receives the progress events of all the chapters, see [`crate::progress`] for the provided sinks
+    #[must_use]
+    pub fn set_progress(mut self, progress: Arc<dyn ProgressSink<Event>>) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Sends the progress events to the channel, shorthand for [`Self::set_progress`]
+    #[must_use]
+    pub fn set_sender(self, sender: mpsc::UnboundedSender<Event>) -> Self {
+        self.set_progress(Arc::new(sender))
+    }
+
+    /// Sends the progress events to a bounded channel, merging them while the receiver lags behind,
+    /// shorthand for [`Self::set_progress`] with a [`BoundedSender`]
+    #[must_use]
+    pub fn set_bounded_sender(self, sender: mpsc::Sender<Event>) -> Self {
+        self.set_progress(Arc::new(BoundedSender::new(sender)))
+    }
+
+    /// Shares the store between all the chapters, identical pages are only downloaded once
+    #[must_use]
+    pub fn set_page_store(mut self, page_store: Arc<PageStore>) -> Self {
+        self.page_store = Some(page_store);
+        self
+    }
+
+    /// Transforms the pages of all the chapters, see [`ArchiveDownload::set_transforms`]
+    #[must_use]
+    pub fn set_transforms(mut self, transforms: Transforms) -> Self {
+        self.transforms = transforms;
+        self
+    }
+
+    /// Cancelling the token aborts all the in-flight and pending chapter downloads
+    #[must_use]
+    pub fn set_cancellation_token(mut self, cancellation_token: CancellationToken) -> Self {
+        self.cancellation_token = cancellation_token;
+        self
+    }
+}
+
+/// Tags the events of one chapter download with the chapter id, and forwards them to the batch sink
+#[derive(Debug)]
+struct ChapterProgress {
+    chapter_id: String,
+    progress: Arc<dyn ProgressSink<Event>>,
+}
+
+impl ProgressSink<archive_download::Event> for ChapterProgress {
+    fn on_event(&self, event: &archive_download::Event) {
+        self.progress
+            .on_event(&Event::Chapter(self.chapter_id.clone(), event.clone()));
+    }
+}
+
+/// Options shared by all the chapter downloads of a batch
+#[derive(Debug, Clone)]
+struct ChapterOptions {
+    max_parallel_download: usize,
+    max_download_retries: u32,
+    stall_timeout: Option<Duration>,
+    deadline: Option<Duration>,
+    transforms: Transforms,
+}
+
+/// Downloads one chapter, forwarding its events to the batch `progress`
+async fn download_chapter(
+    client: Client,
+    chapter_id: String,
+    options: ChapterOptions,
+    progress: Arc<dyn ProgressSink<Event>>,
+    page_store: Option<Arc<PageStore>>,
+    cancellation_token: CancellationToken,
+) -> Result<CbzWriter<Cursor<Vec<u8>>>> {
+    info!("Downloading chapter {chapter_id}");
+
+    let mut archive_download = ArchiveDownload::new(&chapter_id)
+        .set_max_parallel_download(options.max_parallel_download)
+        .set_max_download_retries(options.max_download_retries)
+        .set_stall_timeout(options.stall_timeout)
+        .set_deadline(options.deadline)
+        .set_transforms(options.transforms)
+        .set_progress(Arc::new(ChapterProgress {
+            chapter_id: chapter_id.clone(),
+            progress: Arc::clone(&progress),
+        }))
+        .set_cancellation_token(cancellation_token);
+    if let Some(page_store) = page_store {
+        archive_download = archive_download.set_page_store(page_store);
+    }
+    let cbz_writer = archive_download.request_with(&client).await;
+
+    match cbz_writer {
+        Ok(cbz_writer) => {
+            progress.on_event(&Event::ChapterDone(chapter_id));
+            Ok(cbz_writer)
+        }
+        Err(err) => {
+            error!("chapter {chapter_id} download failed: {err}");
+            progress.on_event(&Event::ChapterFailed(chapter_id));
+            Err(err)
+        }
+    }
+}
+
+impl Request for BatchArchiveDownload {
+    type Response = Response;
+
+    async fn request_with(self, client: &Client) -> Result<Self::Response> {
+        let len = self.chapter_ids.len();
+
+        self.progress.on_event(&Event::Init(len));
+
+        let options = ChapterOptions {
+            max_parallel_download: self.max_parallel_download,
+            max_download_retries: self.max_download_retries,
+            stall_timeout: self.stall_timeout,
+            deadline: self.chapter_deadline,
+            transforms: self.transforms,
+        };
+        let client = client.clone();
+        let progress = Arc::clone(&self.progress);
+        let page_store = self.page_store;
+        let cancellation_token = self.cancellation_token;
+        let downloads = stream::iter(self.chapter_ids)
+            .map(move |chapter_id| {
+                let client = client.clone();
+                let progress = Arc::clone(&progress);
+                let options = options.clone();
+                let page_store = page_store.clone();
+                let cancellation_token = cancellation_token.child_token();
+                async move {
+                    let cbz_writer = download_chapter(
+                        client,
+                        chapter_id.clone(),
+                        options,
+                        progress,
+                        page_store,
+                        cancellation_token,
+                    )
+                    .await;
+                    (chapter_id, cbz_writer)
+                }
+            })
+            .buffer_unordered(self.max_parallel_chapters.max(1));
+
+        let progress = self.progress;
+        let done = stream::once(async move {
+            progress.on_event(&Event::Done);
+        })
+        .filter_map(|()| future::ready(None));
+
+        Ok(downloads.chain(done).boxed())
+    }
+}
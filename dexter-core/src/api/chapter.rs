@@ -0,0 +1,69 @@
+//! Chapter entity shared by the chapter endpoints
+
+use std::cmp::Ordering;
+
+use serde::Deserialize;
+
+use crate::ChapterNumber;
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+pub struct Attributes {
+    pub volume: Option<String>,
+    pub chapter: Option<String>,
+    pub title: Option<String>,
+    #[serde(rename = "translatedLanguage")]
+    pub translated_language: Option<String>,
+    /// Publication date, in the RFC 3339 format, e.g. `2023-06-01T12:00:00+00:00`
+    #[serde(rename = "publishAt")]
+    pub publish_at: Option<String>,
+    /// Date from which the chapter can be read, in the RFC 3339 format
+    #[serde(rename = "readableAt")]
+    pub readable_at: Option<String>,
+    /// Upload date, in the RFC 3339 format
+    #[serde(rename = "createdAt")]
+    pub created_at: Option<String>,
+    /// Amount of pages hosted on `MangaDex`, `0` for the external chapters
+    pub pages: Option<u32>,
+    /// Link to the chapter on the publisher website, for the chapters not hosted on `MangaDex`
+    #[serde(rename = "externalUrl")]
+    pub external_url: Option<String>,
+}
+
+impl Attributes {
+    /// Whether the pages of the chapter can be downloaded from `MangaDex`
+    #[must_use]
+    pub fn is_hosted(&self) -> bool {
+        self.external_url.is_none() && self.pages != Some(0)
+    }
+
+    #[must_use]
+    pub fn volume_number(&self) -> ChapterNumber {
+        self.volume.as_deref().into()
+    }
+
+    #[must_use]
+    pub fn chapter_number(&self) -> ChapterNumber {
+        self.chapter.as_deref().into()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+pub struct Data {
+    pub id: String,
+    pub attributes: Attributes,
+}
+
+impl Data {
+    /// Compares chapters by volume, and then by chapter number
+    #[must_use]
+    pub fn cmp_by_number(&self, other: &Self) -> Ordering {
+        self.attributes
+            .volume_number()
+            .cmp(&other.attributes.volume_number())
+            .then_with(|| {
+                self.attributes
+                    .chapter_number()
+                    .cmp(&other.attributes.chapter_number())
+            })
+    }
+}
@@ -0,0 +1,109 @@
+use std::{future::Future, time::Duration};
+
+use bytes::{Bytes, BytesMut};
+use reqwest::{header::CONTENT_TYPE, Response};
+use tracing::info;
+
+use crate::{Client, Error, Request, Result};
+
+use super::archive_download::DEFAULT_MAX_DOWNLOAD_RETRIES;
+
+/// Images larger than this are considered broken, and their download is aborted
+pub static DEFAULT_MAX_IMAGE_SIZE: u64 = 20 * 1024 * 1024;
+
+/// Downloads a single image, retrying on transient failures.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DownloadImage {
+    url: String,
+    max_download_retries: u32,
+    max_image_size: u64,
+}
+
+impl DownloadImage {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            max_download_retries: DEFAULT_MAX_DOWNLOAD_RETRIES,
+            max_image_size: DEFAULT_MAX_IMAGE_SIZE,
+        }
+    }
+
+    #[must_use]
+    pub fn set_max_download_retries(mut self, max_download_retries: u32) -> Self {
+        self.max_download_retries = max_download_retries;
+        self
+    }
+
+    /// Size in bytes above which the download is aborted
+    #[must_use]
+    pub fn set_max_image_size(mut self, max_image_size: u64) -> Self {
+        self.max_image_size = max_image_size;
+        self
+    }
+}
+
+impl Request for DownloadImage {
+    type Response = Bytes;
+
+    async fn request_with(self, client: &Client) -> Result<Self::Response> {
+        info!("Downloading {}", self.url);
+
+        let response = client
+            .http_with_retries(self.max_download_retries)
+            .get(self.url)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        read_image_body(response, self.max_image_size, None, |_| {}).await
+    }
+}
+
+/// Reads the image body chunk by chunk, calling `on_chunk` with the size of each chunk.
+///
+/// Fails early if the response is not an image, or if it's larger than `max_size` bytes,
+/// according to its headers or to the amount of bytes actually received,
+/// and fails with [`Error::Stalled`] if no chunk is received for `stall_timeout`.
+pub(crate) async fn read_image_body(
+    mut response: Response,
+    max_size: u64,
+    stall_timeout: Option<Duration>,
+    mut on_chunk: impl FnMut(usize),
+) -> Result<Bytes> {
+    if let Some(content_type) = response.headers().get(CONTENT_TYPE) {
+        let content_type = content_type.to_str().unwrap_or_default();
+        if !content_type.starts_with("image/") {
+            return Err(Error::UnexpectedContentType(content_type.to_string()));
+        }
+    }
+
+    if let Some(content_length) = response.content_length() {
+        if content_length > max_size {
+            return Err(Error::ImageTooLarge(max_size));
+        }
+    }
+
+    let mut body = BytesMut::new();
+    while let Some(chunk) = with_stall_timeout(stall_timeout, response.chunk()).await?? {
+        if (body.len() + chunk.len()) as u64 > max_size {
+            return Err(Error::ImageTooLarge(max_size));
+        }
+        body.extend_from_slice(&chunk);
+        on_chunk(chunk.len());
+    }
+
+    Ok(body.freeze())
+}
+
+/// Fails with [`Error::Stalled`] if the future doesn't complete within `stall_timeout`
+pub(crate) async fn with_stall_timeout<T>(
+    stall_timeout: Option<Duration>,
+    future: impl Future<Output = T>,
+) -> Result<T> {
+    match stall_timeout {
+        Some(stall_timeout) => tokio::time::timeout(stall_timeout, future)
+            .await
+            .map_err(|_| Error::Stalled(stall_timeout)),
+        None => Ok(future.await),
+    }
+}
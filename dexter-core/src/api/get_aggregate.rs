@@ -0,0 +1,144 @@
+use std::collections::BTreeMap;
+
+use serde::{de::IgnoredAny, Deserialize, Deserializer};
+
+use crate::{ChapterNumber, ChapterSelection, Client, Request, Result};
+
+/// `MangaDex` returns an empty array instead of an empty object when a manga, or a volume, has no chapters
+fn map_or_empty_array<'de, D, V>(deserializer: D) -> Result<BTreeMap<String, V>, D::Error>
+where
+    D: Deserializer<'de>,
+    V: Deserialize<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum MapOrArray<V> {
+        Map(BTreeMap<String, V>),
+        Array(#[allow(dead_code)] Vec<IgnoredAny>),
+    }
+
+    Ok(match MapOrArray::deserialize(deserializer)? {
+        MapOrArray::Map(map) => map,
+        MapOrArray::Array(_) => BTreeMap::new(),
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+pub struct Chapter {
+    pub chapter: String,
+    /// Id of one of the uploads of this chapter number
+    pub id: String,
+    /// Ids of the other uploads of this chapter number, e.g. by other groups
+    pub others: Vec<String>,
+    pub count: u32,
+}
+
+impl Chapter {
+    #[must_use]
+    pub fn chapter_number(&self) -> ChapterNumber {
+        ChapterNumber::parse(&self.chapter)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+pub struct Volume {
+    pub volume: String,
+    pub count: u32,
+    #[serde(deserialize_with = "map_or_empty_array")]
+    pub chapters: BTreeMap<String, Chapter>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+pub struct Response {
+    #[serde(deserialize_with = "map_or_empty_array")]
+    pub volumes: BTreeMap<String, Volume>,
+}
+
+impl Response {
+    /// All the chapters of all the volumes
+    pub fn chapters(&self) -> impl Iterator<Item = &Chapter> {
+        self.volumes
+            .values()
+            .flat_map(|volume| volume.chapters.values())
+    }
+
+    /// The chapters in the `selection`, or all of them, sorted by number,
+    /// and only the `latest` numeric ones when set
+    #[must_use]
+    pub fn select(
+        &self,
+        selection: Option<&ChapterSelection>,
+        latest: Option<usize>,
+    ) -> Vec<&Chapter> {
+        let mut chapters = self
+            .chapters()
+            .filter(|chapter| {
+                selection.map_or(true, |selection| {
+                    selection.contains(&chapter.chapter_number())
+                })
+            })
+            .collect::<Vec<_>>();
+        chapters.sort_by_cached_key(|chapter| chapter.chapter_number());
+        if let Some(latest) = latest {
+            chapters.retain(|chapter| chapter.chapter_number().is_numeric());
+            chapters.drain(..chapters.len().saturating_sub(latest));
+        }
+        chapters
+    }
+}
+
+/// Get the volumes and chapter numbers available for the given manga id, optionally restricted to some languages.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct GetAggregate {
+    manga_id: String,
+    languages: Option<Vec<String>>,
+}
+
+impl GetAggregate {
+    pub fn new(manga_id: impl Into<String>) -> Self {
+        Self {
+            manga_id: manga_id.into(),
+            languages: None,
+        }
+    }
+
+    #[must_use]
+    pub fn set_languages(mut self, languages: Option<Vec<String>>) -> Self {
+        self.languages = languages;
+        self
+    }
+
+    #[must_use]
+    pub fn with_languages(
+        mut self,
+        languages: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.languages = Some(languages.into_iter().map(Into::into).collect());
+        self
+    }
+
+    #[must_use]
+    pub fn push_language(mut self, language: impl Into<String>) -> Self {
+        let language = language.into();
+        match &mut self.languages {
+            Some(languages) => languages.push(language),
+            None => self.languages = Some(vec![language]),
+        };
+        self
+    }
+}
+
+impl Request for GetAggregate {
+    type Response = Response;
+
+    async fn request_with(self, client: &Client) -> Result<Self::Response> {
+        let mut url = client.endpoint(&format!("manga/{}/aggregate", self.manga_id))?;
+        if let Some(languages) = &self.languages {
+            for language in languages {
+                url.query_pairs_mut()
+                    .append_pair("translatedLanguage[]", language);
+            }
+        }
+        client.get_json(url, "get_aggregate").await
+    }
+}
@@ -1,24 +1,7 @@
-use async_trait::async_trait;
 use serde::Deserialize;
 
-use crate::{Request, Result};
-
-use super::{base_url, get_json};
-
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
-pub struct Attributes {
-    pub volume: Option<String>,
-    pub chapter: Option<String>,
-    pub title: Option<String>,
-    #[serde(rename = "translatedLanguage")]
-    pub translated_language: Option<String>,
-}
-
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
-pub struct Data {
-    pub id: String,
-    pub attributes: Attributes,
-}
+pub use super::chapter::{Attributes, Data};
+use crate::{Client, Request, Result};
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
 pub struct Response {
@@ -69,13 +52,11 @@ impl GetChapter {
     }
 }
 
-#[async_trait]
 impl Request for GetChapter {
     type Response = Response;
 
-    async fn request(mut self) -> Result<Self::Response> {
-        let mut url = base_url();
-        url.set_path("chapter");
+    async fn request_with(self, client: &Client) -> Result<Self::Response> {
+        let mut url = client.endpoint("chapter")?;
         url.query_pairs_mut()
             .append_pair("manga", &self.manga_id)
             .append_pair("chapter[]", &self.chapter_number);
@@ -86,6 +67,6 @@ impl Request for GetChapter {
         if let Some(volume_number) = &self.volume_number {
             url.query_pairs_mut().append_pair("volume[]", volume_number);
         };
-        get_json(url, "get_chapter").await
+        client.get_json(url, "get_chapter").await
     }
 }
@@ -0,0 +1,68 @@
+use serde::Deserialize;
+
+use crate::{Client, Request, Result};
+
+use super::{get_chapter, get_manga};
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+pub struct Relationship {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub type_: String,
+    /// Only set for the manga, which is included in the response
+    pub attributes: Option<get_manga::Attributes>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+pub struct Data {
+    pub id: String,
+    pub attributes: get_chapter::Attributes,
+    pub relationships: Vec<Relationship>,
+}
+
+impl Data {
+    /// The manga the chapter belongs to
+    #[must_use]
+    pub fn manga(&self) -> Option<&Relationship> {
+        self.relationships
+            .iter()
+            .find(|relationship| relationship.type_ == "manga")
+    }
+
+    #[must_use]
+    pub fn manga_title(&self) -> Option<&str> {
+        self.manga()?
+            .attributes
+            .as_ref()
+            .map(|attributes| attributes.title.en.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+pub struct Response {
+    pub data: Data,
+}
+
+/// Get one chapter given its id, along with the manga it belongs to.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct GetChapterById {
+    chapter_id: String,
+}
+
+impl GetChapterById {
+    pub fn new(chapter_id: impl Into<String>) -> Self {
+        Self {
+            chapter_id: chapter_id.into(),
+        }
+    }
+}
+
+impl Request for GetChapterById {
+    type Response = Response;
+
+    async fn request_with(self, client: &Client) -> Result<Self::Response> {
+        let mut url = client.endpoint(&format!("chapter/{}", self.chapter_id))?;
+        url.query_pairs_mut().append_pair("includes[]", "manga");
+        client.get_json(url, "get_chapter_by_id").await
+    }
+}
@@ -1,27 +1,31 @@
 use std::iter::IntoIterator;
 
-use async_trait::async_trait;
 use serde::Deserialize;
 
-use crate::{Request, Result};
-
-use super::{base_url, get_json};
+pub use super::chapter::{Attributes, Data};
+use crate::{api::Paginated, Client, Request, Result};
 
 pub static DEFAULT_CHAPTERS_LIMIT: u32 = 100;
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
-pub struct Attributes {
-    pub volume: Option<String>,
-    pub chapter: Option<String>,
-    pub title: Option<String>,
-    #[serde(rename = "translatedLanguage")]
-    pub translated_language: Option<String>,
+/// Field the chapters are sorted by, most recent or highest first
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Order {
+    #[default]
+    Chapter,
+    PublishAt,
+    ReadableAt,
+    CreatedAt,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
-pub struct Data {
-    pub id: String,
-    pub attributes: Attributes,
+impl Order {
+    fn query_key(self) -> &'static str {
+        match self {
+            Self::Chapter => "order[chapter]",
+            Self::PublishAt => "order[publishAt]",
+            Self::ReadableAt => "order[readableAt]",
+            Self::CreatedAt => "order[createdAt]",
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
@@ -41,6 +45,8 @@ pub struct GetChapters {
     chapters: Option<Vec<String>>,
     volumes: Option<Vec<String>>,
     languages: Option<Vec<String>>,
+    groups: Option<Vec<String>>,
+    order: Order,
 }
 
 impl GetChapters {
@@ -52,6 +58,8 @@ impl GetChapters {
             chapters: None,
             volumes: None,
             languages: None,
+            groups: None,
+            order: Order::default(),
         }
     }
 
@@ -61,6 +69,12 @@ impl GetChapters {
         self
     }
 
+    #[must_use]
+    pub fn set_order(mut self, order: Order) -> Self {
+        self.order = order;
+        self
+    }
+
     #[must_use]
     pub fn set_offset(mut self, offset: u32) -> Self {
         self.offset = offset;
@@ -135,19 +149,56 @@ impl GetChapters {
         };
         self
     }
+
+    /// Only the chapters uploaded by these scanlation groups are returned, see [`crate::SearchGroups`] to find their ids
+    #[must_use]
+    pub fn set_groups(mut self, groups: Option<Vec<String>>) -> Self {
+        self.groups = groups;
+        self
+    }
+
+    #[must_use]
+    pub fn with_groups(mut self, groups: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.groups = Some(groups.into_iter().map(Into::into).collect());
+        self
+    }
+
+    #[must_use]
+    pub fn push_group(mut self, group: impl Into<String>) -> Self {
+        let group = group.into();
+        match &mut self.groups {
+            Some(groups) => groups.push(group),
+            None => self.groups = Some(vec![group]),
+        };
+        self
+    }
+
+    /// Requests all the chapters, see [`Self::request_all_with`]
+    #[must_use]
+    pub fn request_all(self) -> Paginated<Data> {
+        self.request_all_with(&Client::shared())
+    }
+
+    /// Streams all the chapters starting from the offset, `limit` being the size of each requested page.
+    /// The following pages are requested until the `total` is reached, instead of stopping at the first one.
+    #[must_use]
+    pub fn request_all_with(self, client: &Client) -> Paginated<Data> {
+        let offset = self.offset;
+        Paginated::new(client, self, offset, Self::set_offset, |response| {
+            (response.data, response.total)
+        })
+    }
 }
 
-#[async_trait]
 impl Request for GetChapters {
     type Response = Response;
 
-    async fn request(mut self) -> Result<Self::Response> {
-        let mut url = base_url();
-        url.set_path("chapter");
+    async fn request_with(self, client: &Client) -> Result<Self::Response> {
+        let mut url = client.endpoint("chapter")?;
         url.query_pairs_mut()
             .append_pair("manga", &self.manga_id)
             .append_pair("limit", &self.limit.to_string())
-            .append_pair("order[chapter]", "desc");
+            .append_pair(self.order.query_key(), "desc");
         if self.offset > 0 {
             url.query_pairs_mut()
                 .append_pair("offset", &self.offset.to_string());
@@ -168,6 +219,11 @@ impl Request for GetChapters {
                 url.query_pairs_mut().append_pair("volume[]", volume);
             }
         }
-        get_json(url, "get_chapters").await
+        if let Some(groups) = &self.groups {
+            for group in groups {
+                url.query_pairs_mut().append_pair("groups[]", group);
+            }
+        }
+        client.get_json(url, "get_chapters").await
     }
 }
@@ -1,9 +1,6 @@
-use async_trait::async_trait;
 use serde::Deserialize;
 
-use crate::{Request, Result};
-
-use super::{base_url, get_json};
+use crate::{Client, Request, Result};
 
 // #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
 // pub struct Attributes {
@@ -14,6 +11,9 @@ use super::{base_url, get_json};
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
 struct Chapter {
     data: Vec<String>,
+    /// Compressed versions of the pages, in the same order
+    #[serde(default, rename = "dataSaver")]
+    data_saver: Vec<String>,
     hash: String,
 }
 
@@ -28,6 +28,8 @@ struct ImageLinks {
 pub struct Description {
     pub filename: String,
     pub url: String,
+    /// Url of the compressed version of the page, used when the original is missing
+    pub data_saver_url: Option<String>,
 }
 
 type Response = Vec<Description>;
@@ -46,27 +48,32 @@ impl GetImageLinks {
     }
 }
 
-#[async_trait]
 impl Request for GetImageLinks {
     type Response = Response;
 
-    async fn request(self) -> Result<Response> {
-        let mut url = base_url();
-        url.set_path(&format!("at-home/server/{}", self.chapter_id));
-        let image_links = get_json::<ImageLinks>(url, "get_image_links").await?;
-        Ok(image_links
-            .chapter
+    async fn request_with(self, client: &Client) -> Result<Response> {
+        let url = client.endpoint(&format!("at-home/server/{}", self.chapter_id))?;
+        let image_links = client
+            .get_json::<ImageLinks>(url, "get_image_links")
+            .await?;
+        let ImageLinks { chapter, base_url } = image_links;
+        let mut data_saver = chapter.data_saver.into_iter();
+        Ok(chapter
             .data
             .into_iter()
             .map(|image_filename| {
-                let url = format!(
-                    "{}/data/{}/{image_filename}",
-                    image_links.base_url, image_links.chapter.hash
-                );
+                let url = format!("{base_url}/data/{}/{image_filename}", chapter.hash);
+                let data_saver_url = data_saver.next().map(|data_saver_filename| {
+                    format!(
+                        "{base_url}/data-saver/{}/{data_saver_filename}",
+                        chapter.hash
+                    )
+                });
 
                 Description {
                     filename: image_filename,
                     url,
+                    data_saver_url,
                 }
             })
             .collect())
@@ -1,9 +1,6 @@
-use async_trait::async_trait;
 use serde::Deserialize;
 
-use crate::{Request, Result};
-
-use super::{base_url, get_json};
+use crate::{Client, Request, Result};
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
 pub struct Title {
@@ -40,13 +37,11 @@ impl GetManga {
     }
 }
 
-#[async_trait]
 impl Request for GetManga {
     type Response = Response;
 
-    async fn request(self) -> Result<Self::Response> {
-        let mut url = base_url();
-        url.set_path(&format!("manga/{}", self.manga_id));
-        get_json(url, "get_manga").await
+    async fn request_with(self, client: &Client) -> Result<Self::Response> {
+        let url = client.endpoint(&format!("manga/{}", self.manga_id))?;
+        client.get_json(url, "get_manga").await
     }
 }
@@ -0,0 +1,129 @@
+//! Title creation and edition, only available with the `manga-drafts` feature.
+//!
+//! These endpoints modify the `MangaDex` database and require a [`Client`] with an access token
+//! belonging to a user allowed to edit titles. Created titles are drafts until submitted for review.
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::{Client, Request, Result};
+
+use super::get_manga;
+
+/// Localized strings, indexed by language code (`en`, `ja-ro`, ...)
+pub type LocalizedString = BTreeMap<String, String>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Ongoing,
+    Completed,
+    Hiatus,
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContentRating {
+    Safe,
+    Suggestive,
+    Erotica,
+    Pornographic,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PublicationDemographic {
+    Shounen,
+    Shoujo,
+    Josei,
+    Seinen,
+}
+
+/// The title attributes sent on creation and edition, unset fields are omitted from the body
+#[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MangaDraft {
+    #[serde(skip_serializing_if = "LocalizedString::is_empty")]
+    pub title: LocalizedString,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub alt_titles: Vec<LocalizedString>,
+    #[serde(skip_serializing_if = "LocalizedString::is_empty")]
+    pub description: LocalizedString,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub original_language: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<Status>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_rating: Option<ContentRating>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub publication_demographic: Option<PublicationDemographic>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub year: Option<u16>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+struct UpdateMangaBody<'a> {
+    #[serde(flatten)]
+    draft: &'a MangaDraft,
+    version: u32,
+}
+
+/// Creates a new title draft.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CreateManga {
+    draft: MangaDraft,
+}
+
+impl CreateManga {
+    #[must_use]
+    pub fn new(draft: MangaDraft) -> Self {
+        Self { draft }
+    }
+}
+
+impl Request for CreateManga {
+    type Response = get_manga::Response;
+
+    async fn request_with(self, client: &Client) -> Result<Self::Response> {
+        let url = client.endpoint("manga")?;
+        client.post_json(url, &self.draft, "create_manga").await
+    }
+}
+
+/// Updates the title with the given manga id.
+///
+/// `version` is the current version of the title, the update is rejected if the title
+/// has been edited in the meantime.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct UpdateManga {
+    manga_id: String,
+    draft: MangaDraft,
+    version: u32,
+}
+
+impl UpdateManga {
+    pub fn new(manga_id: impl Into<String>, draft: MangaDraft, version: u32) -> Self {
+        Self {
+            manga_id: manga_id.into(),
+            draft,
+            version,
+        }
+    }
+}
+
+impl Request for UpdateManga {
+    type Response = get_manga::Response;
+
+    async fn request_with(self, client: &Client) -> Result<Self::Response> {
+        let url = client.endpoint(&format!("manga/{}", self.manga_id))?;
+        let body = UpdateMangaBody {
+            draft: &self.draft,
+            version: self.version,
+        };
+        client.put_json(url, &body, "update_manga").await
+    }
+}
@@ -1,53 +1,53 @@
+use std::future::Future;
+
 pub use archive_download::ArchiveDownload;
-use async_trait::async_trait;
+pub use batch_archive_download::BatchArchiveDownload;
+pub use download_image::DownloadImage;
+pub use get_aggregate::GetAggregate;
 pub use get_chapter::GetChapter;
+pub use get_chapter_by_id::GetChapterById;
 pub use get_chapters::GetChapters;
 pub use get_image_links::GetImageLinks;
 pub use get_manga::GetManga;
-use reqwest::header::USER_AGENT;
-use reqwest::IntoUrl;
-use reqwest::Url;
+#[cfg(feature = "manga-drafts")]
+pub use manga_draft::{CreateManga, UpdateManga};
+pub use paginated::Paginated;
+pub use report::{GetReportReasons, ReportContent};
+pub use scanlation_group::{GetScanlationGroup, SearchGroups};
 pub use search::Search;
-use serde::Deserialize;
-use tracing::error;
+pub use upload::{AbandonUploadSession, BeginUploadSession, CommitUploadSession, UploadPages};
 
-use crate::Result;
+use crate::{Client, Result};
 
 pub mod archive_download;
+pub mod batch_archive_download;
+pub mod chapter;
+pub mod download_image;
+pub mod get_aggregate;
 pub mod get_chapter;
+pub mod get_chapter_by_id;
 pub mod get_chapters;
 pub mod get_image_links;
 pub mod get_manga;
+#[cfg(feature = "manga-drafts")]
+pub mod manga_draft;
+pub mod paginated;
+pub mod report;
+pub mod scanlation_group;
 pub mod search;
+pub mod upload;
 
-static FAKE_USER_AGENT: &str = "user agent";
-
-/// Returns the base mangadex url
-pub(super) fn base_url() -> Url {
-    "https://api.mangadex.org/".parse().unwrap()
-}
-
-/// Send a get request to `url` and decode the json response as `T`
-pub(super) async fn get_json<T: for<'de> Deserialize<'de>>(
-    url: impl IntoUrl,
-    context: &str,
-) -> Result<T> {
-    reqwest::Client::new()
-        .get(url)
-        .header(USER_AGENT, FAKE_USER_AGENT)
-        .send()
-        .await?
-        .json()
-        .await
-        .map_err(|err| {
-            error!("error decoding {context}: {err}");
-            err.into()
-        })
-}
-
-#[async_trait]
 pub trait Request {
     type Response;
 
-    async fn request(self) -> Result<Self::Response>;
+    /// Sends the request using the provided `client`
+    fn request_with(self, client: &Client) -> impl Future<Output = Result<Self::Response>> + Send;
+
+    /// Sends the request using the [`Client::shared`] client
+    fn request(self) -> impl Future<Output = Result<Self::Response>> + Send
+    where
+        Self: Sized + Send,
+    {
+        async move { self.request_with(&Client::shared()).await }
+    }
 }
@@ -0,0 +1,87 @@
+use std::{
+    fmt::{self, Debug},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::{stream, stream::BoxStream, Stream, StreamExt, TryStreamExt};
+use tokio::time::sleep;
+
+use crate::{client::DEFAULT_MIN_REQUEST_INTERVAL, Client, Request, Result};
+
+/// Stream of all the items of a paginated endpoint.
+///
+/// The pages are requested lazily, one after the other and spaced by at least
+/// [`DEFAULT_MIN_REQUEST_INTERVAL`], until the `total` announced by the api is reached.
+/// Use [`TryStreamExt::try_collect`] to get all the items at once.
+pub struct Paginated<T> {
+    inner: BoxStream<'static, Result<T>>,
+}
+
+impl<T: Send + 'static> Paginated<T> {
+    /// Requests the pages of `request` starting from `offset`, `set_offset` moves the request to the next page
+    /// and `page` splits a response into its items and the total number of items
+    pub(crate) fn new<R>(
+        client: &Client,
+        request: R,
+        offset: u32,
+        set_offset: fn(R, u32) -> R,
+        page: fn(R::Response) -> (Vec<T>, u32),
+    ) -> Self
+    where
+        R: Request + Clone + Send + Sync + 'static,
+    {
+        let client = client.clone();
+        let pages = stream::try_unfold(Some(offset), move |current| {
+            let client = client.clone();
+            let request = request.clone();
+            async move {
+                let Some(current) = current else {
+                    return Ok(None);
+                };
+                if current > offset {
+                    sleep(DEFAULT_MIN_REQUEST_INTERVAL).await;
+                }
+                request_page(&client, request, current, set_offset, page)
+                    .await
+                    .map(|(items, next)| Some((stream::iter(items.into_iter().map(Ok)), next)))
+            }
+        });
+
+        Self {
+            inner: pages.try_flatten().boxed(),
+        }
+    }
+}
+
+/// Returns the items of the page at `offset`, and the offset of the next page if any
+async fn request_page<R, T>(
+    client: &Client,
+    request: R,
+    offset: u32,
+    set_offset: fn(R, u32) -> R,
+    page: fn(R::Response) -> (Vec<T>, u32),
+) -> Result<(Vec<T>, Option<u32>)>
+where
+    R: Request,
+{
+    let (items, total) = page(set_offset(request, offset).request_with(client).await?);
+    let next = offset.saturating_add(u32::try_from(items.len()).unwrap_or(u32::MAX));
+    // An empty page would otherwise be requested again and again
+    let next = (!items.is_empty() && next < total).then_some(next);
+    Ok((items, next))
+}
+
+impl<T> Stream for Paginated<T> {
+    type Item = Result<T>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.poll_next_unpin(cx)
+    }
+}
+
+impl<T> Debug for Paginated<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Paginated").finish_non_exhaustive()
+    }
+}
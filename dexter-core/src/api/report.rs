@@ -0,0 +1,133 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{Client, Request, Result};
+
+/// The kind of content being reported
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Category {
+    Manga,
+    Chapter,
+    ScanlationGroup,
+    User,
+    Author,
+}
+
+impl Category {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Manga => "manga",
+            Self::Chapter => "chapter",
+            Self::ScanlationGroup => "scanlation_group",
+            Self::User => "user",
+            Self::Author => "author",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+pub struct ReasonTitle {
+    pub en: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReasonAttributes {
+    pub reason: ReasonTitle,
+    pub details_required: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+pub struct Reason {
+    pub id: String,
+    pub attributes: ReasonAttributes,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+pub struct GetReportReasonsResponse {
+    pub data: Vec<Reason>,
+}
+
+/// Lists the reasons content of the given category can be reported for.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct GetReportReasons {
+    category: Category,
+}
+
+impl GetReportReasons {
+    #[must_use]
+    pub fn new(category: Category) -> Self {
+        Self { category }
+    }
+}
+
+impl Request for GetReportReasons {
+    type Response = GetReportReasonsResponse;
+
+    async fn request_with(self, client: &Client) -> Result<Self::Response> {
+        let url = client.endpoint(&format!("report/reasons/{}", self.category.as_str()))?;
+        client.get_json(url, "get_report_reasons").await
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ReportContentBody<'a> {
+    category: Category,
+    reason: &'a str,
+    object_id: &'a str,
+    details: &'a str,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+pub struct ReportContentResponse {
+    pub result: String,
+}
+
+/// Reports a manga, chapter, group, user, or author to the moderators.
+///
+/// The reason id is one of the reasons returned by [`GetReportReasons`] for the same category,
+/// some of them require `details` to be set. Reporting requires a [`Client`] with an access token.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ReportContent {
+    category: Category,
+    object_id: String,
+    reason_id: String,
+    details: String,
+}
+
+impl ReportContent {
+    pub fn new(
+        category: Category,
+        object_id: impl Into<String>,
+        reason_id: impl Into<String>,
+    ) -> Self {
+        Self {
+            category,
+            object_id: object_id.into(),
+            reason_id: reason_id.into(),
+            details: String::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn set_details(mut self, details: impl Into<String>) -> Self {
+        self.details = details.into();
+        self
+    }
+}
+
+impl Request for ReportContent {
+    type Response = ReportContentResponse;
+
+    async fn request_with(self, client: &Client) -> Result<Self::Response> {
+        let url = client.endpoint("report")?;
+        let body = ReportContentBody {
+            category: self.category,
+            reason: &self.reason_id,
+            object_id: &self.object_id,
+            details: &self.details,
+        };
+        client.post_json(url, &body, "report_content").await
+    }
+}
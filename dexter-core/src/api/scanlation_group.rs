@@ -0,0 +1,98 @@
+use serde::Deserialize;
+
+use crate::{api::search::LocalizedString, Client, Request, Result};
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+pub struct Attributes {
+    pub name: String,
+    #[serde(default, rename = "altNames")]
+    pub alt_names: Vec<LocalizedString>,
+    pub website: Option<String>,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+pub struct Data {
+    pub id: String,
+    pub attributes: Attributes,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+pub struct Response {
+    pub data: Data,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+pub struct SearchResponse {
+    pub limit: u32,
+    pub offset: u32,
+    pub total: u32,
+    pub data: Vec<Data>,
+}
+
+/// Get scanlation group information for the given group id.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct GetScanlationGroup {
+    group_id: String,
+}
+
+impl GetScanlationGroup {
+    pub fn new(group_id: impl Into<String>) -> Self {
+        Self {
+            group_id: group_id.into(),
+        }
+    }
+}
+
+impl Request for GetScanlationGroup {
+    type Response = Response;
+
+    async fn request_with(self, client: &Client) -> Result<Self::Response> {
+        let url = client.endpoint(&format!("group/{}", self.group_id))?;
+        client.get_json(url, "get_scanlation_group").await
+    }
+}
+
+/// Search for scanlation groups by name, e.g. to resolve the group ids used by [`crate::GetChapters::with_groups`]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SearchGroups {
+    name: String,
+    limit: Option<u32>,
+}
+
+impl SearchGroups {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            limit: None,
+        }
+    }
+
+    #[must_use]
+    pub fn set_limit(mut self, limit: Option<u32>) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    #[must_use]
+    pub fn with_limit(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+}
+
+impl Request for SearchGroups {
+    type Response = SearchResponse;
+
+    async fn request_with(self, client: &Client) -> Result<Self::Response> {
+        let mut url = client.endpoint("group")?;
+        url.query_pairs_mut()
+            .append_pair("name", &self.name)
+            .append_pair("order[relevance]", "desc");
+        if let Some(limit) = self.limit {
+            url.query_pairs_mut()
+                .append_pair("limit", &limit.to_string());
+        }
+        client.get_json(url, "search_groups").await
+    }
+}
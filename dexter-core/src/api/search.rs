@@ -1,18 +1,67 @@
-use async_trait::async_trait;
+use std::collections::BTreeMap;
+
 use serde::Deserialize;
 
-use crate::{Request, Result};
+use crate::{Client, Request, Result};
 
-use super::{base_url, get_json};
+/// Localized strings, indexed by language code (`en`, `ja-ro`, ...)
+pub type LocalizedString = BTreeMap<String, String>;
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
 pub struct Title {
     pub en: String,
+    /// The main title is sometimes provided in another language than english
+    #[serde(flatten)]
+    pub others: LocalizedString,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
 pub struct Attributes {
     pub title: Title,
+    #[serde(default, rename = "altTitles")]
+    pub alt_titles: Vec<LocalizedString>,
+    #[serde(default)]
+    pub description: LocalizedString,
+    pub year: Option<u32>,
+    pub status: Option<String>,
+    #[serde(rename = "contentRating")]
+    pub content_rating: Option<String>,
+}
+
+impl Attributes {
+    /// Returns the title in the first of the `languages` it's available in,
+    /// looking at the main title and then at the alternative titles, and falling back to the english title
+    #[must_use]
+    pub fn localized_title(&self, languages: &[impl AsRef<str>]) -> &str {
+        languages
+            .iter()
+            .find_map(|language| {
+                let language = language.as_ref();
+                if language == "en" {
+                    return Some(self.title.en.as_str());
+                }
+                self.title
+                    .others
+                    .get(language)
+                    .or_else(|| {
+                        self.alt_titles
+                            .iter()
+                            .find_map(|alt_title| alt_title.get(language))
+                    })
+                    .map(String::as_str)
+            })
+            .unwrap_or(&self.title.en)
+    }
+
+    /// Returns the description in the first of the `languages` it's available in, falling back to the english one
+    #[must_use]
+    pub fn localized_description(&self, languages: &[impl AsRef<str>]) -> Option<&str> {
+        languages
+            .iter()
+            .find_map(|language| self.description.get(language.as_ref()))
+            .or_else(|| self.description.get("en"))
+            .map(String::as_str)
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
@@ -54,13 +103,11 @@ impl Search {
     }
 }
 
-#[async_trait]
 impl Request for Search {
     type Response = Response;
 
-    async fn request(self) -> Result<Self::Response> {
-        let mut url = base_url();
-        url.set_path("manga");
+    async fn request_with(self, client: &Client) -> Result<Self::Response> {
+        let mut url = client.endpoint("manga")?;
         url.query_pairs_mut()
             .append_pair("title", &self.title)
             .append_pair("order[relevance]", "desc");
@@ -68,6 +115,6 @@ impl Request for Search {
             url.query_pairs_mut()
                 .append_pair("limit", &limit.to_string());
         }
-        get_json(url, "search").await
+        client.get_json(url, "search").await
     }
 }
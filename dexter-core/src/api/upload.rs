@@ -0,0 +1,231 @@
+use reqwest::multipart::{Form, Part};
+use serde::{Deserialize, Serialize};
+
+use crate::{Client, Request, Result};
+
+/// Max amount of files accepted by the api in one upload request
+pub static MAX_PAGES_PER_UPLOAD: usize = 10;
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+pub struct Session {
+    pub id: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+pub struct SessionResponse {
+    pub data: Session,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+struct BeginUploadSessionBody<'a> {
+    groups: &'a [String],
+    manga: &'a str,
+}
+
+/// Begins an upload session for a new chapter of the given manga id.
+/// The pages are then sent with [`UploadPages`], and the chapter is created with [`CommitUploadSession`].
+///
+/// All the upload endpoints require a [`Client`] with an access token.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BeginUploadSession {
+    manga_id: String,
+    groups: Vec<String>,
+}
+
+impl BeginUploadSession {
+    pub fn new(manga_id: impl Into<String>) -> Self {
+        Self {
+            manga_id: manga_id.into(),
+            groups: Vec::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn with_groups(mut self, groups: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.groups = groups.into_iter().map(Into::into).collect();
+        self
+    }
+
+    #[must_use]
+    pub fn push_group(mut self, group: impl Into<String>) -> Self {
+        self.groups.push(group.into());
+        self
+    }
+}
+
+impl Request for BeginUploadSession {
+    type Response = SessionResponse;
+
+    async fn request_with(self, client: &Client) -> Result<Self::Response> {
+        let url = client.endpoint("upload/begin")?;
+        let body = BeginUploadSessionBody {
+            groups: &self.groups,
+            manga: &self.manga_id,
+        };
+        client.post_json(url, &body, "begin_upload_session").await
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+pub struct UploadedPageAttributes {
+    #[serde(rename = "originalFileName")]
+    pub original_file_name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+pub struct UploadedPage {
+    pub id: String,
+    pub attributes: UploadedPageAttributes,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+pub struct UploadPagesResponse {
+    pub data: Vec<UploadedPage>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Page {
+    pub filename: String,
+    pub bytes: Vec<u8>,
+}
+
+/// Uploads pages to an upload session, at most [`MAX_PAGES_PER_UPLOAD`] at once.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct UploadPages {
+    session_id: String,
+    pages: Vec<Page>,
+}
+
+impl UploadPages {
+    pub fn new(session_id: impl Into<String>) -> Self {
+        Self {
+            session_id: session_id.into(),
+            pages: Vec::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn with_pages(mut self, pages: impl IntoIterator<Item = Page>) -> Self {
+        self.pages = pages.into_iter().collect();
+        self
+    }
+
+    #[must_use]
+    pub fn push_page(mut self, filename: impl Into<String>, bytes: impl Into<Vec<u8>>) -> Self {
+        self.pages.push(Page {
+            filename: filename.into(),
+            bytes: bytes.into(),
+        });
+        self
+    }
+}
+
+impl Request for UploadPages {
+    type Response = UploadPagesResponse;
+
+    async fn request_with(self, client: &Client) -> Result<Self::Response> {
+        let url = client.endpoint(&format!("upload/{}", self.session_id))?;
+        let form = self
+            .pages
+            .into_iter()
+            .enumerate()
+            .fold(Form::new(), |form, (index, page)| {
+                form.part(
+                    format!("file{}", index + 1),
+                    Part::bytes(page.bytes).file_name(page.filename),
+                )
+            });
+        client.post_multipart(url, form, "upload_pages").await
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub struct ChapterDraft {
+    pub volume: Option<String>,
+    pub chapter: Option<String>,
+    pub title: Option<String>,
+    #[serde(rename = "translatedLanguage")]
+    pub translated_language: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+struct CommitUploadSessionBody<'a> {
+    #[serde(rename = "chapterDraft")]
+    chapter_draft: &'a ChapterDraft,
+    #[serde(rename = "pageOrder")]
+    page_order: &'a [String],
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+pub struct CommittedChapter {
+    pub id: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+pub struct CommitUploadSessionResponse {
+    pub data: CommittedChapter,
+}
+
+/// Commits an upload session, creating the chapter with the uploaded pages in the given order.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CommitUploadSession {
+    session_id: String,
+    chapter_draft: ChapterDraft,
+    page_order: Vec<String>,
+}
+
+impl CommitUploadSession {
+    /// `page_order` contains the ids of the uploaded pages, as returned by [`UploadPages`]
+    pub fn new(
+        session_id: impl Into<String>,
+        chapter_draft: ChapterDraft,
+        page_order: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        Self {
+            session_id: session_id.into(),
+            chapter_draft,
+            page_order: page_order.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl Request for CommitUploadSession {
+    type Response = CommitUploadSessionResponse;
+
+    async fn request_with(self, client: &Client) -> Result<Self::Response> {
+        let url = client.endpoint(&format!("upload/{}/commit", self.session_id))?;
+        let body = CommitUploadSessionBody {
+            chapter_draft: &self.chapter_draft,
+            page_order: &self.page_order,
+        };
+        client.post_json(url, &body, "commit_upload_session").await
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+pub struct AbandonUploadSessionResponse {
+    pub result: String,
+}
+
+/// Abandons an upload session, discarding all the uploaded pages.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AbandonUploadSession {
+    session_id: String,
+}
+
+impl AbandonUploadSession {
+    pub fn new(session_id: impl Into<String>) -> Self {
+        Self {
+            session_id: session_id.into(),
+        }
+    }
+}
+
+impl Request for AbandonUploadSession {
+    type Response = AbandonUploadSessionResponse;
+
+    async fn request_with(self, client: &Client) -> Result<Self::Response> {
+        let url = client.endpoint(&format!("upload/{}", self.session_id))?;
+        client.delete_json(url, "abandon_upload_session").await
+    }
+}
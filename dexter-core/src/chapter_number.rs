@@ -0,0 +1,94 @@
+use std::{cmp::Ordering, collections::BTreeSet, convert::Infallible, fmt::Display, str::FromStr};
+
+/// A parsed chapter (or volume) number.
+///
+/// `MangaDex` returns chapter numbers as free form strings (`"10"`, `"10.5"`, `"Extra"`, or nothing at all),
+/// which don't sort properly as strings. Numeric values are ordered as decimals (`2 < 10 < 10.05 < 10.5 < 11`),
+/// then come extras ordered alphabetically, and finally missing numbers.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ChapterNumber {
+    /// Whole part, and decimal digits without their trailing zeros, e.g. `(10, "05")` for `10.050`.
+    /// Comparing the digits as strings then orders the numbers as decimals.
+    Numeric(u64, String),
+    Extra(String),
+    Missing,
+}
+
+impl ChapterNumber {
+    #[must_use]
+    pub fn parse(value: &str) -> Self {
+        let value = value.trim();
+        if value.is_empty() {
+            return Self::Missing;
+        }
+        let (whole, fraction) = value.split_once('.').unwrap_or((value, ""));
+        match whole.parse() {
+            Ok(whole) if fraction.chars().all(|c| c.is_ascii_digit()) => {
+                Self::Numeric(whole, fraction.trim_end_matches('0').to_string())
+            }
+            _ => Self::Extra(value.to_string()),
+        }
+    }
+
+    #[must_use]
+    pub fn is_numeric(&self) -> bool {
+        matches!(self, Self::Numeric(..))
+    }
+
+    #[must_use]
+    pub fn is_extra(&self) -> bool {
+        matches!(self, Self::Extra(_))
+    }
+
+    #[must_use]
+    pub fn is_missing(&self) -> bool {
+        matches!(self, Self::Missing)
+    }
+
+    /// Returns the whole chapter numbers missing between 1 and the highest numeric chapter.
+    ///
+    /// A decimal chapter (e.g. `10.5`) doesn't fill the gap of its whole number, extras and missing numbers are ignored.
+    #[must_use]
+    pub fn gaps<'a>(numbers: impl IntoIterator<Item = &'a Self>) -> Vec<u64> {
+        let wholes = numbers
+            .into_iter()
+            .filter_map(|number| match number {
+                Self::Numeric(whole, fraction) if fraction.is_empty() => Some(*whole),
+                _ => None,
+            })
+            .collect::<BTreeSet<_>>();
+        let last = wholes.last().copied().unwrap_or_default();
+        (1..last).filter(|whole| !wholes.contains(whole)).collect()
+    }
+
+    /// Compares two raw chapter numbers as returned by the api
+    #[must_use]
+    pub fn cmp_raw(a: Option<&str>, b: Option<&str>) -> Ordering {
+        Self::from(a).cmp(&Self::from(b))
+    }
+}
+
+impl FromStr for ChapterNumber {
+    type Err = Infallible;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Ok(Self::parse(value))
+    }
+}
+
+impl From<Option<&str>> for ChapterNumber {
+    fn from(value: Option<&str>) -> Self {
+        value.map_or(Self::Missing, Self::parse)
+    }
+}
+
+impl Display for ChapterNumber {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Numeric(whole, fraction) if fraction.is_empty() => write!(f, "{whole}"),
+            Self::Numeric(whole, fraction) => write!(f, "{whole}.{fraction}"),
+            Self::Extra(value) => write!(f, "{value}"),
+            Self::Missing => write!(f, "-"),
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use std::str::FromStr;
+
+use crate::{ChapterNumber, Error};
+
+/// An inclusive range of chapter numbers, unbounded when a bound is missing
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ChapterRange {
+    pub start: Option<ChapterNumber>,
+    pub end: Option<ChapterNumber>,
+}
+
+impl ChapterRange {
+    /// Only numeric chapters are part of a range, extras and missing numbers never are
+    #[must_use]
+    pub fn contains(&self, number: &ChapterNumber) -> bool {
+        number.is_numeric()
+            && self.start.as_ref().map_or(true, |start| start <= number)
+            && self.end.as_ref().map_or(true, |end| number <= end)
+    }
+}
+
+/// Chapters picked by number, parsed from a comma separated list of numbers and ranges,
+/// e.g. `1-20,45,60-` for the chapters 1 to 20, the chapter 45, and the chapters from 60 onwards
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ChapterSelection(pub Vec<ChapterRange>);
+
+impl ChapterSelection {
+    #[must_use]
+    pub fn contains(&self, number: &ChapterNumber) -> bool {
+        self.0.iter().any(|range| range.contains(number))
+    }
+}
+
+/// Parses a bound of a range, `None` if empty
+fn parse_bound(bound: &str, part: &str) -> Result<Option<ChapterNumber>, Error> {
+    if bound.trim().is_empty() {
+        return Ok(None);
+    }
+    let number = ChapterNumber::parse(bound);
+    if !number.is_numeric() {
+        return Err(Error::InvalidChapterSelection(part.to_string()));
+    }
+    Ok(Some(number))
+}
+
+impl FromStr for ChapterSelection {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        value
+            .split(',')
+            .map(|part| {
+                let range = if let Some((start, end)) = part.split_once('-') {
+                    ChapterRange {
+                        start: parse_bound(start, part)?,
+                        end: parse_bound(end, part)?,
+                    }
+                } else {
+                    let number = parse_bound(part, part)?;
+                    ChapterRange {
+                        start: number.clone(),
+                        end: number,
+                    }
+                };
+                if range.start.is_none() && range.end.is_none() {
+                    return Err(Error::InvalidChapterSelection(part.to_string()));
+                }
+                Ok(range)
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
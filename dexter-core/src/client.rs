@@ -0,0 +1,507 @@
+use std::{
+    fmt::{self, Debug},
+    sync::{Arc, OnceLock},
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use camino::Utf8Path;
+use reqwest::{
+    header::{HeaderValue, USER_AGENT},
+    multipart::Form,
+    IntoUrl, Request, Response,
+};
+use reqwest_middleware::{ClientBuilder, ClientWithMiddleware, Middleware, Next, RequestBuilder};
+use reqwest_retry::{policies::ExponentialBackoff, RetryTransientMiddleware};
+use serde::{Deserialize, Serialize};
+use task_local_extensions::Extensions;
+use tokio::{sync::Mutex, time::sleep_until};
+use tracing::{error, info, warn};
+use url::Url;
+
+use crate::{
+    api::{archive_download, get_chapters, get_manga, search},
+    link, naming,
+    output::Decision,
+    progress::ProgressSink,
+    write_atomically_with_comic_info, ArchiveDownload, ComicInfo, GetChapters, GetManga, IfExists,
+    Request as _, Result, Search,
+};
+
+/// User agent identifying dexter, some MD@Home nodes reject requests without a meaningful one
+pub static DEFAULT_USER_AGENT: &str = concat!(
+    "dexter/",
+    env!("CARGO_PKG_VERSION"),
+    " (+https://github.com/gaku-sei/dexter)"
+);
+
+/// Root of the `MangaDex` api, used unless overridden with [`Client::with_api_url`] or [`API_URL_ENV`]
+pub static DEFAULT_API_URL: &str = "https://api.mangadex.org/";
+
+/// Environment variable overriding the api root of the clients, for staging environments, mirrors, or test servers
+pub static API_URL_ENV: &str = "DEXTER_API_URL";
+
+/// Time after which an api request is aborted, unless overridden with [`Client::with_request_timeout`] or [`REQUEST_TIMEOUT_ENV`]
+pub static DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Environment variable overriding the api request timeout of the clients, in seconds, `0` disabling it
+pub static REQUEST_TIMEOUT_ENV: &str = "DEXTER_REQUEST_TIMEOUT";
+
+/// Minimum time between two api requests sent by a [`Dexter`], staying below the `MangaDex` rate limit
+pub static DEFAULT_MIN_REQUEST_INTERVAL: Duration = Duration::from_millis(250);
+
+static SHARED_CLIENT: OnceLock<Client> = OnceLock::new();
+
+/// Http client used to send the requests, cheap to clone as the connection pool is shared between clones.
+///
+/// Middlewares can be attached to intercept all the requests, including image downloads,
+/// which is how [`crate::mock::FixtureMiddleware`] answers requests without reaching the network.
+///
+/// All the endpoints, including uploads and reports, are resolved against the api url,
+/// which defaults to the value of [`API_URL_ENV`] if set, or [`DEFAULT_API_URL`] otherwise.
+///
+/// Api requests time out after [`DEFAULT_REQUEST_TIMEOUT`], page uploads and image downloads are not concerned
+/// as they can legitimately be slow, see [`crate::ArchiveDownload::set_stall_timeout`] for the latter.
+#[derive(Clone)]
+pub struct Client {
+    inner: reqwest::Client,
+    middlewares: Vec<Arc<dyn Middleware>>,
+    access_token: Option<String>,
+    api_url: Url,
+    user_agent: HeaderValue,
+    request_timeout: Option<Duration>,
+}
+
+/// Reads the api url from the environment, falling back to the default one if unset or invalid
+fn default_api_url() -> Url {
+    if let Ok(api_url) = std::env::var(API_URL_ENV) {
+        match api_url.parse() {
+            Ok(api_url) => return with_trailing_slash(api_url),
+            Err(err) => warn!("ignoring invalid {API_URL_ENV} {api_url}: {err}"),
+        }
+    }
+    DEFAULT_API_URL.parse().unwrap()
+}
+
+/// Reads the request timeout from the environment, falling back to the default one if unset or invalid
+fn default_request_timeout() -> Option<Duration> {
+    if let Ok(request_timeout) = std::env::var(REQUEST_TIMEOUT_ENV) {
+        match request_timeout.parse() {
+            Ok(0) => return None,
+            Ok(secs) => return Some(Duration::from_secs(secs)),
+            Err(err) => warn!("ignoring invalid {REQUEST_TIMEOUT_ENV} {request_timeout}: {err}"),
+        }
+    }
+    Some(DEFAULT_REQUEST_TIMEOUT)
+}
+
+/// Endpoints are joined to the api url, which would drop its last path segment without a trailing slash
+fn with_trailing_slash(mut url: Url) -> Url {
+    if !url.path().ends_with('/') {
+        url.set_path(&format!("{}/", url.path()));
+    }
+    url
+}
+
+impl Client {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the client shared by the whole process, used by [`crate::Request::request`]
+    #[must_use]
+    pub fn shared() -> Self {
+        SHARED_CLIENT.get_or_init(Self::default).clone()
+    }
+
+    #[must_use]
+    pub fn inner(&self) -> &reqwest::Client {
+        &self.inner
+    }
+
+    /// Sets the token sent to the api, required by the endpoints acting on behalf of a user (uploads, ...)
+    #[must_use]
+    pub fn with_access_token(mut self, access_token: impl Into<String>) -> Self {
+        self.access_token = Some(access_token.into());
+        self
+    }
+
+    /// Sets the root of the api, e.g. `https://staging.example.com/api/`
+    #[must_use]
+    pub fn with_api_url(mut self, api_url: Url) -> Self {
+        self.api_url = with_trailing_slash(api_url);
+        self
+    }
+
+    #[must_use]
+    pub fn api_url(&self) -> &Url {
+        &self.api_url
+    }
+
+    /// Overrides the user agent sent with all the requests, meant for applications embedding dexter-core
+    ///
+    /// # Errors
+    ///
+    /// Fails if the user agent is not a valid header value
+    pub fn with_user_agent(mut self, user_agent: impl AsRef<str>) -> Result<Self> {
+        self.user_agent = HeaderValue::from_str(user_agent.as_ref())?;
+        Ok(self)
+    }
+
+    /// Sets the time after which an api request is aborted, `None` waiting forever
+    #[must_use]
+    pub fn with_request_timeout(mut self, request_timeout: Option<Duration>) -> Self {
+        self.request_timeout = request_timeout;
+        self
+    }
+
+    #[must_use]
+    pub fn with_middleware(mut self, middleware: impl Middleware) -> Self {
+        self.middlewares.push(Arc::new(middleware));
+        self
+    }
+
+    /// Resolves the endpoint `path` against the api url
+    pub(crate) fn endpoint(&self, path: &str) -> Result<Url> {
+        Ok(self.api_url.join(path)?)
+    }
+
+    /// Builds an http client running the attached middlewares
+    pub(crate) fn http(&self) -> ClientWithMiddleware {
+        self.build_http(ClientBuilder::new(self.inner.clone()))
+    }
+
+    /// Builds an http client retrying transient failures, before running the attached middlewares
+    pub(crate) fn http_with_retries(&self, max_retries: u32) -> ClientWithMiddleware {
+        let retry_policy = ExponentialBackoff::builder().build_with_max_retries(max_retries);
+        self.build_http(
+            ClientBuilder::new(self.inner.clone())
+                .with(RetryTransientMiddleware::new_with_policy(retry_policy)),
+        )
+    }
+
+    /// Sets the user agent on all the requests, then runs the attached middlewares
+    fn build_http(&self, builder: ClientBuilder) -> ClientWithMiddleware {
+        self.middlewares
+            .iter()
+            .fold(
+                builder.with(UserAgentMiddleware(self.user_agent.clone())),
+                |builder, middleware| builder.with_arc(Arc::clone(middleware)),
+            )
+            .build()
+    }
+
+    /// Send a get request to `url` and decode the json response as `T`
+    pub(crate) async fn get_json<T: for<'de> Deserialize<'de>>(
+        &self,
+        url: impl IntoUrl,
+        context: &str,
+    ) -> Result<T> {
+        self.send_json(self.http().get(url), context).await
+    }
+
+    /// Send a post request with a json `body` to `url` and decode the json response as `T`
+    pub(crate) async fn post_json<B: Serialize + Sync, T: for<'de> Deserialize<'de>>(
+        &self,
+        url: impl IntoUrl,
+        body: &B,
+        context: &str,
+    ) -> Result<T> {
+        self.send_json(self.http().post(url).json(body), context)
+            .await
+    }
+
+    /// Send a put request with a json `body` to `url` and decode the json response as `T`
+    #[cfg(feature = "manga-drafts")]
+    pub(crate) async fn put_json<B: Serialize + Sync, T: for<'de> Deserialize<'de>>(
+        &self,
+        url: impl IntoUrl,
+        body: &B,
+        context: &str,
+    ) -> Result<T> {
+        self.send_json(self.http().put(url).json(body), context)
+            .await
+    }
+
+    /// Send a post request with a multipart `form` to `url` and decode the json response as `T`.
+    ///
+    /// The request timeout doesn't apply, as uploading the pages of a chapter can take longer on slow connections.
+    pub(crate) async fn post_multipart<T: for<'de> Deserialize<'de>>(
+        &self,
+        url: impl IntoUrl,
+        form: Form,
+        context: &str,
+    ) -> Result<T> {
+        self.send_json_with_timeout(self.http().post(url).multipart(form), None, context)
+            .await
+    }
+
+    /// Send a delete request to `url` and decode the json response as `T`
+    pub(crate) async fn delete_json<T: for<'de> Deserialize<'de>>(
+        &self,
+        url: impl IntoUrl,
+        context: &str,
+    ) -> Result<T> {
+        self.send_json(self.http().delete(url), context).await
+    }
+
+    async fn send_json<T: for<'de> Deserialize<'de>>(
+        &self,
+        request: RequestBuilder,
+        context: &str,
+    ) -> Result<T> {
+        self.send_json_with_timeout(request, self.request_timeout, context)
+            .await
+    }
+
+    async fn send_json_with_timeout<T: for<'de> Deserialize<'de>>(
+        &self,
+        request: RequestBuilder,
+        request_timeout: Option<Duration>,
+        context: &str,
+    ) -> Result<T> {
+        let request = match &self.access_token {
+            Some(access_token) => request.bearer_auth(access_token),
+            None => request,
+        };
+        let request = match request_timeout {
+            Some(request_timeout) => request.timeout(request_timeout),
+            None => request,
+        };
+        let response = request.send().await?;
+        let status = response.status();
+        let response = response
+            .error_for_status()
+            .inspect_err(|_| error!("error requesting {context}: {status}"))?;
+        response.json().await.map_err(|err| {
+            error!("error decoding {context}: {err}");
+            err.into()
+        })
+    }
+}
+
+impl Debug for Client {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Client")
+            .field("inner", &self.inner)
+            .field("middlewares", &self.middlewares.len())
+            .field("access_token", &self.access_token.as_ref().map(|_| "***"))
+            .field("api_url", &self.api_url.as_str())
+            .field("user_agent", &self.user_agent)
+            .field("request_timeout", &self.request_timeout)
+            .finish()
+    }
+}
+
+impl Default for Client {
+    fn default() -> Self {
+        reqwest::Client::default().into()
+    }
+}
+
+impl From<reqwest::Client> for Client {
+    fn from(inner: reqwest::Client) -> Self {
+        Self {
+            inner,
+            middlewares: Vec::new(),
+            access_token: None,
+            api_url: default_api_url(),
+            user_agent: HeaderValue::from_static(DEFAULT_USER_AGENT),
+            request_timeout: default_request_timeout(),
+        }
+    }
+}
+
+/// Sets the user agent of the requests, unless already set by the caller
+struct UserAgentMiddleware(HeaderValue);
+
+#[async_trait]
+impl Middleware for UserAgentMiddleware {
+    async fn handle(
+        &self,
+        mut req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        req.headers_mut()
+            .entry(USER_AGENT)
+            .or_insert_with(|| self.0.clone());
+        next.run(req, extensions).await
+    }
+}
+
+/// High level entry point for applications, wrapping a [`Client`] and the requests they usually chain.
+///
+/// Api requests are spaced by at least [`DEFAULT_MIN_REQUEST_INTERVAL`], shared between clones,
+/// ids can be given as `MangaDex` links, and all the failures are returned instead of panicking.
+/// Authentication is provided by the client, see [`Client::with_access_token`].
+#[derive(Debug, Clone)]
+pub struct Dexter {
+    client: Client,
+    max_download_retries: u32,
+    if_exists: IfExists,
+    min_request_interval: Duration,
+    last_request: Arc<Mutex<Option<Instant>>>,
+}
+
+impl Dexter {
+    #[must_use]
+    pub fn new(client: Client) -> Self {
+        Self {
+            client,
+            max_download_retries: archive_download::DEFAULT_MAX_DOWNLOAD_RETRIES,
+            if_exists: IfExists::default(),
+            min_request_interval: DEFAULT_MIN_REQUEST_INTERVAL,
+            last_request: Arc::default(),
+        }
+    }
+
+    #[must_use]
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    #[must_use]
+    pub fn set_max_download_retries(mut self, max_download_retries: u32) -> Self {
+        self.max_download_retries = max_download_retries;
+        self
+    }
+
+    /// What to do when the archive written by [`Self::download_chapter_to`] already exists
+    #[must_use]
+    pub fn set_if_exists(mut self, if_exists: IfExists) -> Self {
+        self.if_exists = if_exists;
+        self
+    }
+
+    #[must_use]
+    pub fn set_min_request_interval(mut self, min_request_interval: Duration) -> Self {
+        self.min_request_interval = min_request_interval;
+        self
+    }
+
+    /// Searches mangas by title, most relevant first
+    ///
+    /// # Errors
+    ///
+    /// Fails if the request fails
+    pub async fn search(&self, title: &str, limit: Option<u32>) -> Result<search::Response> {
+        self.throttle().await;
+        Search::new(title)
+            .set_limit(limit)
+            .request_with(&self.client)
+            .await
+    }
+
+    /// Gets a manga from its id or link
+    ///
+    /// # Errors
+    ///
+    /// Fails if `manga` is neither an id nor a manga link, or if the request fails
+    pub async fn manga(&self, manga: &str) -> Result<get_manga::Response> {
+        let manga_id = link::manga_id(manga)?;
+        self.throttle().await;
+        GetManga::new(manga_id).request_with(&self.client).await
+    }
+
+    /// Gets a page of the chapters of a manga, from its id or link, translated in one of the `languages`,
+    /// sorted by volume and chapter number, most recent first
+    ///
+    /// # Errors
+    ///
+    /// Fails if `manga` is neither an id nor a manga link, or if the request fails
+    pub async fn chapters(
+        &self,
+        manga: &str,
+        languages: impl IntoIterator<Item = impl Into<String>>,
+        offset: u32,
+        limit: u32,
+    ) -> Result<get_chapters::Response> {
+        let manga_id = link::manga_id(manga)?;
+        self.throttle().await;
+        let mut chapters = GetChapters::new(manga_id)
+            .with_languages(languages)
+            .set_offset(offset)
+            .set_limit(limit)
+            .request_with(&self.client)
+            .await?;
+        chapters.data.sort_by(|a, b| b.cmp_by_number(a));
+        Ok(chapters)
+    }
+
+    /// Downloads a chapter, from its id or link, and writes its archive to `path`,
+    /// returning the decision taken if the archive already exists, see [`Self::set_if_exists`]
+    ///
+    /// # Errors
+    ///
+    /// Fails if `chapter` is neither an id nor a chapter link, if the download fails, or if the archive can't be written
+    pub async fn download_chapter_to(
+        &self,
+        chapter: &str,
+        path: &Utf8Path,
+        progress: Arc<dyn ProgressSink<archive_download::Event>>,
+    ) -> Result<Option<Decision>> {
+        let chapter_id = link::chapter_id(chapter)?;
+        self.throttle().await;
+        let decision = self
+            .if_exists
+            .decide(&chapter_id, path, &self.client)
+            .await?;
+        let Some(path) = decision
+            .as_ref()
+            .map_or(Some(path), |decision| decision.destination(path))
+        else {
+            return Ok(decision);
+        };
+        self.throttle().await;
+        let cbz_writer = ArchiveDownload::new(&chapter_id)
+            .set_max_download_retries(self.max_download_retries)
+            .set_progress(progress)
+            .request_with(&self.client)
+            .await?;
+        self.throttle().await;
+        let comic_info = match ComicInfo::fetch(&chapter_id, &self.client).await {
+            Ok(comic_info) => Some(comic_info),
+            Err(err) => {
+                warn!("metadata of chapter {chapter_id} not written to {path}: {err}");
+                None
+            }
+        };
+        write_atomically_with_comic_info(
+            cbz_writer,
+            path,
+            self.if_exists.write_policy(),
+            comic_info.as_ref(),
+        )?;
+        info!("{path} written");
+        Ok(decision)
+    }
+
+    /// Returns the name of the archive of a chapter, e.g. `Detective Conan - 1 - The Shrunken Detective.cbz`
+    #[must_use]
+    pub fn chapter_file_name(manga: &get_manga::Data, chapter: &get_chapters::Data) -> String {
+        let name = format!(
+            "{} - {} - {}",
+            manga.attributes.title.en,
+            chapter.attributes.chapter.as_deref().unwrap_or("unknown"),
+            chapter.attributes.title.as_deref().unwrap_or("unknown"),
+        );
+        naming::file_name_with_extension(&name, "cbz")
+    }
+
+    /// Waits until the minimum interval since the previous api request has elapsed
+    async fn throttle(&self) {
+        let mut last_request = self.last_request.lock().await;
+        if let Some(last_request) = *last_request {
+            sleep_until((last_request + self.min_request_interval).into()).await;
+        }
+        *last_request = Some(Instant::now());
+    }
+}
+
+impl Default for Dexter {
+    fn default() -> Self {
+        Self::new(Client::shared())
+    }
+}
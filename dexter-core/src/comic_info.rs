@@ -0,0 +1,90 @@
+use std::{
+    fmt::Write as _,
+    fs::File,
+    io::{Seek, SeekFrom, Write},
+};
+
+use zip::{write::FileOptions, ZipWriter};
+
+use crate::{
+    api::{get_chapter_by_id, GetChapterById},
+    xml::escape,
+    Client, Link, Request, Result,
+};
+
+/// Name of the metadata file in the archives, read by the comic readers and the media servers
+pub static COMIC_INFO_FILE_NAME: &str = "ComicInfo.xml";
+
+/// Metadata of a chapter, written to the `ComicInfo.xml` file of its archive
+#[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ComicInfo {
+    pub series: Option<String>,
+    pub volume: Option<String>,
+    pub number: Option<String>,
+    pub title: Option<String>,
+    pub language_iso: Option<String>,
+    /// Link to the chapter on `MangaDex`
+    pub web: Option<String>,
+}
+
+impl ComicInfo {
+    /// Fetches the metadata of the chapter, and of the manga it belongs to
+    ///
+    /// # Errors
+    ///
+    /// Fails if the chapter can't be fetched
+    pub async fn fetch(chapter_id: &str, client: &Client) -> Result<Self> {
+        let response = GetChapterById::new(chapter_id).request_with(client).await?;
+        Ok(Self::from(&response.data))
+    }
+
+    /// Returns the `ComicInfo.xml` document, only the known fields are written
+    #[must_use]
+    pub fn to_xml(&self) -> String {
+        let mut xml = String::new();
+        let _ = writeln!(xml, r#"<?xml version="1.0" encoding="utf-8"?>"#);
+        let _ = writeln!(xml, "<ComicInfo>");
+        for (tag, value) in [
+            ("Title", &self.title),
+            ("Series", &self.series),
+            ("Number", &self.number),
+            ("Volume", &self.volume),
+            ("Web", &self.web),
+            ("LanguageISO", &self.language_iso),
+        ] {
+            if let Some(value) = value {
+                let _ = writeln!(xml, "  <{tag}>{}</{tag}>", escape(value));
+            }
+        }
+        let _ = writeln!(xml, "</ComicInfo>");
+        xml
+    }
+
+    /// Adds the `ComicInfo.xml` file to the archive written in `file`
+    ///
+    /// # Errors
+    ///
+    /// Fails if `file` is not a zip archive, or if it can't be written
+    pub fn append_to(&self, file: &mut File) -> Result<()> {
+        file.seek(SeekFrom::Start(0))?;
+        let mut zip = ZipWriter::new_append(file)?;
+        zip.start_file(COMIC_INFO_FILE_NAME, FileOptions::default())?;
+        zip.write_all(self.to_xml().as_bytes())?;
+        zip.finish()?;
+
+        Ok(())
+    }
+}
+
+impl From<&get_chapter_by_id::Data> for ComicInfo {
+    fn from(chapter: &get_chapter_by_id::Data) -> Self {
+        Self {
+            series: chapter.manga_title().map(ToString::to_string),
+            volume: chapter.attributes.volume.clone(),
+            number: chapter.attributes.chapter.clone(),
+            title: chapter.attributes.title.clone(),
+            language_iso: chapter.attributes.translated_language.clone(),
+            web: Some(Link::Chapter(chapter.id.clone()).url()),
+        }
+    }
+}
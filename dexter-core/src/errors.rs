@@ -3,20 +3,76 @@ pub enum Error {
     #[error("send image download event error: {0}")]
     Send(#[from] tokio::sync::mpsc::error::SendError<crate::api::archive_download::Event>),
 
+    #[error("send batch download event error: {0}")]
+    SendBatch(
+        #[from] tokio::sync::mpsc::error::SendError<crate::api::batch_archive_download::Event>,
+    ),
+
     #[error("join error: {0}")]
     Join(#[from] tokio::task::JoinError),
 
     #[error("cbz error: {0}")]
     Cbz(#[from] eco_cbz::Error),
 
+    #[error("zip error: {0}")]
+    Zip(#[from] zip::result::ZipError),
+
     #[error("reqwest error: {0}")]
     Reqwest(#[from] reqwest::Error),
 
     #[error("reqwest middleware error: {0}")]
     ReqwestMiddleware(#[from] reqwest_middleware::Error),
 
+    #[error("invalid header value: {0}")]
+    InvalidHeaderValue(#[from] reqwest::header::InvalidHeaderValue),
+
     #[error("url parse error: {0}")]
     UrlParse(#[from] url::ParseError),
+
+    #[error("download cancelled")]
+    Cancelled,
+
+    #[error("invalid id: {0}, expected an id or a MangaDex link")]
+    InvalidId(String),
+
+    #[error(
+        "invalid chapter selection: {0}, expected a number or a range such as 1-20, 60-, or -10"
+    )]
+    InvalidChapterSelection(String),
+
+    #[error("image not served by the MD@Home node: {0}")]
+    MissingImage(String),
+
+    #[error("unexpected content type for an image: {0}")]
+    UnexpectedContentType(String),
+
+    #[error("image larger than {0} bytes")]
+    ImageTooLarge(u64),
+
+    #[error("no bytes received for {0:?}")]
+    Stalled(std::time::Duration),
+
+    #[error("chapter {0} has no pages on MangaDex, it may be hosted on an external website")]
+    Unavailable(String),
+
+    #[error("chapter not downloaded within {0:?}")]
+    DeadlineExceeded(std::time::Duration),
+
+    #[error("page transform error: {0}")]
+    Transform(Box<dyn std::error::Error + Send + Sync>),
+
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("not enough space in {dir}: {needed} bytes needed, {available} available")]
+    InsufficientSpace {
+        dir: camino::Utf8PathBuf,
+        needed: u64,
+        available: u64,
+    },
+
+    #[error("file already exists: {0}")]
+    AlreadyExists(camino::Utf8PathBuf),
 }
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -0,0 +1,89 @@
+use std::fmt::Write as _;
+
+use crate::{
+    api::{get_chapters, get_manga},
+    xml::escape,
+    Link,
+};
+
+/// Update date of a feed without any chapter, feeds must always have one
+static EPOCH: &str = "1970-01-01T00:00:00+00:00";
+
+/// Date the chapter was released at, the first of the readable, publication, and upload dates provided
+fn released_at(chapter: &get_chapters::Data) -> Option<&str> {
+    chapter
+        .attributes
+        .readable_at
+        .as_deref()
+        .or(chapter.attributes.publish_at.as_deref())
+        .or(chapter.attributes.created_at.as_deref())
+}
+
+/// Title of a feed entry, e.g. `Vol. 1 Ch. 10 - The Tenth Case`
+fn entry_title(chapter: &get_chapters::Data) -> String {
+    let attributes = &chapter.attributes;
+    let mut title = String::new();
+    if let Some(volume) = &attributes.volume {
+        let _ = write!(title, "Vol. {volume} ");
+    }
+    match &attributes.chapter {
+        Some(number) => {
+            let _ = write!(title, "Ch. {number}");
+        }
+        None => title.push_str("Oneshot"),
+    }
+    if let Some(chapter_title) = attributes
+        .title
+        .as_deref()
+        .filter(|title| !title.is_empty())
+    {
+        let _ = write!(title, " - {chapter_title}");
+    }
+    title
+}
+
+/// Generates an Atom feed of the chapters of the manga, so that feed readers can track the new releases.
+///
+/// The entries link to the chapter pages on `MangaDex`, in the order of `chapters`,
+/// and the feed is updated at the release date of the most recent chapter.
+#[must_use]
+pub fn atom(manga: &get_manga::Data, chapters: &[get_chapters::Data]) -> String {
+    let manga_url = escape(&Link::Title(manga.id.clone()).url());
+    let updated = chapters
+        .iter()
+        .filter_map(released_at)
+        .max()
+        .unwrap_or(EPOCH);
+
+    let mut feed = String::new();
+    let _ = writeln!(feed, r#"<?xml version="1.0" encoding="utf-8"?>"#);
+    let _ = writeln!(feed, r#"<feed xmlns="http://www.w3.org/2005/Atom">"#);
+    let _ = writeln!(feed, "  <id>{manga_url}</id>");
+    let _ = writeln!(
+        feed,
+        "  <title>{}</title>",
+        escape(&manga.attributes.title.en)
+    );
+    let _ = writeln!(feed, r#"  <link href="{manga_url}"/>"#);
+    // Required by Atom when the entries don't have an author of their own
+    let _ = writeln!(feed, "  <author><name>MangaDex</name></author>");
+    let _ = writeln!(feed, "  <updated>{}</updated>", escape(updated));
+    for chapter in chapters {
+        let chapter_url = escape(&Link::Chapter(chapter.id.clone()).url());
+        let _ = writeln!(feed, "  <entry>");
+        let _ = writeln!(feed, "    <id>{chapter_url}</id>");
+        let _ = writeln!(feed, "    <title>{}</title>", escape(&entry_title(chapter)));
+        let _ = writeln!(feed, r#"    <link href="{chapter_url}"/>"#);
+        let _ = writeln!(
+            feed,
+            "    <updated>{}</updated>",
+            escape(released_at(chapter).unwrap_or(updated))
+        );
+        if let Some(language) = &chapter.attributes.translated_language {
+            let _ = writeln!(feed, r#"    <category term="{}"/>"#, escape(language));
+        }
+        let _ = writeln!(feed, "  </entry>");
+    }
+    let _ = writeln!(feed, "</feed>");
+    feed
+}
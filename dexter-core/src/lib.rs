@@ -2,9 +2,33 @@
 #![deny(clippy::pedantic)]
 
 pub use crate::{
-    api::{ArchiveDownload, GetChapter, GetChapters, GetImageLinks, GetManga, Request, Search},
+    api::{
+        ArchiveDownload, BatchArchiveDownload, DownloadImage, GetAggregate, GetChapter,
+        GetChapters, GetImageLinks, GetManga, GetScanlationGroup, Request, Search, SearchGroups,
+    },
+    chapter_number::ChapterNumber,
+    chapter_selection::{ChapterRange, ChapterSelection},
+    client::{Client, Dexter},
+    comic_info::ComicInfo,
     errors::{Error, Result},
+    link::Link,
+    output::{write_atomically, write_atomically_with_comic_info, IfExists, WritePolicy},
+    transform::{PageTransform, Transforms},
 };
 
 pub mod api;
+pub mod chapter_number;
+pub mod chapter_selection;
+pub mod client;
+pub mod comic_info;
 pub mod errors;
+pub mod feed;
+pub mod link;
+pub mod mock;
+pub mod naming;
+pub mod output;
+pub mod page_store;
+pub mod progress;
+pub mod summary;
+pub mod transform;
+mod xml;
@@ -0,0 +1,89 @@
+use url::Url;
+
+use crate::{Error, Result};
+
+/// A `MangaDex` title or chapter, parsed from a link to the website
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Link {
+    /// Manga id, from a `https://mangadex.org/title/{id}/{slug}` link
+    Title(String),
+    /// Chapter id, from a `https://mangadex.org/chapter/{id}/{page}` link
+    Chapter(String),
+}
+
+impl Link {
+    /// Parses a link to a `MangaDex` title or chapter page, `None` for any other text
+    #[must_use]
+    pub fn parse(link: &str) -> Option<Self> {
+        let url = Url::parse(link.trim()).ok()?;
+        if !url
+            .host_str()
+            .is_some_and(|host| host == "mangadex.org" || host.ends_with(".mangadex.org"))
+        {
+            return None;
+        }
+
+        let mut segments = url.path_segments()?;
+        let kind = segments.next()?;
+        let id = segments.next().filter(|id| is_uuid(id))?.to_string();
+        match kind {
+            "title" | "manga" => Some(Self::Title(id)),
+            "chapter" => Some(Self::Chapter(id)),
+            _ => None,
+        }
+    }
+
+    /// Returns the link to the page on the `MangaDex` website
+    #[must_use]
+    pub fn url(&self) -> String {
+        match self {
+            Self::Title(id) => format!("https://mangadex.org/title/{id}"),
+            Self::Chapter(id) => format!("https://mangadex.org/chapter/{id}"),
+        }
+    }
+}
+
+/// Whether the value is formatted as the uuids used as ids by `MangaDex`
+#[must_use]
+pub fn is_uuid(value: &str) -> bool {
+    value.len() == 36
+        && value.char_indices().all(|(index, char)| {
+            if matches!(index, 8 | 13 | 18 | 23) {
+                char == '-'
+            } else {
+                char.is_ascii_hexdigit()
+            }
+        })
+}
+
+/// Returns the manga id, given either as is or as a title link
+///
+/// # Errors
+///
+/// Fails if the value is neither an id nor a title link
+pub fn manga_id(value: &str) -> Result<String> {
+    let value = value.trim();
+    if is_uuid(value) {
+        return Ok(value.to_string());
+    }
+    match Link::parse(value) {
+        Some(Link::Title(id)) => Ok(id),
+        _ => Err(Error::InvalidId(value.to_string())),
+    }
+}
+
+/// Returns the chapter id, given either as is or as a chapter link
+///
+/// # Errors
+///
+/// Fails if the value is neither an id nor a chapter link
+pub fn chapter_id(value: &str) -> Result<String> {
+    let value = value.trim();
+    if is_uuid(value) {
+        return Ok(value.to_string());
+    }
+    match Link::parse(value) {
+        Some(Link::Chapter(id)) => Ok(id),
+        _ => Err(Error::InvalidId(value.to_string())),
+    }
+}
@@ -0,0 +1,128 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use http::StatusCode;
+use reqwest::{Request, Response};
+use reqwest_middleware::{Middleware, Next};
+use task_local_extensions::Extensions;
+use url::Url;
+
+/// Middleware answering requests with recorded fixtures instead of reaching the network.
+///
+/// Fixtures are matched on the url path only (`/manga`, `/at-home/server/{id}`, ...),
+/// requests to unknown paths are answered with a 404. All the requested urls and json bodies are recorded,
+/// so that tests can check the query and the body built by the requests.
+#[derive(Debug, Clone, Default)]
+pub struct FixtureMiddleware {
+    fixtures: HashMap<String, Bytes>,
+    stalled: HashSet<String>,
+    failures: Arc<Mutex<HashMap<String, (StatusCode, usize)>>>,
+    requested_urls: Arc<Mutex<Vec<Url>>>,
+    requested_bodies: Arc<Mutex<Vec<Option<Bytes>>>>,
+}
+
+impl FixtureMiddleware {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn with_fixture(mut self, path: impl Into<String>, body: impl Into<Bytes>) -> Self {
+        self.fixtures.insert(path.into(), body.into());
+        self
+    }
+
+    /// Requests to `path` never get an answer, as with a stalled MD@Home node
+    #[must_use]
+    pub fn with_stalled(mut self, path: impl Into<String>) -> Self {
+        self.stalled.insert(path.into());
+        self
+    }
+
+    /// The next `count` requests to `path` are answered with `status`, as with a failing MD@Home node
+    #[must_use]
+    pub fn with_failures(self, path: impl Into<String>, status: StatusCode, count: usize) -> Self {
+        self.failures
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(path.into(), (status, count));
+        self
+    }
+
+    /// Returns the status of the next failure planned for `path`, if any
+    fn next_failure(&self, path: &str) -> Option<StatusCode> {
+        let mut failures = self.failures.lock().unwrap_or_else(PoisonError::into_inner);
+        let (status, count) = failures.get_mut(path)?;
+        if *count == 0 {
+            return None;
+        }
+        *count -= 1;
+        Some(*status)
+    }
+
+    /// Returns all the urls requested so far, in order
+    #[must_use]
+    pub fn requested_urls(&self) -> Vec<Url> {
+        self.requested_urls
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Returns the bodies of all the requests so far, in order, `None` for the requests without
+    /// a body or with a streamed one, e.g. a multipart upload
+    #[must_use]
+    pub fn requested_bodies(&self) -> Vec<Option<Bytes>> {
+        self.requested_bodies
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+}
+
+#[async_trait]
+impl Middleware for FixtureMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        _extensions: &mut Extensions,
+        _next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        let url = req.url().clone();
+        let fixture = self.fixtures.get(url.path()).cloned();
+        let stalled = self.stalled.contains(url.path());
+        let failure = self.next_failure(url.path());
+        self.requested_bodies
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(
+                req.body()
+                    .and_then(reqwest::Body::as_bytes)
+                    .map(Bytes::copy_from_slice),
+            );
+        self.requested_urls
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(url);
+
+        if stalled {
+            std::future::pending::<()>().await;
+        }
+
+        let response = match (failure, fixture) {
+            (None, Some(body)) => http::Response::new(body),
+            (failure, _) => {
+                let mut response = http::Response::new(Bytes::new());
+                *response.status_mut() = failure.unwrap_or(StatusCode::NOT_FOUND);
+                response
+            }
+        };
+
+        Ok(response.into())
+    }
+}
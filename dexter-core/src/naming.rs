@@ -0,0 +1,86 @@
+/// Longest file name, in bytes, accepted by the common filesystems
+pub static MAX_FILE_NAME_LEN: usize = 255;
+
+/// Longest series name, in characters, used in the archive paths.
+///
+/// Series are repeated in the folders and the file names of the layouts,
+/// long titles would otherwise push the paths past the 260 characters supported by most Windows applications.
+pub static MAX_SERIES_LEN: usize = 80;
+
+/// Characters forbidden in file names on Windows, and path separators everywhere else
+static FORBIDDEN_CHARS: &str = r#"/\?<>:*|""#;
+
+/// Device names Windows reserves, whatever the extension
+static RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Returns the longest prefix of `name` that fits in `max_len` bytes, without splitting a character
+fn truncate(name: &str, max_len: usize) -> &str {
+    let end = name
+        .char_indices()
+        .map(|(index, char)| index + char.len_utf8())
+        .take_while(|end| *end <= max_len)
+        .last()
+        .unwrap_or(0);
+    &name[..end]
+}
+
+/// Turns `name` into a file or folder name valid on all the platforms, whatever the platform dexter runs on,
+/// as archives are often copied to Windows machines and FAT formatted e-readers.
+///
+/// Forbidden and control characters are removed, leading spaces and trailing dots and spaces are trimmed,
+/// reserved device names such as `CON` or `nul.txt` get an underscore (`CON_`, `nul_.txt`),
+/// and the name is truncated to [`MAX_FILE_NAME_LEN`] bytes.
+#[must_use]
+pub fn file_name(name: &str) -> String {
+    let name = name
+        .chars()
+        .filter(|char| !char.is_control() && !FORBIDDEN_CHARS.contains(*char))
+        .collect::<String>();
+    // Room for the underscore of the reserved names
+    let name = truncate(name.trim_start(), MAX_FILE_NAME_LEN - 1).trim_end_matches(['.', ' ']);
+    if name.is_empty() {
+        return "_".to_string();
+    }
+
+    let stem = name.split('.').next().unwrap_or_default();
+    if RESERVED_NAMES
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(stem.trim_end()))
+    {
+        return format!("{stem}_{}", &name[stem.len()..]);
+    }
+
+    name.to_string()
+}
+
+/// Returns the file name `name.extension`, valid on all the platforms, see [`file_name`].
+/// Long names are truncated before the extension, which is always kept.
+#[must_use]
+pub fn file_name_with_extension(name: &str, extension: &str) -> String {
+    let max_len = MAX_FILE_NAME_LEN.saturating_sub(extension.len() + 2);
+    format!("{}.{extension}", file_name(truncate(name, max_len)))
+}
+
+/// Returns the series name used in the archive paths, valid on all the platforms, see [`file_name`].
+/// Names longer than [`MAX_SERIES_LEN`] characters are cut at a word boundary when possible.
+#[must_use]
+pub fn series_name(series: &str) -> String {
+    let series = if series.chars().count() > MAX_SERIES_LEN {
+        let end = series
+            .char_indices()
+            .nth(MAX_SERIES_LEN)
+            .map_or(series.len(), |(index, _)| index);
+        let shortened = &series[..end];
+        match shortened.rfind(char::is_whitespace) {
+            Some(index) if index > end / 2 => &shortened[..index],
+            _ => shortened,
+        }
+    } else {
+        series
+    };
+
+    file_name(series)
+}
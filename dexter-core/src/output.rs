@@ -0,0 +1,371 @@
+use std::{
+    fmt::{self, Display},
+    fs::{hard_link, remove_file, rename, File, OpenOptions},
+    io::{Cursor, ErrorKind, Write},
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use camino::{Utf8Path, Utf8PathBuf};
+use eco_cbz::CbzWriter;
+use serde::Serialize;
+use tracing::{error, info, warn};
+use zip::ZipArchive;
+
+use crate::{Client, ComicInfo, Error, GetImageLinks, Request, Result};
+
+/// Rough size of a chapter archive, used to check the free space before downloading several chapters
+pub static ESTIMATED_CHAPTER_SIZE: u64 = 8 * 1024 * 1024;
+
+/// Tells apart the temporary files, see [`part_path`]
+static NEXT_PART: AtomicU64 = AtomicU64::new(0);
+
+/// Extensions of the pages served by `MangaDex`
+static PAGE_EXTENSIONS: [&str; 5] = ["png", "jpg", "jpeg", "gif", "webp"];
+
+/// What to do when the destination file already exists
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum WritePolicy {
+    #[default]
+    Overwrite,
+    NoClobber,
+}
+
+impl WritePolicy {
+    /// Checks that `path` can be written according to the policy
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`Error::AlreadyExists`] if the policy forbids overwriting an existing `path`
+    pub fn ensure_writable(self, path: &Utf8Path) -> Result<()> {
+        match self {
+            Self::NoClobber if path.exists() => Err(Error::AlreadyExists(path.to_path_buf())),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// What to do with a chapter whose archive already exists, decided before downloading it
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum IfExists {
+    #[default]
+    Overwrite,
+    /// Keeps the existing archive, the chapter is not downloaded
+    Skip,
+    /// Writes the chapter next to the existing archive, e.g. `chapter (1).cbz`
+    Rename,
+    /// Keeps the existing archive if it has as many pages as the chapter, and replaces it otherwise
+    Verify,
+}
+
+/// Decision taken for a chapter whose archive already exists, see [`IfExists::decide`]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Decision {
+    Overwritten,
+    Skipped,
+    Renamed(Utf8PathBuf),
+    /// The archive has the expected number of pages, and is kept
+    Verified,
+    /// The archive doesn't have the expected number of pages, and is replaced
+    Replaced {
+        pages: usize,
+        expected: usize,
+    },
+}
+
+impl Decision {
+    /// Returns the path the chapter must be written to, `None` if it must not be downloaded
+    #[must_use]
+    pub fn destination<'a>(&'a self, path: &'a Utf8Path) -> Option<&'a Utf8Path> {
+        match self {
+            Self::Overwritten | Self::Replaced { .. } => Some(path),
+            Self::Renamed(path) => Some(path),
+            Self::Skipped | Self::Verified => None,
+        }
+    }
+}
+
+impl Display for Decision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Overwritten => write!(f, "overwritten"),
+            Self::Skipped => write!(f, "skipped"),
+            Self::Renamed(path) => write!(f, "renamed to {path}"),
+            Self::Verified => write!(f, "verified, skipped"),
+            Self::Replaced { pages, expected } => {
+                write!(f, "{pages}/{expected} pages, replaced")
+            }
+        }
+    }
+}
+
+impl IfExists {
+    /// Decides what to do with the chapter `chapter_id` whose archive is `path`, `None` if `path` doesn't exist
+    ///
+    /// # Errors
+    ///
+    /// With [`Self::Verify`], fails if the chapter pages can't be listed, an unreadable archive is replaced
+    pub async fn decide(
+        self,
+        chapter_id: &str,
+        path: &Utf8Path,
+        client: &Client,
+    ) -> Result<Option<Decision>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let decision = match self {
+            Self::Overwrite => Decision::Overwritten,
+            Self::Skip => Decision::Skipped,
+            Self::Rename => Decision::Renamed(available_path(path)),
+            Self::Verify => {
+                let pages = page_count(path).unwrap_or_else(|err| {
+                    warn!("couldn't read {path}: {err}");
+                    0
+                });
+                let expected = GetImageLinks::new(chapter_id)
+                    .request_with(client)
+                    .await?
+                    .len();
+                if pages == expected {
+                    Decision::Verified
+                } else {
+                    Decision::Replaced { pages, expected }
+                }
+            }
+        };
+        info!("{path} already exists: {decision}");
+
+        Ok(Some(decision))
+    }
+
+    /// Policy used to write the archive once downloaded,
+    /// the destination might have been created during the download
+    #[must_use]
+    pub fn write_policy(self) -> WritePolicy {
+        match self {
+            Self::Overwrite | Self::Verify => WritePolicy::Overwrite,
+            Self::Skip | Self::Rename => WritePolicy::NoClobber,
+        }
+    }
+}
+
+/// Returns the first path that doesn't exist among `name (1).ext`, `name (2).ext`, ...
+fn available_path(path: &Utf8Path) -> Utf8PathBuf {
+    let stem = path.file_stem().unwrap_or_default();
+    (1..u32::MAX)
+        .map(|index| {
+            let file_name = match path.extension() {
+                Some(extension) => format!("{stem} ({index}).{extension}"),
+                None => format!("{stem} ({index})"),
+            };
+            path.with_file_name(file_name)
+        })
+        .find(|path| !path.exists())
+        .unwrap_or_else(|| path.to_path_buf())
+}
+
+/// Whether the archive file `name` is a page, judging by its extension
+#[must_use]
+pub fn is_page(name: &str) -> bool {
+    Utf8Path::new(name)
+        .extension()
+        .is_some_and(|extension| PAGE_EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str()))
+}
+
+/// Counts the pages contained in the archive, without reading them
+///
+/// # Errors
+///
+/// Fails if the archive can't be opened
+pub fn page_count(path: &Utf8Path) -> Result<usize> {
+    let archive = ZipArchive::new(File::open(path)?)?;
+
+    Ok(archive.file_names().filter(|name| is_page(name)).count())
+}
+
+/// Returns the space available to the current user on the filesystem of `dir`,
+/// `None` on the platforms where it can't be known
+///
+/// # Errors
+///
+/// Fails if the filesystem can't be queried, e.g. if `dir` doesn't exist
+#[cfg(unix)]
+pub fn available_space(dir: &Utf8Path) -> Result<Option<u64>> {
+    let stat = rustix::fs::statvfs(dir.as_std_path()).map_err(std::io::Error::from)?;
+
+    Ok(Some(stat.f_bavail.saturating_mul(stat.f_frsize)))
+}
+
+/// Returns the space available to the current user on the filesystem of `dir`,
+/// `None` on the platforms where it can't be known
+///
+/// # Errors
+///
+/// Never fails on this platform
+#[cfg(not(unix))]
+pub fn available_space(_dir: &Utf8Path) -> Result<Option<u64>> {
+    Ok(None)
+}
+
+/// Checks that `needed` bytes can be written in `dir`, so that a download fails before it starts
+/// rather than in the middle of an archive
+///
+/// # Errors
+///
+/// Fails with [`Error::InsufficientSpace`] if the filesystem of `dir` doesn't have enough space
+pub fn ensure_available_space(dir: &Utf8Path, needed: u64) -> Result<()> {
+    match available_space(dir)? {
+        Some(available) if available < needed => Err(Error::InsufficientSpace {
+            dir: dir.to_path_buf(),
+            needed,
+            available,
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Returns a temporary path to write `path` to, in the same directory so that the final rename is atomic.
+///
+/// The path is unique to the call, named after the process and a counter, so that concurrent writes
+/// of the same file, in this process or in another one, never share their temporary file.
+#[must_use]
+pub fn part_path(path: &Utf8Path) -> Utf8PathBuf {
+    let id = NEXT_PART.fetch_add(1, Ordering::Relaxed);
+    Utf8PathBuf::from(format!("{path}.{}-{id}.part", std::process::id()))
+}
+
+/// Moves `part_path` to `path` according to the policy, the move never overwrites an existing file with
+/// [`WritePolicy::NoClobber`], even one created by another process in the meantime
+fn persist(part_path: &Utf8Path, path: &Utf8Path, policy: WritePolicy) -> Result<()> {
+    match policy {
+        WritePolicy::Overwrite => Ok(rename(part_path, path)?),
+        WritePolicy::NoClobber => {
+            let res = rename_no_clobber(part_path, path);
+            if res.is_err() && part_path.exists() {
+                remove_file(part_path)?;
+            }
+            res
+        }
+    }
+}
+
+/// Renames `from` to `to` unless `to` exists, even if another process creates it in the meantime.
+/// `from` is left untouched when the rename fails.
+///
+/// # Errors
+///
+/// Fails with [`Error::AlreadyExists`] if `to` exists, or if `from` can't be renamed
+pub fn rename_no_clobber(from: &Utf8Path, to: &Utf8Path) -> Result<()> {
+    // Unlike a rename, a hard link fails if the destination exists
+    match hard_link(from, to) {
+        Ok(()) => Ok(remove_file(from)?),
+        Err(err) if err.kind() == ErrorKind::AlreadyExists => {
+            Err(Error::AlreadyExists(to.to_path_buf()))
+        }
+        // Some filesystems, e.g. FAT, have no hard links, the check and the rename are not atomic there
+        Err(err) => {
+            warn!("couldn't link {from} to {to}, renaming it instead: {err}");
+            WritePolicy::NoClobber.ensure_writable(to)?;
+            Ok(rename(from, to)?)
+        }
+    }
+}
+
+/// Writes the archive to a temporary file next to `path`, flushes it to the disk, and then renames it.
+/// A crash or an interruption never leaves a truncated archive at `path`, nor corrupts an existing one.
+///
+/// # Errors
+///
+/// Fails if the policy forbids writing `path`, or if the archive can't be written
+pub fn write_atomically(
+    cbz_writer: CbzWriter<Cursor<Vec<u8>>>,
+    path: &Utf8Path,
+    policy: WritePolicy,
+) -> Result<()> {
+    write_atomically_with_comic_info(cbz_writer, path, policy, None)
+}
+
+/// Same as [`write_atomically`], also adding the `ComicInfo.xml` metadata file to the archive
+///
+/// # Errors
+///
+/// Fails if the policy forbids writing `path`, or if the archive can't be written
+pub fn write_atomically_with_comic_info(
+    cbz_writer: CbzWriter<Cursor<Vec<u8>>>,
+    path: &Utf8Path,
+    policy: WritePolicy,
+    comic_info: Option<&ComicInfo>,
+) -> Result<()> {
+    write_part_then_rename(path, policy, |file| {
+        cbz_writer.write_to(&*file)?;
+        match comic_info {
+            Some(comic_info) => comic_info.append_to(file),
+            None => Ok(()),
+        }
+    })
+}
+
+/// Same as [`write_atomically`] for any file, e.g. a feed or a report
+///
+/// # Errors
+///
+/// Fails if the policy forbids writing `path`, or if the file can't be written
+pub fn write_bytes_atomically(
+    bytes: impl AsRef<[u8]>,
+    path: &Utf8Path,
+    policy: WritePolicy,
+) -> Result<()> {
+    write_part_then_rename(path, policy, |file| Ok(file.write_all(bytes.as_ref())?))
+}
+
+/// Writes the temporary file of `path` with `write`, and then renames it to `path`
+fn write_part_then_rename(
+    path: &Utf8Path,
+    policy: WritePolicy,
+    write: impl FnOnce(&mut File) -> Result<()>,
+) -> Result<()> {
+    policy.ensure_writable(path)?;
+
+    let part_path = part_path(path);
+
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .truncate(true)
+        .create(true)
+        .open(&part_path)?;
+
+    // The data must reach the disk before the rename, or a power loss could leave an empty file at `path`
+    let written = write(&mut file).and_then(|()| Ok(file.sync_all()?));
+    drop(file);
+    if let Err(err) = written {
+        if let Err(err) = remove_file(&part_path) {
+            error!("failed to remove {part_path}: {err}");
+        }
+        return Err(err);
+    }
+
+    // The destination might have been created while the file was being written
+    persist(&part_path, path, policy)?;
+    sync_parent(path);
+
+    Ok(())
+}
+
+/// Flushes the directory entry of `path` to the disk, so that the rename survives a power loss.
+/// The file is already complete at `path`, a failure is only logged.
+#[cfg(unix)]
+fn sync_parent(path: &Utf8Path) {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_str().is_empty() => parent,
+        _ => Utf8Path::new("."),
+    };
+    if let Err(err) = File::open(parent).and_then(|dir| dir.sync_all()) {
+        warn!("couldn't flush the directory {parent}: {err}");
+    }
+}
+
+/// Directories can't be opened as files on Windows, flushing the rename is left to the file system
+#[cfg(not(unix))]
+fn sync_parent(_path: &Utf8Path) {}
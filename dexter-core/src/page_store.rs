@@ -0,0 +1,139 @@
+use std::{
+    fmt::Write as _,
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+};
+
+use bytes::Bytes;
+use camino::{Utf8Path, Utf8PathBuf};
+use sha2::{Digest, Sha256};
+use tokio::fs;
+use tracing::{info, warn};
+
+use crate::{output::part_path, Result};
+
+/// Length of the hexadecimal sha256 digests found in the page filenames
+const CHECKSUM_LEN: usize = 64;
+
+/// Pages shared between chapter downloads, stored on disk and addressed by their checksum.
+///
+/// `MangaDex` names the pages `{index}-{sha256}.{extension}`, so identical pages (e.g. the credit pages
+/// re-published in every chapter of a series) can be looked up before being downloaded.
+/// Pages without a checksum in their filename are never stored, and the checksum is verified
+/// when a page is stored and when it's read, so that a corrupted page is never reused.
+#[derive(Debug)]
+pub struct PageStore {
+    dir: Utf8PathBuf,
+    pages_reused: AtomicUsize,
+    bytes_saved: AtomicU64,
+}
+
+impl PageStore {
+    /// Uses `dir` as the store, it's created if it doesn't exist
+    ///
+    /// # Errors
+    ///
+    /// Fails if `dir` can't be created
+    pub fn new(dir: impl Into<Utf8PathBuf>) -> Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+
+        Ok(Self {
+            dir,
+            pages_reused: AtomicUsize::new(0),
+            bytes_saved: AtomicU64::new(0),
+        })
+    }
+
+    /// Extracts the sha256 checksum from a page filename, if any
+    #[must_use]
+    pub fn checksum(filename: &str) -> Option<&str> {
+        let (_, checksum) = Utf8Path::new(filename).file_stem()?.split_once('-')?;
+        (checksum.len() == CHECKSUM_LEN && checksum.chars().all(|c| c.is_ascii_hexdigit()))
+            .then_some(checksum)
+    }
+
+    /// Whether `bytes` match the checksum of `filename`
+    fn is_intact(filename: &str, bytes: &[u8]) -> bool {
+        let digest = Sha256::digest(bytes).iter().fold(
+            String::with_capacity(CHECKSUM_LEN),
+            |mut digest, byte| {
+                let _ = write!(digest, "{byte:02x}");
+                digest
+            },
+        );
+        Self::checksum(filename).is_some_and(|checksum| checksum.eq_ignore_ascii_case(&digest))
+    }
+
+    fn path(&self, filename: &str) -> Option<Utf8PathBuf> {
+        let checksum = Self::checksum(filename)?;
+        let mut path = self.dir.join(checksum);
+        if let Some(extension) = Utf8Path::new(filename).extension() {
+            path.set_extension(extension);
+        }
+        Some(path)
+    }
+
+    /// Returns the stored page matching the checksum of `filename`, and counts it as reused
+    pub async fn get(&self, filename: &str) -> Option<Bytes> {
+        let path = self.path(filename)?;
+        let bytes = match fs::read(&path).await {
+            Ok(bytes) => Bytes::from(bytes),
+            Err(err) => {
+                if err.kind() != std::io::ErrorKind::NotFound {
+                    warn!("failed to read {path} from the page store: {err}");
+                }
+                return None;
+            }
+        };
+
+        if !Self::is_intact(filename, &bytes) {
+            warn!("{path} is corrupted, removing it from the page store");
+            if let Err(err) = fs::remove_file(&path).await {
+                warn!("failed to remove {path} from the page store: {err}");
+            }
+            return None;
+        }
+
+        info!("Reusing {filename} from the page store");
+        self.pages_reused.fetch_add(1, Ordering::Relaxed);
+        self.bytes_saved
+            .fetch_add(bytes.len() as u64, Ordering::Relaxed);
+
+        Some(bytes)
+    }
+
+    /// Stores the page unless it doesn't match its checksum, failures are only logged as the download itself succeeded.
+    ///
+    /// The page is written to a temporary file of its own and then renamed, as the same page can be stored
+    /// by several downloads at once, in this process or in another one.
+    pub async fn insert(&self, filename: &str, bytes: &Bytes) {
+        let Some(path) = self.path(filename) else {
+            return;
+        };
+        if !Self::is_intact(filename, bytes) {
+            warn!("{filename} doesn't match its checksum, not storing it");
+            return;
+        }
+        let part_path = part_path(&path);
+        let res = match fs::write(&part_path, bytes).await {
+            Ok(()) => fs::rename(&part_path, &path).await,
+            Err(err) => Err(err),
+        };
+        if let Err(err) = res {
+            warn!("failed to write {path} to the page store: {err}");
+            let _ = fs::remove_file(&part_path).await;
+        }
+    }
+
+    /// How many pages have been read from the store instead of being downloaded
+    #[must_use]
+    pub fn pages_reused(&self) -> usize {
+        self.pages_reused.load(Ordering::Relaxed)
+    }
+
+    /// Total size in bytes of the pages read from the store
+    #[must_use]
+    pub fn bytes_saved(&self) -> u64 {
+        self.bytes_saved.load(Ordering::Relaxed)
+    }
+}
@@ -0,0 +1,213 @@
+use std::{
+    collections::VecDeque,
+    fmt::Debug,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use tokio::sync::mpsc;
+
+use crate::api::archive_download::Event;
+
+/// Receives the events of a download, see [`crate::ArchiveDownload::set_progress`].
+///
+/// Events are sent from the download tasks, implementations must not block.
+pub trait ProgressSink<E>: Debug + Send + Sync {
+    fn on_event(&self, event: &E);
+}
+
+/// Ignores all the events, used by default
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NoProgress;
+
+impl<E> ProgressSink<E> for NoProgress {
+    fn on_event(&self, _event: &E) {}
+}
+
+/// Forwards the events to a tokio channel, events are dropped once the receiver is closed
+impl<E: Debug + Clone + Send> ProgressSink<E> for mpsc::UnboundedSender<E> {
+    fn on_event(&self, event: &E) {
+        let _ = self.send(event.clone());
+    }
+}
+
+/// Events a [`BoundedSender`] can merge while they wait for room in the channel
+pub trait Coalesce: Sized {
+    /// Whether both events belong to the same stream, e.g. the same chapter of a batch.
+    /// The events of a stream keep their order, the events of different streams may be reordered.
+    fn same_stream(&self, other: &Self) -> bool;
+
+    /// Merges `self` and the `next` event of the same stream into one, or `None` if they must be sent separately
+    fn coalesce(&self, next: &Self) -> Option<Self>;
+}
+
+/// Forwards the events of a download to a bounded channel, without ever blocking the download.
+///
+/// Events wait in a queue until the channel has room. Overflow policy: while they wait, each event is
+/// merged with [`Coalesce`] into the last queued event of its stream, for a chapter download
+/// [`Event::Download`], [`Event::Zip`], and [`Event::Progress`] are merged into one [`Event::Coalesced`].
+/// The events of concurrent streams, e.g. the chapters of a batch, are merged even when they interleave,
+/// so a lagging receiver only delays the events, and the queue only grows by the events that can't be merged,
+/// such as `Init` and `Done`. Events are dropped once the receiver is closed.
+#[derive(Debug)]
+pub struct BoundedSender<E = Event> {
+    queue: Arc<Mutex<VecDeque<E>>>,
+    wake: mpsc::Sender<()>,
+}
+
+impl<E: Send + 'static> BoundedSender<E> {
+    /// Spawns the task forwarding the queued events to `sender`, must be called from a tokio runtime
+    #[must_use]
+    pub fn new(sender: mpsc::Sender<E>) -> Self {
+        let queue = Arc::new(Mutex::new(VecDeque::new()));
+        let (wake, mut woken) = mpsc::channel(1);
+
+        let forwarded = Arc::clone(&queue);
+        tokio::spawn(async move {
+            while woken.recv().await.is_some() {
+                if forward(&forwarded, &sender).await.is_err() {
+                    return;
+                }
+            }
+            // The sink is dropped, flushes the events queued since the last wake up
+            let _ = forward(&forwarded, &sender).await;
+        });
+
+        Self { queue, wake }
+    }
+
+    /// Amount of events waiting for room in the channel
+    #[must_use]
+    pub fn pending(&self) -> usize {
+        self.queue
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .len()
+    }
+}
+
+/// Sends the queued events as long as the channel has room, and the queue is not empty
+async fn forward<E>(
+    queue: &Mutex<VecDeque<E>>,
+    sender: &mpsc::Sender<E>,
+) -> Result<(), mpsc::error::SendError<()>> {
+    loop {
+        let permit = sender.reserve().await?;
+        let event = queue
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .pop_front();
+        let Some(event) = event else {
+            return Ok(());
+        };
+        permit.send(event);
+    }
+}
+
+/// Returns the pages downloaded, the pages packed, and the bytes received, for the events that can be merged
+fn ticks(event: &Event) -> Option<(usize, usize, usize)> {
+    match event {
+        Event::Download => Some((1, 0, 0)),
+        Event::Zip => Some((0, 1, 0)),
+        Event::Progress(bytes) => Some((0, 0, *bytes)),
+        Event::Coalesced {
+            downloads,
+            zips,
+            bytes,
+        } => Some((*downloads, *zips, *bytes)),
+        Event::Init(_) | Event::DataSaver(_) | Event::Done => None,
+    }
+}
+
+/// A chapter download is a single stream
+impl Coalesce for Event {
+    fn same_stream(&self, _other: &Self) -> bool {
+        true
+    }
+
+    fn coalesce(&self, next: &Self) -> Option<Self> {
+        ticks(self)
+            .zip(ticks(next))
+            .map(|(last, new)| Event::Coalesced {
+                downloads: last.0 + new.0,
+                zips: last.1 + new.1,
+                bytes: last.2 + new.2,
+            })
+    }
+}
+
+impl<E: Coalesce + Debug + Clone + Send> ProgressSink<E> for BoundedSender<E> {
+    fn on_event(&self, event: &E) {
+        let mut queue = self.queue.lock().unwrap_or_else(PoisonError::into_inner);
+        let merged = queue
+            .iter()
+            .rposition(|queued| queued.same_stream(event))
+            .and_then(|index| Some((index, queue[index].coalesce(event)?)));
+        match merged {
+            Some((index, merged)) => queue[index] = merged,
+            None => queue.push_back(event.clone()),
+        }
+        drop(queue);
+
+        // The wake up channel holds at most one message, the forwarding task drains the whole queue anyway
+        let _ = self.wake.try_send(());
+    }
+}
+
+#[cfg(feature = "indicatif")]
+pub use self::indicatif::IndicatifProgress;
+
+#[cfg(feature = "indicatif")]
+mod indicatif {
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    use indicatif::{HumanBytes, ProgressBar};
+
+    use crate::api::archive_download::Event;
+
+    use super::ProgressSink;
+
+    /// Displays the progress of a chapter download in a progress bar, along with the amount of bytes received.
+    ///
+    /// Each page counts twice, once downloaded and once packed, and the message is replaced by the bytes received.
+    #[derive(Debug)]
+    pub struct IndicatifProgress {
+        bar: ProgressBar,
+        received_bytes: AtomicU64,
+    }
+
+    impl IndicatifProgress {
+        #[must_use]
+        pub fn new(bar: ProgressBar) -> Self {
+            Self {
+                bar,
+                received_bytes: AtomicU64::new(0),
+            }
+        }
+    }
+
+    impl ProgressSink<Event> for IndicatifProgress {
+        fn on_event(&self, event: &Event) {
+            match event {
+                Event::Init(len) => self.bar.set_length(*len as u64 * 2),
+                Event::Progress(len) => {
+                    let received_bytes = self
+                        .received_bytes
+                        .fetch_add(*len as u64, Ordering::Relaxed)
+                        + *len as u64;
+                    self.bar.set_message(HumanBytes(received_bytes).to_string());
+                }
+                Event::Download | Event::Zip => self.bar.inc(1),
+                Event::Coalesced {
+                    downloads,
+                    zips,
+                    bytes,
+                } => {
+                    self.on_event(&Event::Progress(*bytes));
+                    self.bar.inc((downloads + zips) as u64);
+                }
+                Event::DataSaver(_) => {}
+                Event::Done => self.bar.finish(),
+            }
+        }
+    }
+}
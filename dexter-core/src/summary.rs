@@ -0,0 +1,186 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display},
+    time::{Duration, Instant},
+};
+
+use camino::{Utf8Path, Utf8PathBuf};
+use serde::Serialize;
+
+use crate::{
+    api::{archive_download, batch_archive_download},
+    output::Decision,
+};
+
+/// Aggregates the events of a batch download into a report, shared by the cli and the gui.
+///
+/// The paths are not part of the events, and must be recorded by the caller once the archives are written.
+#[derive(Debug, Clone, Serialize)]
+pub struct Summary {
+    pub chapters: usize,
+    pub succeeded: Vec<String>,
+    pub failed: Vec<String>,
+    pub pages_fetched: usize,
+    /// Pages whose original was missing, replaced by their data saver version, by chapter id
+    pub data_saver_pages: BTreeMap<String, Vec<String>>,
+    /// Decisions taken for the chapters whose archive already existed, by chapter id
+    pub existing: BTreeMap<String, Decision>,
+    pub bytes_downloaded: u64,
+    pub elapsed_secs: f64,
+    pub paths: Vec<Utf8PathBuf>,
+    #[serde(skip)]
+    started_at: Instant,
+}
+
+impl Default for Summary {
+    fn default() -> Self {
+        Self {
+            chapters: 0,
+            succeeded: Vec::new(),
+            failed: Vec::new(),
+            pages_fetched: 0,
+            data_saver_pages: BTreeMap::new(),
+            existing: BTreeMap::new(),
+            bytes_downloaded: 0,
+            elapsed_secs: 0.0,
+            paths: Vec::new(),
+            started_at: Instant::now(),
+        }
+    }
+}
+
+impl Summary {
+    /// Starts measuring the elapsed time
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, event: &batch_archive_download::Event) {
+        match event {
+            batch_archive_download::Event::Init(chapters) => self.chapters = *chapters,
+            batch_archive_download::Event::Chapter(chapter_id, event) => {
+                if let archive_download::Event::DataSaver(filename) = event {
+                    self.data_saver_pages
+                        .entry(chapter_id.clone())
+                        .or_default()
+                        .push(filename.clone());
+                }
+                self.record_chapter_event(event);
+            }
+            batch_archive_download::Event::ChapterDone(chapter_id) => {
+                self.succeeded.push(chapter_id.clone());
+            }
+            batch_archive_download::Event::ChapterFailed(chapter_id) => {
+                self.failed.push(chapter_id.clone());
+            }
+            batch_archive_download::Event::Done => self.finish(),
+        }
+    }
+
+    /// Records the events of a single chapter download
+    pub fn record_chapter_event(&mut self, event: &archive_download::Event) {
+        match event {
+            archive_download::Event::Progress(len) => self.bytes_downloaded += *len as u64,
+            archive_download::Event::Download => self.pages_fetched += 1,
+            archive_download::Event::Coalesced {
+                downloads, bytes, ..
+            } => {
+                self.pages_fetched += downloads;
+                self.bytes_downloaded += *bytes as u64;
+            }
+            archive_download::Event::Init(_)
+            | archive_download::Event::Zip
+            | archive_download::Event::DataSaver(_)
+            | archive_download::Event::Done => {}
+        }
+    }
+
+    /// Records a chapter that was downloaded but whose archive couldn't be written, it counts as failed
+    pub fn record_write_failure(&mut self, chapter_id: impl Into<String>) {
+        let chapter_id = chapter_id.into();
+        self.succeeded.retain(|succeeded| *succeeded != chapter_id);
+        self.failed.push(chapter_id);
+    }
+
+    /// Records the decision taken for a chapter whose archive already existed, see [`crate::IfExists`]
+    pub fn record_decision(&mut self, chapter_id: impl Into<String>, decision: Decision) {
+        self.existing.insert(chapter_id.into(), decision);
+    }
+
+    pub fn record_path(&mut self, path: impl AsRef<Utf8Path>) {
+        self.paths.push(path.as_ref().to_path_buf());
+    }
+
+    /// Stops measuring the elapsed time, called on [`batch_archive_download::Event::Done`]
+    pub fn finish(&mut self) {
+        self.elapsed_secs = self.started_at.elapsed().as_secs_f64();
+    }
+
+    #[must_use]
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_secs_f64(self.elapsed_secs)
+    }
+
+    /// Average download speed, in bytes per second
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn average_speed(&self) -> f64 {
+        if self.elapsed_secs > 0.0 {
+            self.bytes_downloaded as f64 / self.elapsed_secs
+        } else {
+            0.0
+        }
+    }
+}
+
+impl Display for Summary {
+    #[allow(clippy::cast_precision_loss)]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const MIB: f64 = 1024.0 * 1024.0;
+
+        writeln!(
+            f,
+            "Chapters: {} succeeded, {} failed, {} total",
+            self.succeeded.len(),
+            self.failed.len(),
+            self.chapters
+        )?;
+        for chapter_id in &self.failed {
+            writeln!(f, "  failed: {chapter_id}")?;
+        }
+        writeln!(f, "Pages fetched: {}", self.pages_fetched)?;
+        if !self.data_saver_pages.is_empty() {
+            writeln!(
+                f,
+                "Data saver: {} page(s) missing in full quality",
+                self.data_saver_pages.values().map(Vec::len).sum::<usize>()
+            )?;
+            for (chapter_id, filenames) in &self.data_saver_pages {
+                writeln!(f, "  {chapter_id}: {}", filenames.join(", "))?;
+            }
+        }
+        if !self.existing.is_empty() {
+            writeln!(f, "Existing archives: {}", self.existing.len())?;
+            for (chapter_id, decision) in &self.existing {
+                writeln!(f, "  {chapter_id}: {decision}")?;
+            }
+        }
+        writeln!(
+            f,
+            "Downloaded: {:.2} MiB in {:.1}s ({:.2} MiB/s)",
+            self.bytes_downloaded as f64 / MIB,
+            self.elapsed_secs,
+            self.average_speed() / MIB
+        )?;
+        write!(f, "Written:")?;
+        if self.paths.is_empty() {
+            write!(f, " none")?;
+        }
+        for path in &self.paths {
+            write!(f, "\n  {path}")?;
+        }
+
+        Ok(())
+    }
+}
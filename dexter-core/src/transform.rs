@@ -0,0 +1,144 @@
+use std::{fmt::Debug, sync::Arc};
+
+use bytes::Bytes;
+
+use crate::Result;
+
+/// A page of a chapter, once downloaded
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Page {
+    /// Filename of the page on the MD@Home node, its extension is used in the archive
+    pub filename: String,
+    pub bytes: Bytes,
+}
+
+/// Modifies the pages before they're packed, e.g. to crop or recompress them,
+/// see [`crate::ArchiveDownload::set_transforms`].
+///
+/// Transforms run on the blocking thread pool, they can be cpu intensive.
+/// A failing transform fails the whole chapter, as a page that can't be downloaded would.
+pub trait PageTransform: Debug + Send + Sync {
+    /// Returns the transformed page
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`crate::Error::Transform`] if the page can't be transformed
+    fn apply(&self, page: Page) -> Result<Page>;
+}
+
+/// Transforms applied in order, each one receiving the page returned by the previous one.
+///
+/// With the `image` feature, [`Grayscale`] and [`Downscale`] are built in.
+///
+/// Being a transform itself, a registry can be composed into another one.
+#[derive(Debug, Clone, Default)]
+pub struct Transforms(Vec<Arc<dyn PageTransform>>);
+
+impl Transforms {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a transform, run after the ones already registered
+    #[must_use]
+    pub fn with(mut self, transform: impl PageTransform + 'static) -> Self {
+        self.push(Arc::new(transform));
+        self
+    }
+
+    /// Appends a transform, run after the ones already registered
+    pub fn push(&mut self, transform: Arc<dyn PageTransform>) {
+        self.0.push(transform);
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl PageTransform for Transforms {
+    fn apply(&self, page: Page) -> Result<Page> {
+        self.0
+            .iter()
+            .try_fold(page, |page, transform| transform.apply(page))
+    }
+}
+
+/// Converts the pages to grayscale, e-ink readers can't display colors anyway
+#[cfg(feature = "image")]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Grayscale;
+
+#[cfg(feature = "image")]
+impl PageTransform for Grayscale {
+    fn apply(&self, page: Page) -> Result<Page> {
+        let (image, format) = decode(&page)?;
+        encode(page.filename, &image.grayscale(), format)
+    }
+}
+
+/// Shrinks the pages wider or taller than the bounds, e.g. the screen of a reader, keeping their aspect ratio.
+/// The smaller pages are left untouched.
+#[cfg(feature = "image")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Downscale {
+    pub max_width: u32,
+    pub max_height: u32,
+}
+
+#[cfg(feature = "image")]
+impl PageTransform for Downscale {
+    fn apply(&self, page: Page) -> Result<Page> {
+        let (image, format) = decode(&page)?;
+        if image.width() <= self.max_width && image.height() <= self.max_height {
+            return Ok(page);
+        }
+        let image = image.resize(
+            self.max_width,
+            self.max_height,
+            image::imageops::FilterType::Lanczos3,
+        );
+        encode(page.filename, &image, format)
+    }
+}
+
+/// Decodes the page, in the format of its extension or else in the one guessed from its bytes
+#[cfg(feature = "image")]
+fn decode(page: &Page) -> Result<(image::DynamicImage, image::ImageFormat)> {
+    let format = image::ImageFormat::from_path(&page.filename)
+        .or_else(|_| image::guess_format(&page.bytes))
+        .map_err(|err| crate::Error::Transform(err.into()))?;
+    let image = image::load_from_memory_with_format(&page.bytes, format)
+        .map_err(|err| crate::Error::Transform(err.into()))?;
+
+    Ok((image, format))
+}
+
+/// Encodes the page in its original format, or in png if the format can't be written
+#[cfg(feature = "image")]
+fn encode(
+    filename: String,
+    image: &image::DynamicImage,
+    format: image::ImageFormat,
+) -> Result<Page> {
+    let mut bytes = std::io::Cursor::new(Vec::new());
+    if image.write_to(&mut bytes, format).is_ok() {
+        return Ok(Page {
+            filename,
+            bytes: bytes.into_inner().into(),
+        });
+    }
+
+    let mut bytes = std::io::Cursor::new(Vec::new());
+    image
+        .write_to(&mut bytes, image::ImageFormat::Png)
+        .map_err(|err| crate::Error::Transform(err.into()))?;
+    Ok(Page {
+        filename: camino::Utf8Path::new(&filename)
+            .with_extension("png")
+            .into_string(),
+        bytes: bytes.into_inner().into(),
+    })
+}
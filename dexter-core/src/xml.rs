@@ -0,0 +1,17 @@
+/// Escapes the characters with a meaning in xml text and attribute values
+pub(crate) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for char in text.chars() {
+        match char {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // Control characters are not allowed in xml 1.0 documents
+            char if char.is_control() && !matches!(char, '\t' | '\n' | '\r') => {}
+            char => escaped.push(char),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,42 @@
+use dexter_core::ChapterNumber;
+
+#[test]
+fn decimal_order() {
+    let parse = ChapterNumber::parse;
+
+    assert!(parse("2") < parse("10"));
+    assert!(parse("10") < parse("10.05"));
+    assert!(parse("10.05") < parse("10.10"));
+    assert!(parse("10.10") < parse("10.5"));
+    assert!(parse("10.5") < parse("11"));
+    assert_ne!(parse("10.05"), parse("10.5"));
+    assert_eq!(parse("10.50"), parse("10.5"));
+    assert_eq!(parse("03"), parse("3"));
+    assert!(parse("11") < parse("Extra"));
+    assert!(parse("Extra") < parse(""));
+}
+
+#[test]
+fn extras() {
+    assert_eq!(
+        ChapterNumber::parse("1.2.3"),
+        ChapterNumber::Extra("1.2.3".to_string())
+    );
+    assert_eq!(
+        ChapterNumber::parse("Oneshot"),
+        ChapterNumber::Extra("Oneshot".to_string())
+    );
+    assert_eq!(ChapterNumber::parse(" "), ChapterNumber::Missing);
+}
+
+#[test]
+fn display() {
+    for (value, displayed) in [
+        ("10.50", "10.5"),
+        ("007", "7"),
+        ("Extra", "Extra"),
+        ("", "-"),
+    ] {
+        assert_eq!(ChapterNumber::parse(value).to_string(), displayed);
+    }
+}
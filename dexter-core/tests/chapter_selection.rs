@@ -0,0 +1,29 @@
+use dexter_core::{ChapterNumber, ChapterSelection};
+
+#[test]
+fn selected_numbers() {
+    let selection = "1-2,5-,10.5".parse::<ChapterSelection>().unwrap();
+    let contains = |number| selection.contains(&ChapterNumber::parse(number));
+
+    assert!(contains("1"));
+    assert!(contains("1.5"));
+    assert!(!contains("3"));
+    assert!(contains("5"));
+    assert!(contains("100"));
+    assert!(!contains("Extra"));
+    assert!(!contains(""));
+
+    let selection = "-3".parse::<ChapterSelection>().unwrap();
+    assert!(selection.contains(&ChapterNumber::parse("0")));
+    assert!(!selection.contains(&ChapterNumber::parse("3.5")));
+}
+
+#[test]
+fn invalid_selections() {
+    for selection in ["-", "", "1-extra", "1,,2", "Oneshot"] {
+        assert!(
+            selection.parse::<ChapterSelection>().is_err(),
+            "{selection}"
+        );
+    }
+}
@@ -0,0 +1,81 @@
+use dexter_core::{
+    api::{get_chapters, get_manga},
+    feed::atom,
+};
+
+fn chapter(id: &str, chapter: Option<&str>, title: &str, readable_at: &str) -> get_chapters::Data {
+    get_chapters::Data {
+        id: id.to_string(),
+        attributes: get_chapters::Attributes {
+            volume: Some("1".to_string()),
+            chapter: chapter.map(ToString::to_string),
+            title: Some(title.to_string()),
+            translated_language: Some("en".to_string()),
+            publish_at: None,
+            readable_at: Some(readable_at.to_string()),
+            created_at: None,
+            pages: None,
+            external_url: None,
+        },
+    }
+}
+
+#[test]
+fn atom_feed() {
+    let manga = get_manga::Data {
+        id: "7f30dfc3-0b80-4dcc-a3b9-0cd746fac005".to_string(),
+        attributes: get_manga::Attributes {
+            title: get_manga::Title {
+                en: "Kaguya & <Shirogane>".to_string(),
+            },
+        },
+    };
+    let chapters = [
+        chapter(
+            "5e4b9c7e-8d2a-4a51-9d0c-3f0f5c1b2a10",
+            Some("10"),
+            "The Tenth Case",
+            "2023-03-10T12:00:00+00:00",
+        ),
+        chapter(
+            "07bf2a09-f30d-410f-aba1-025e2d27a88f",
+            None,
+            "",
+            "2022-11-02T12:00:00+00:00",
+        ),
+    ];
+
+    let feed = atom(&manga, &chapters);
+
+    assert!(feed.starts_with(r#"<?xml version="1.0" encoding="utf-8"?>"#));
+    assert!(feed.contains("<title>Kaguya &amp; &lt;Shirogane&gt;</title>"));
+    assert!(
+        feed.contains("<id>https://mangadex.org/title/7f30dfc3-0b80-4dcc-a3b9-0cd746fac005</id>")
+    );
+    assert!(feed.contains("  <updated>2023-03-10T12:00:00+00:00</updated>"));
+    assert!(feed.contains("<author><name>MangaDex</name></author>"));
+    assert_eq!(feed.matches("<entry>").count(), 2);
+    assert!(feed.contains("<title>Vol. 1 Ch. 10 - The Tenth Case</title>"));
+    assert!(feed.contains("<title>Vol. 1 Oneshot</title>"));
+    assert!(feed.contains(
+        r#"<link href="https://mangadex.org/chapter/07bf2a09-f30d-410f-aba1-025e2d27a88f"/>"#
+    ));
+    assert!(feed.trim_end().ends_with("</feed>"));
+}
+
+#[test]
+fn empty_atom_feed() {
+    let manga = get_manga::Data {
+        id: "7f30dfc3-0b80-4dcc-a3b9-0cd746fac005".to_string(),
+        attributes: get_manga::Attributes {
+            title: get_manga::Title {
+                en: "Detective Conan".to_string(),
+            },
+        },
+    };
+
+    let feed = atom(&manga, &[]);
+
+    assert!(feed.contains("<updated>1970-01-01T00:00:00+00:00</updated>"));
+    assert!(!feed.contains("<entry>"));
+}
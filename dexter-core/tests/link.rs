@@ -0,0 +1,43 @@
+use dexter_core::{
+    link::{chapter_id, manga_id},
+    Link,
+};
+
+static MANGA_ID: &str = "7f30dfc3-0b80-4dcc-a3b9-0cd746fac005";
+static CHAPTER_ID: &str = "07bf2a09-f30d-410f-aba1-025e2d27a88f";
+
+#[test]
+fn parse_links() {
+    assert_eq!(
+        Link::parse(&format!(
+            "https://mangadex.org/title/{MANGA_ID}/detective-conan"
+        )),
+        Some(Link::Title(MANGA_ID.to_string()))
+    );
+    assert_eq!(
+        Link::parse(&format!("https://mangadex.org/chapter/{CHAPTER_ID}/3")),
+        Some(Link::Chapter(CHAPTER_ID.to_string()))
+    );
+    assert_eq!(
+        Link::parse(&format!("https://example.com/title/{MANGA_ID}")),
+        None
+    );
+    assert_eq!(Link::parse("https://mangadex.org/title/not-an-id"), None);
+    let link = Link::Chapter(CHAPTER_ID.to_string());
+    assert_eq!(Link::parse(&link.url()), Some(link));
+}
+
+#[test]
+fn resolve_ids() {
+    assert_eq!(manga_id(MANGA_ID).unwrap(), MANGA_ID);
+    assert_eq!(
+        manga_id(&format!(" https://mangadex.org/title/{MANGA_ID} ")).unwrap(),
+        MANGA_ID
+    );
+    assert_eq!(
+        chapter_id(&format!("https://mangadex.org/chapter/{CHAPTER_ID}")).unwrap(),
+        CHAPTER_ID
+    );
+    assert!(chapter_id(&format!("https://mangadex.org/title/{MANGA_ID}")).is_err());
+    assert!(manga_id("detective conan").is_err());
+}
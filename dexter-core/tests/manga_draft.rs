@@ -0,0 +1,79 @@
+use dexter_core::{
+    api::{
+        manga_draft::{MangaDraft, Status},
+        CreateManga, UpdateManga,
+    },
+    mock::FixtureMiddleware,
+    Client, Request,
+};
+use serde_json::json;
+
+static MANGA_ID: &str = "7f30dfc3-0b80-4dcc-a3b9-0cd746fac005";
+
+fn client(fixtures: &FixtureMiddleware) -> Client {
+    Client::new()
+        .with_access_token("token")
+        .with_middleware(fixtures.clone())
+}
+
+/// The json body of the only request sent
+fn body(fixtures: &FixtureMiddleware) -> serde_json::Value {
+    let bodies = fixtures.requested_bodies();
+    assert_eq!(bodies.len(), 1);
+    serde_json::from_slice(bodies[0].as_ref().unwrap()).unwrap()
+}
+
+fn draft() -> MangaDraft {
+    MangaDraft {
+        title: [("en".to_string(), "Detective Conan".to_string())].into(),
+        status: Some(Status::Ongoing),
+        year: Some(1994),
+        ..MangaDraft::default()
+    }
+}
+
+#[tokio::test]
+async fn create_manga() {
+    let fixtures =
+        FixtureMiddleware::new().with_fixture("/manga", include_str!("fixtures/manga.json"));
+
+    let response = CreateManga::new(draft())
+        .request_with(&client(&fixtures))
+        .await
+        .unwrap();
+
+    assert_eq!(response.data.id, MANGA_ID);
+    // The unset fields are left out of the body
+    assert_eq!(
+        body(&fixtures),
+        json!({
+            "title": { "en": "Detective Conan" },
+            "status": "ongoing",
+            "year": 1994,
+        })
+    );
+}
+
+#[tokio::test]
+async fn update_manga() {
+    let fixtures = FixtureMiddleware::new().with_fixture(
+        format!("/manga/{MANGA_ID}"),
+        include_str!("fixtures/manga.json"),
+    );
+
+    let response = UpdateManga::new(MANGA_ID, draft(), 3)
+        .request_with(&client(&fixtures))
+        .await
+        .unwrap();
+
+    assert_eq!(response.data.attributes.title.en, "Detective Conan");
+    assert_eq!(
+        body(&fixtures),
+        json!({
+            "title": { "en": "Detective Conan" },
+            "status": "ongoing",
+            "year": 1994,
+            "version": 3,
+        })
+    );
+}
@@ -0,0 +1,55 @@
+use dexter_core::naming::{
+    file_name, file_name_with_extension, series_name, MAX_FILE_NAME_LEN, MAX_SERIES_LEN,
+};
+
+#[test]
+fn forbidden_characters() {
+    assert_eq!(file_name("Re:Zero / Arc 1?"), "ReZero  Arc 1");
+    assert_eq!(file_name("\"Oshi no Ko\"\n"), "Oshi no Ko");
+    assert_eq!(file_name("..."), "_");
+    assert_eq!(file_name(""), "_");
+}
+
+#[test]
+fn reserved_names() {
+    assert_eq!(file_name("CON"), "CON_");
+    assert_eq!(file_name("nul.txt"), "nul_.txt");
+    assert_eq!(file_name("Lpt1 "), "Lpt1_");
+    assert_eq!(file_name("Console"), "Console");
+    assert_eq!(file_name_with_extension("aux", "cbz"), "aux_.cbz");
+}
+
+#[test]
+fn trailing_dots_and_spaces() {
+    assert_eq!(file_name("  Dr. Stone Vol.  "), "Dr. Stone Vol");
+    assert_eq!(
+        file_name_with_extension("What's up...", "cbz"),
+        "What's up.cbz"
+    );
+}
+
+#[test]
+fn long_names() {
+    let title = "ね".repeat(200);
+
+    let name = file_name(&title);
+    assert!(name.len() < MAX_FILE_NAME_LEN);
+    assert!(title.starts_with(&name));
+
+    let name = file_name_with_extension(&title, "cbz");
+    assert!(name.len() <= MAX_FILE_NAME_LEN);
+    assert!(name.ends_with("ね.cbz"));
+}
+
+#[test]
+fn long_series() {
+    let series = "The Daily Life of the Immortal King Who Reincarnated as a Slime and Opened a Cafe in Another World";
+
+    let name = series_name(series);
+    assert_eq!(
+        name,
+        "The Daily Life of the Immortal King Who Reincarnated as a Slime and Opened a"
+    );
+    assert!(name.chars().count() <= MAX_SERIES_LEN);
+    assert_eq!(series_name("Detective Conan"), "Detective Conan");
+}
@@ -0,0 +1,169 @@
+use camino::{Utf8Path, Utf8PathBuf};
+use dexter_core::{
+    output::{available_space, ensure_available_space, rename_no_clobber, write_bytes_atomically},
+    write_atomically, write_atomically_with_comic_info, ComicInfo, Error, WritePolicy,
+};
+use eco_cbz::CbzWriter;
+
+#[test]
+fn insufficient_space() {
+    let dir = Utf8PathBuf::try_from(std::env::temp_dir()).unwrap();
+
+    ensure_available_space(&dir, 0).unwrap();
+    if let Some(available) = available_space(&dir).unwrap() {
+        assert!(matches!(
+            ensure_available_space(&dir, available.saturating_add(1 << 40)),
+            Err(Error::InsufficientSpace { .. })
+        ));
+    }
+}
+
+#[test]
+fn comic_info() {
+    let path = Utf8PathBuf::try_from(std::env::temp_dir())
+        .unwrap()
+        .join(format!("dexter-comic-info-{}.cbz", std::process::id()));
+    let mut cbz_writer = CbzWriter::default();
+    cbz_writer
+        .insert_bytes_with_extension(b"page", "png")
+        .unwrap();
+    let comic_info = ComicInfo {
+        series: Some("Kaguya & Shirogane".to_string()),
+        number: Some("1".to_string()),
+        language_iso: Some("en".to_string()),
+        ..ComicInfo::default()
+    };
+
+    write_atomically_with_comic_info(cbz_writer, &path, WritePolicy::Overwrite, Some(&comic_info))
+        .unwrap();
+
+    let mut archive = zip::ZipArchive::new(std::fs::File::open(&path).unwrap()).unwrap();
+    assert_eq!(archive.len(), 2);
+    let mut xml = String::new();
+    std::io::Read::read_to_string(&mut archive.by_name("ComicInfo.xml").unwrap(), &mut xml)
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(xml.contains("<Series>Kaguya &amp; Shirogane</Series>"));
+    assert!(xml.contains("<Number>1</Number>"));
+    assert!(xml.contains("<LanguageISO>en</LanguageISO>"));
+    assert!(!xml.contains("<Volume>"));
+}
+
+fn temp_path(name: &str) -> Utf8PathBuf {
+    Utf8PathBuf::try_from(std::env::temp_dir())
+        .unwrap()
+        .join(format!("dexter-{name}-{}", std::process::id()))
+}
+
+/// Counts the temporary files left next to `path`
+fn leftovers(path: &Utf8Path) -> usize {
+    let prefix = format!("{}.", path.file_name().unwrap());
+    std::fs::read_dir(path.parent().unwrap())
+        .unwrap()
+        .filter_map(|entry| entry.unwrap().file_name().into_string().ok())
+        .filter(|name| name.starts_with(&prefix) && name.ends_with(".part"))
+        .count()
+}
+
+fn archive() -> CbzWriter<std::io::Cursor<Vec<u8>>> {
+    let mut cbz_writer = CbzWriter::default();
+    cbz_writer
+        .insert_bytes_with_extension(b"page", "png")
+        .unwrap();
+    cbz_writer
+}
+
+#[test]
+fn overwrite_policy() {
+    let path = temp_path("overwrite.cbz");
+    std::fs::write(&path, b"previous archive").unwrap();
+
+    write_atomically(archive(), &path, WritePolicy::Overwrite).unwrap();
+
+    let archive = zip::ZipArchive::new(std::fs::File::open(&path).unwrap()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(archive.len(), 1);
+    assert_eq!(leftovers(&path), 0);
+}
+
+#[test]
+fn no_clobber_policy() {
+    let path = temp_path("no-clobber.cbz");
+
+    write_atomically(archive(), &path, WritePolicy::NoClobber).unwrap();
+    let res = write_atomically(archive(), &path, WritePolicy::NoClobber);
+    let written = std::fs::metadata(&path).unwrap().len();
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(matches!(res, Err(Error::AlreadyExists(existing)) if existing == path));
+    assert!(written > 0);
+    assert_eq!(leftovers(&path), 0);
+}
+
+#[test]
+fn bytes() {
+    let path = temp_path("feed.xml");
+
+    write_bytes_atomically("<feed/>", &path, WritePolicy::Overwrite).unwrap();
+    write_bytes_atomically("<feed></feed>", &path, WritePolicy::Overwrite).unwrap();
+    let res = write_bytes_atomically("<feed/>", &path, WritePolicy::NoClobber);
+    let written = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(matches!(res, Err(Error::AlreadyExists(_))));
+    assert_eq!(written, "<feed></feed>");
+}
+
+#[test]
+fn rename_without_clobbering() {
+    let from = temp_path("renamed-from.txt");
+    let to = temp_path("renamed-to.txt");
+
+    std::fs::write(&from, "first").unwrap();
+    rename_no_clobber(&from, &to).unwrap();
+    std::fs::write(&from, "second").unwrap();
+    let res = rename_no_clobber(&from, &to);
+    let kept = std::fs::read_to_string(&from).unwrap();
+    let written = std::fs::read_to_string(&to).unwrap();
+    std::fs::remove_file(&from).unwrap();
+    std::fs::remove_file(&to).unwrap();
+
+    assert!(matches!(res, Err(Error::AlreadyExists(existing)) if existing == to));
+    assert_eq!(kept, "second");
+    assert_eq!(written, "first");
+}
+
+#[test]
+fn concurrent_no_clobber() {
+    let path = temp_path("concurrent.txt");
+
+    let results = std::thread::scope(|scope| {
+        let writers = (0..8)
+            .map(|index| {
+                let path = &path;
+                scope.spawn(move || {
+                    write_bytes_atomically(index.to_string(), path, WritePolicy::NoClobber)
+                        .map(|()| index)
+                })
+            })
+            .collect::<Vec<_>>();
+        writers
+            .into_iter()
+            .map(|writer| writer.join().unwrap())
+            .collect::<Vec<_>>()
+    });
+    let written = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    // Exactly one writer wins, and its file is never replaced by another one
+    let winners = results
+        .iter()
+        .filter_map(|res| res.as_ref().ok())
+        .collect::<Vec<_>>();
+    assert_eq!(winners.len(), 1);
+    assert_eq!(written, winners[0].to_string());
+    assert!(results
+        .iter()
+        .all(|res| matches!(res, Ok(_) | Err(Error::AlreadyExists(_)))));
+    assert_eq!(leftovers(&path), 0);
+}
@@ -0,0 +1,57 @@
+use dexter_core::{
+    api::{archive_download, batch_archive_download::Event},
+    progress::{BoundedSender, ProgressSink},
+};
+use tokio::sync::mpsc;
+
+static FIRST_CHAPTER_ID: &str = "07bf2a09-f30d-410f-aba1-025e2d27a88f";
+static SECOND_CHAPTER_ID: &str = "5e4b9c7e-8d2a-4a51-9d0c-3f0f5c1b2a10";
+
+fn chapter(chapter_id: &str, event: archive_download::Event) -> Event {
+    Event::Chapter(chapter_id.to_string(), event)
+}
+
+#[tokio::test]
+async fn interleaved_chapters() {
+    let (tx, mut rx) = mpsc::channel(1);
+    let sender = BoundedSender::new(tx);
+
+    // Nothing is received while the chapters download concurrently
+    sender.on_event(&Event::Init(2));
+    for chapter_id in [FIRST_CHAPTER_ID, SECOND_CHAPTER_ID] {
+        sender.on_event(&chapter(chapter_id, archive_download::Event::Init(500)));
+    }
+    for _ in 0..500 {
+        for chapter_id in [FIRST_CHAPTER_ID, SECOND_CHAPTER_ID] {
+            sender.on_event(&chapter(chapter_id, archive_download::Event::Progress(10)));
+            sender.on_event(&chapter(chapter_id, archive_download::Event::Download));
+            sender.on_event(&chapter(chapter_id, archive_download::Event::Zip));
+        }
+    }
+    // The batch init, and the init and the merged events of each chapter
+    assert!(sender.pending() <= 5, "{} events queued", sender.pending());
+
+    drop(sender);
+    let mut events = Vec::new();
+    while let Some(event) = rx.recv().await {
+        events.push(event);
+    }
+    assert_eq!(events.first(), Some(&Event::Init(2)));
+    for chapter_id in [FIRST_CHAPTER_ID, SECOND_CHAPTER_ID] {
+        let merged = events
+            .iter()
+            .filter_map(|event| match event {
+                Event::Chapter(
+                    id,
+                    archive_download::Event::Coalesced {
+                        downloads,
+                        zips,
+                        bytes,
+                    },
+                ) if id == chapter_id => Some((*downloads, *zips, *bytes)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(merged, [(500, 500, 5000)]);
+    }
+}
@@ -0,0 +1,881 @@
+use std::{sync::Arc, time::Duration};
+
+use bytes::Bytes;
+use camino::Utf8PathBuf;
+use dexter_core::{
+    api::{archive_download, batch_archive_download, get_aggregate, get_chapters, GetChapterById},
+    mock::FixtureMiddleware,
+    output::Decision,
+    page_store::PageStore,
+    progress::NoProgress,
+    transform::{Page, PageTransform, Transforms},
+    ArchiveDownload, BatchArchiveDownload, ChapterNumber, ChapterSelection, Client, Dexter, Error,
+    GetAggregate, GetChapters, GetImageLinks, GetManga, IfExists, Request, Search, SearchGroups,
+};
+use futures::{StreamExt, TryStreamExt};
+use http::StatusCode;
+use tokio::sync::mpsc;
+
+static MANGA_ID: &str = "7f30dfc3-0b80-4dcc-a3b9-0cd746fac005";
+static CHAPTER_ID: &str = "07bf2a09-f30d-410f-aba1-025e2d27a88f";
+static GROUP_ID: &str = "2b9c4f3e-8a1d-4e6f-9c7b-5d3a2e1f0b4c";
+static CHAPTER_HASH: &str = "3c1e0b9f5d7a4e2b8c6d0f1a2b3c4d5e";
+
+/// Pages of the chapter listed in `fixtures/at_home.json`
+static FIRST_PAGE: &str = "1-0a1b2c3d4e5f.png";
+static SECOND_PAGE: &str = "2-6a7b8c9d0e1f.jpg";
+
+fn client(fixtures: &FixtureMiddleware) -> Client {
+    Client::new().with_middleware(fixtures.clone())
+}
+
+/// The MD@Home node serving the chapter, without its pages
+fn at_home_fixtures() -> FixtureMiddleware {
+    FixtureMiddleware::new().with_fixture(
+        format!("/at-home/server/{CHAPTER_ID}"),
+        include_str!("fixtures/at_home.json"),
+    )
+}
+
+/// The MD@Home node serving the chapter, and its two pages
+fn chapter_fixtures() -> FixtureMiddleware {
+    at_home_fixtures()
+        .with_fixture(
+            format!("/data/{CHAPTER_HASH}/{FIRST_PAGE}"),
+            &b"first page"[..],
+        )
+        .with_fixture(
+            format!("/data/{CHAPTER_HASH}/{SECOND_PAGE}"),
+            &b"second page"[..],
+        )
+}
+
+/// Counts the downloaded pages, the packed pages, and the received bytes, including the coalesced events
+fn tally(events: &[archive_download::Event]) -> (usize, usize, usize) {
+    events
+        .iter()
+        .fold((0, 0, 0), |(d, z, b), event| match event {
+            archive_download::Event::Download => (d + 1, z, b),
+            archive_download::Event::Zip => (d, z + 1, b),
+            archive_download::Event::Progress(len) => (d, z, b + len),
+            archive_download::Event::Coalesced {
+                downloads,
+                zips,
+                bytes,
+            } => (d + downloads, z + zips, b + bytes),
+            archive_download::Event::Init(_)
+            | archive_download::Event::DataSaver(_)
+            | archive_download::Event::Done => (d, z, b),
+        })
+}
+
+fn query_pairs(fixtures: &FixtureMiddleware) -> Vec<(String, String)> {
+    let urls = fixtures.requested_urls();
+    assert_eq!(urls.len(), 1);
+    urls[0]
+        .query_pairs()
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect()
+}
+
+#[tokio::test]
+async fn search() {
+    let fixtures =
+        FixtureMiddleware::new().with_fixture("/manga", include_str!("fixtures/search.json"));
+
+    let response = Search::new("conan")
+        .with_limit(2)
+        .request_with(&client(&fixtures))
+        .await
+        .unwrap();
+
+    assert_eq!(response.data.len(), 2);
+    assert_eq!(response.data[0].id, MANGA_ID);
+    assert_eq!(response.data[0].attributes.title.en, "Detective Conan");
+    assert_eq!(
+        response.data[0]
+            .attributes
+            .localized_title(&["ko", "ja-ro", "en"]),
+        "Meitantei Conan"
+    );
+    assert_eq!(
+        response.data[0].attributes.localized_description(&["de"]),
+        Some("Shinichi Kudo is a high school detective.")
+    );
+    assert_eq!(response.data[1].attributes.content_rating, None);
+    assert_eq!(
+        query_pairs(&fixtures),
+        [
+            ("title".to_string(), "conan".to_string()),
+            ("order[relevance]".to_string(), "desc".to_string()),
+            ("limit".to_string(), "2".to_string()),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn get_manga() {
+    let fixtures = FixtureMiddleware::new().with_fixture(
+        format!("/manga/{MANGA_ID}"),
+        include_str!("fixtures/manga.json"),
+    );
+
+    let response = GetManga::new(MANGA_ID)
+        .request_with(&client(&fixtures))
+        .await
+        .unwrap();
+
+    assert_eq!(response.data.id, MANGA_ID);
+    assert_eq!(response.data.attributes.title.en, "Detective Conan");
+}
+
+#[tokio::test]
+async fn get_chapters() {
+    let fixtures =
+        FixtureMiddleware::new().with_fixture("/chapter", include_str!("fixtures/chapters.json"));
+
+    let mut response = GetChapters::new(MANGA_ID)
+        .set_offset(100)
+        .with_volumes(["1"])
+        .with_chapters(["1", "2"])
+        .push_language("en")
+        .push_group(GROUP_ID)
+        .request_with(&client(&fixtures))
+        .await
+        .unwrap();
+
+    assert_eq!(response.total, 4);
+    assert_eq!(
+        query_pairs(&fixtures),
+        [
+            ("manga".to_string(), MANGA_ID.to_string()),
+            ("limit".to_string(), "100".to_string()),
+            ("order[chapter]".to_string(), "desc".to_string()),
+            ("offset".to_string(), "100".to_string()),
+            ("chapter[]".to_string(), "1".to_string()),
+            ("chapter[]".to_string(), "2".to_string()),
+            ("translatedLanguage[]".to_string(), "en".to_string()),
+            ("volume[]".to_string(), "1".to_string()),
+            ("groups[]".to_string(), GROUP_ID.to_string()),
+        ]
+    );
+
+    response.data.sort_by(|a, b| a.cmp_by_number(b));
+    let numbers = response
+        .data
+        .iter()
+        .map(|chapter| chapter.attributes.chapter_number())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        numbers,
+        [
+            ChapterNumber::Numeric(1, String::new()),
+            ChapterNumber::Numeric(2, "5".to_string()),
+            ChapterNumber::Numeric(10, String::new()),
+            ChapterNumber::Missing,
+        ]
+    );
+}
+
+#[tokio::test]
+async fn get_chapters_by_publication_date() {
+    let fixtures =
+        FixtureMiddleware::new().with_fixture("/chapter", include_str!("fixtures/chapters.json"));
+
+    let response = GetChapters::new(MANGA_ID)
+        .set_order(get_chapters::Order::PublishAt)
+        .request_with(&client(&fixtures))
+        .await
+        .unwrap();
+
+    assert!(query_pairs(&fixtures).contains(&("order[publishAt]".to_string(), "desc".to_string())));
+    assert_eq!(
+        response.data[0].attributes.publish_at.as_deref(),
+        Some("2023-03-10T12:00:00+00:00")
+    );
+    assert_eq!(
+        response.data[0].attributes.created_at.as_deref(),
+        Some("2023-03-10T09:30:00+00:00")
+    );
+}
+
+#[tokio::test]
+async fn get_all_chapters() {
+    // The fixture holds 4 chapters out of 8, so that a second page is requested
+    let fixtures = FixtureMiddleware::new().with_fixture(
+        "/chapter",
+        include_str!("fixtures/chapters.json").replace("\"total\": 4", "\"total\": 8"),
+    );
+
+    let chapters = GetChapters::new(MANGA_ID)
+        .set_limit(4)
+        .request_all_with(&client(&fixtures))
+        .try_collect::<Vec<_>>()
+        .await
+        .unwrap();
+
+    assert_eq!(chapters.len(), 8);
+    let offsets = fixtures
+        .requested_urls()
+        .iter()
+        .map(|url| {
+            url.query_pairs()
+                .find(|(key, _)| key == "offset")
+                .map(|(_, value)| value.into_owned())
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(offsets, [None, Some("4".to_string())]);
+}
+
+#[tokio::test]
+async fn custom_api_url() {
+    let fixtures = FixtureMiddleware::new().with_fixture(
+        format!("/mirror/api/manga/{MANGA_ID}"),
+        include_str!("fixtures/manga.json"),
+    );
+
+    GetManga::new(MANGA_ID)
+        .request_with(
+            &client(&fixtures).with_api_url("https://example.com/mirror/api".parse().unwrap()),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(
+        fixtures.requested_urls()[0].as_str(),
+        format!("https://example.com/mirror/api/manga/{MANGA_ID}")
+    );
+}
+
+#[tokio::test]
+async fn search_groups() {
+    let fixtures =
+        FixtureMiddleware::new().with_fixture("/group", include_str!("fixtures/groups.json"));
+
+    let response = SearchGroups::new("example")
+        .with_limit(10)
+        .request_with(&client(&fixtures))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        query_pairs(&fixtures),
+        [
+            ("name".to_string(), "example".to_string()),
+            ("order[relevance]".to_string(), "desc".to_string()),
+            ("limit".to_string(), "10".to_string()),
+        ]
+    );
+    assert_eq!(response.total, 1);
+    assert_eq!(response.data[0].id, GROUP_ID);
+    assert_eq!(response.data[0].attributes.name, "Example Scans");
+}
+
+#[tokio::test]
+async fn get_aggregate() {
+    let fixtures = FixtureMiddleware::new().with_fixture(
+        format!("/manga/{MANGA_ID}/aggregate"),
+        include_str!("fixtures/aggregate.json"),
+    );
+
+    let response = GetAggregate::new(MANGA_ID)
+        .push_language("en")
+        .request_with(&client(&fixtures))
+        .await
+        .unwrap();
+
+    assert_eq!(response.volumes.len(), 3);
+    assert!(response.volumes["3"].chapters.is_empty());
+    assert_eq!(response.volumes["1"].chapters["2"].id, CHAPTER_ID);
+    assert_eq!(
+        query_pairs(&fixtures),
+        [("translatedLanguage[]".to_string(), "en".to_string())]
+    );
+}
+
+#[tokio::test]
+async fn get_aggregate_gaps_and_selection() {
+    let fixtures = FixtureMiddleware::new().with_fixture(
+        format!("/manga/{MANGA_ID}/aggregate"),
+        include_str!("fixtures/aggregate.json"),
+    );
+
+    let response = GetAggregate::new(MANGA_ID)
+        .request_with(&client(&fixtures))
+        .await
+        .unwrap();
+
+    let numbers = response
+        .chapters()
+        .map(get_aggregate::Chapter::chapter_number)
+        .collect::<Vec<_>>();
+    assert_eq!(ChapterNumber::gaps(&numbers), [3, 5, 6]);
+
+    let selection = "1-2,5-".parse::<ChapterSelection>().unwrap();
+    let selected = |latest| {
+        response
+            .select(Some(&selection), latest)
+            .into_iter()
+            .map(|chapter| chapter.chapter.as_str())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(selected(None), ["1", "2", "5.5", "7"]);
+    assert_eq!(selected(Some(2)), ["5.5", "7"]);
+}
+
+#[tokio::test]
+async fn get_image_links() {
+    let fixtures = at_home_fixtures();
+
+    let image_links = GetImageLinks::new(CHAPTER_ID)
+        .request_with(&client(&fixtures))
+        .await
+        .unwrap();
+
+    assert_eq!(image_links.len(), 2);
+    assert_eq!(image_links[0].filename, FIRST_PAGE);
+    assert_eq!(
+        image_links[0].url,
+        format!("https://uploads.mangadex.org/data/{CHAPTER_HASH}/{FIRST_PAGE}")
+    );
+    assert_eq!(
+        image_links[0].data_saver_url.as_deref(),
+        Some(
+            format!("https://uploads.mangadex.org/data-saver/{CHAPTER_HASH}/1-0a1b2c3d4e5f.jpg")
+                .as_str()
+        )
+    );
+}
+
+#[tokio::test]
+async fn archive_download() {
+    let fixtures = chapter_fixtures();
+    let (tx, mut rx) = mpsc::unbounded_channel();
+
+    ArchiveDownload::new(CHAPTER_ID)
+        .set_sender(tx)
+        .request_with(&client(&fixtures))
+        .await
+        .unwrap();
+
+    let mut events = Vec::new();
+    while let Some(event) = rx.recv().await {
+        events.push(event);
+    }
+    assert_eq!(events.first(), Some(&archive_download::Event::Init(2)));
+    assert_eq!(events.last(), Some(&archive_download::Event::Done));
+    assert_eq!(
+        tally(&events),
+        (2, 2, "first page".len() + "second page".len())
+    );
+    assert!(!events
+        .iter()
+        .any(|event| matches!(event, archive_download::Event::DataSaver(_))));
+    assert_eq!(fixtures.requested_urls().len(), 3);
+}
+
+/// Uppercases the pages, and fails on the ones named after `fail_on`
+#[derive(Debug)]
+struct Uppercase {
+    fail_on: &'static str,
+}
+
+impl PageTransform for Uppercase {
+    fn apply(&self, page: Page) -> Result<Page, Error> {
+        if page.filename.starts_with(self.fail_on) {
+            return Err(Error::Transform("unsupported page".into()));
+        }
+        Ok(Page {
+            bytes: page.bytes.to_ascii_uppercase().into(),
+            ..page
+        })
+    }
+}
+
+#[tokio::test]
+async fn archive_download_with_transforms() {
+    let fixtures = chapter_fixtures();
+
+    let cbz_writer = ArchiveDownload::new(CHAPTER_ID)
+        .set_transforms(Transforms::new().with(Uppercase { fail_on: "3-" }))
+        .request_with(&client(&fixtures))
+        .await
+        .unwrap();
+
+    let mut bytes = Vec::new();
+    cbz_writer.write_to(&mut bytes).unwrap();
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes)).unwrap();
+    assert_eq!(archive.len(), 2);
+    let mut page = String::new();
+    std::io::Read::read_to_string(&mut archive.by_index(0).unwrap(), &mut page).unwrap();
+    assert_eq!(page, "FIRST PAGE");
+
+    // A page the transform fails on fails the whole chapter
+    let res = ArchiveDownload::new(CHAPTER_ID)
+        .set_transforms(Transforms::new().with(Uppercase { fail_on: "2-" }))
+        .request_with(&client(&fixtures))
+        .await;
+    assert!(matches!(res, Err(Error::Transform(_))));
+}
+
+#[tokio::test]
+async fn archive_download_failover() {
+    let fixtures = chapter_fixtures()
+        // The first node fails on the first page, even once retried, the second one serves it
+        .with_failures(
+            format!("/data/{CHAPTER_HASH}/{FIRST_PAGE}"),
+            StatusCode::INTERNAL_SERVER_ERROR,
+            2,
+        );
+
+    let cbz_writer = ArchiveDownload::new(CHAPTER_ID)
+        .set_max_download_retries(0)
+        .request_with(&client(&fixtures))
+        .await
+        .unwrap();
+
+    let mut bytes = Vec::new();
+    cbz_writer.write_to(&mut bytes).unwrap();
+    let archive = zip::ZipArchive::new(std::io::Cursor::new(bytes)).unwrap();
+    assert_eq!(archive.len(), 2);
+    let at_home_requests = fixtures
+        .requested_urls()
+        .iter()
+        .filter(|url| url.path().starts_with("/at-home/server/"))
+        .count();
+    assert_eq!(at_home_requests, 2);
+
+    // A page no node can serve fails the chapter instead of being left out of the archive
+    let fixtures = fixtures.with_failures(
+        format!("/data/{CHAPTER_HASH}/{FIRST_PAGE}"),
+        StatusCode::INTERNAL_SERVER_ERROR,
+        usize::MAX,
+    );
+    let res = ArchiveDownload::new(CHAPTER_ID)
+        .set_max_download_retries(0)
+        .request_with(&client(&fixtures))
+        .await;
+    assert!(matches!(res, Err(Error::Reqwest(_))));
+}
+
+#[tokio::test]
+async fn archive_download_with_page_store() {
+    const STORED_PAGE: &str =
+        "1-845bb60fe5c91b77a0b634e351b296a9222c94d686371b0ad741dff73c95edbb.png";
+    let fixtures = FixtureMiddleware::new()
+        .with_fixture(
+            format!("/at-home/server/{CHAPTER_ID}"),
+            include_str!("fixtures/at_home_checksums.json"),
+        )
+        .with_fixture(
+            format!("/data/{CHAPTER_HASH}/{STORED_PAGE}"),
+            &b"first page"[..],
+        )
+        .with_fixture(
+            format!(
+                "/data/{CHAPTER_HASH}/2-9500210b0cfd2a6521c015c59c7ad9bc8578f6d363741b6df73821b29672a343.jpg"
+            ),
+            &b"second page"[..],
+        );
+    let dir = Utf8PathBuf::try_from(std::env::temp_dir())
+        .unwrap()
+        .join(format!("dexter-page-store-{}", std::process::id()));
+    let page_store = Arc::new(PageStore::new(&dir).unwrap());
+    let client = client(&fixtures);
+    let download = || {
+        ArchiveDownload::new(CHAPTER_ID)
+            .set_page_store(Arc::clone(&page_store))
+            .request_with(&client)
+    };
+
+    download().await.unwrap();
+    download().await.unwrap();
+    assert_eq!(page_store.pages_reused(), 2);
+    assert_eq!(
+        page_store.bytes_saved(),
+        ("first page".len() + "second page".len()) as u64
+    );
+    // Only the MD@Home node is requested the second time
+    assert_eq!(fixtures.requested_urls().len(), 4);
+
+    // A corrupted page is evicted and downloaded again
+    let stored_page = dir
+        .join(PageStore::checksum(STORED_PAGE).unwrap())
+        .with_extension("png");
+    std::fs::write(&stored_page, b"corrupted").unwrap();
+    download().await.unwrap();
+    assert_eq!(page_store.pages_reused(), 3);
+    assert_eq!(fixtures.requested_urls().len(), 6);
+    assert_eq!(std::fs::read(&stored_page).unwrap(), b"first page");
+
+    // Pages not matching their checksum are not stored
+    std::fs::remove_file(&stored_page).unwrap();
+    page_store
+        .insert(STORED_PAGE, &Bytes::from_static(b"tampered"))
+        .await;
+    assert!(page_store.get(STORED_PAGE).await.is_none());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+    // The pages of the other fixtures don't have a sha256 checksum
+    assert_eq!(PageStore::checksum(FIRST_PAGE), None);
+}
+
+#[tokio::test]
+async fn archive_download_with_bounded_sender() {
+    let fixtures = chapter_fixtures();
+    let (tx, mut rx) = mpsc::channel(1);
+
+    // Nothing is received until the download is over, the events are merged in the meantime
+    ArchiveDownload::new(CHAPTER_ID)
+        .set_bounded_sender(tx)
+        .request_with(&client(&fixtures))
+        .await
+        .unwrap();
+
+    let mut events = Vec::new();
+    while let Some(event) = rx.recv().await {
+        events.push(event);
+    }
+    assert_eq!(events.first(), Some(&archive_download::Event::Init(2)));
+    assert_eq!(events.last(), Some(&archive_download::Event::Done));
+    assert!(events
+        .iter()
+        .any(|event| matches!(event, archive_download::Event::Coalesced { .. })));
+    assert_eq!(
+        tally(&events),
+        (2, 2, "first page".len() + "second page".len())
+    );
+}
+
+#[tokio::test]
+async fn batch_archive_download() {
+    // No MD@Home node is known for the second chapter, it fails without affecting the first one
+    static MISSING_CHAPTER_ID: &str = "5c2d8e41-6f3a-4b7c-9d0e-1a2b3c4d5e6f";
+    let fixtures = chapter_fixtures();
+    let (tx, mut rx) = mpsc::unbounded_channel();
+
+    let mut downloads: Vec<_> = BatchArchiveDownload::new([CHAPTER_ID, MISSING_CHAPTER_ID])
+        .set_sender(tx)
+        .request_with(&client(&fixtures))
+        .await
+        .unwrap()
+        .collect()
+        .await;
+    downloads.sort_by(|(a, _), (b, _)| a.cmp(b));
+    assert_eq!(downloads.len(), 2);
+    assert_eq!(downloads[0].0, CHAPTER_ID);
+    assert!(downloads[0].1.is_ok());
+    assert_eq!(downloads[1].0, MISSING_CHAPTER_ID);
+    assert!(downloads[1].1.is_err());
+
+    let mut events = Vec::new();
+    while let Some(event) = rx.recv().await {
+        events.push(event);
+    }
+    assert_eq!(
+        events.first(),
+        Some(&batch_archive_download::Event::Init(2))
+    );
+    assert_eq!(events.last(), Some(&batch_archive_download::Event::Done));
+    let chapter_events: Vec<_> = events
+        .iter()
+        .filter_map(|event| match event {
+            batch_archive_download::Event::Chapter(chapter_id, event)
+                if chapter_id == CHAPTER_ID =>
+            {
+                Some(event.clone())
+            }
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        chapter_events.first(),
+        Some(&archive_download::Event::Init(2))
+    );
+    assert_eq!(chapter_events.last(), Some(&archive_download::Event::Done));
+    assert_eq!(
+        tally(&chapter_events),
+        (2, 2, "first page".len() + "second page".len())
+    );
+    assert!(events.contains(&batch_archive_download::Event::ChapterDone(
+        CHAPTER_ID.to_string()
+    )));
+    assert!(
+        events.contains(&batch_archive_download::Event::ChapterFailed(
+            MISSING_CHAPTER_ID.to_string()
+        ))
+    );
+    assert!(
+        !events.contains(&batch_archive_download::Event::ChapterDone(
+            MISSING_CHAPTER_ID.to_string()
+        ))
+    );
+}
+
+#[tokio::test]
+async fn external_chapter() {
+    // External chapters are listed with 0 pages, and the MD@Home node serves none
+    static EXTERNAL_CHAPTER_ID: &str = "3a9f8e7d-6c5b-4a3f-9e2d-1c0b9a8f7e6d";
+    let fixtures = chapter_fixtures()
+        .with_fixture(
+            format!("/chapter/{EXTERNAL_CHAPTER_ID}"),
+            include_str!("fixtures/chapter.json").replace(
+                "\"pages\": 2,",
+                "\"pages\": 0, \"externalUrl\": \"https://example.com/chapter/1\",",
+            ),
+        )
+        .with_fixture(
+            format!("/at-home/server/{EXTERNAL_CHAPTER_ID}"),
+            include_str!("fixtures/at_home_external.json"),
+        );
+    let client = client(&fixtures);
+
+    let chapter = GetChapterById::new(EXTERNAL_CHAPTER_ID)
+        .request_with(&client)
+        .await
+        .unwrap();
+    assert!(!chapter.data.attributes.is_hosted());
+
+    let res = ArchiveDownload::new(EXTERNAL_CHAPTER_ID)
+        .request_with(&client)
+        .await;
+    assert!(
+        matches!(res, Err(Error::Unavailable(chapter_id)) if chapter_id == EXTERNAL_CHAPTER_ID)
+    );
+
+    // The batch reports the external chapter as failed instead of waiting for its pages forever
+    let mut downloads: Vec<_> = tokio::time::timeout(Duration::from_secs(5), async {
+        BatchArchiveDownload::new([CHAPTER_ID, EXTERNAL_CHAPTER_ID])
+            .request_with(&client)
+            .await
+            .unwrap()
+            .collect::<Vec<_>>()
+            .await
+    })
+    .await
+    .unwrap();
+    downloads.sort_by(|(a, _), (b, _)| a.cmp(b));
+    assert_eq!(downloads[0].0, CHAPTER_ID);
+    assert!(downloads[0].1.is_ok());
+    assert_eq!(downloads[1].0, EXTERNAL_CHAPTER_ID);
+    assert!(matches!(downloads[1].1, Err(Error::Unavailable(_))));
+}
+
+#[tokio::test]
+async fn batch_archive_download_with_bounded_sender() {
+    let fixtures = chapter_fixtures();
+    let (tx, mut rx) = mpsc::channel(1);
+
+    let downloads: Vec<_> = BatchArchiveDownload::new([CHAPTER_ID])
+        .set_bounded_sender(tx)
+        .request_with(&client(&fixtures))
+        .await
+        .unwrap()
+        .collect()
+        .await;
+    assert_eq!(downloads.len(), 1);
+    assert!(downloads[0].1.is_ok());
+
+    let mut events = Vec::new();
+    while let Some(event) = rx.recv().await {
+        events.push(event);
+    }
+    assert_eq!(
+        events.first(),
+        Some(&batch_archive_download::Event::Init(1))
+    );
+    assert_eq!(events.last(), Some(&batch_archive_download::Event::Done));
+    // The events of the chapter are merged, without crossing the chapter boundaries
+    let chapter_events: Vec<_> = events
+        .iter()
+        .filter_map(|event| match event {
+            batch_archive_download::Event::Chapter(chapter_id, event) => {
+                assert_eq!(chapter_id, CHAPTER_ID);
+                Some(event.clone())
+            }
+            _ => None,
+        })
+        .collect();
+    assert!(chapter_events
+        .iter()
+        .any(|event| matches!(event, archive_download::Event::Coalesced { .. })));
+    assert_eq!(
+        tally(&chapter_events),
+        (2, 2, "first page".len() + "second page".len())
+    );
+    assert!(events.contains(&batch_archive_download::Event::ChapterDone(
+        CHAPTER_ID.to_string()
+    )));
+}
+
+#[tokio::test]
+async fn archive_download_stalled() {
+    let fixtures = at_home_fixtures()
+        .with_fixture(
+            format!("/data/{CHAPTER_HASH}/{FIRST_PAGE}"),
+            &b"first page"[..],
+        )
+        .with_stalled(format!("/data/{CHAPTER_HASH}/{SECOND_PAGE}"));
+
+    // The stalled page is retried on new nodes straight away, then the chapter fails
+    let res = ArchiveDownload::new(CHAPTER_ID)
+        .set_stall_timeout(Some(Duration::from_millis(50)))
+        .request_with(&client(&fixtures))
+        .await;
+    assert!(matches!(res, Err(Error::Stalled(_))));
+    let at_home_requests = fixtures
+        .requested_urls()
+        .iter()
+        .filter(|url| url.path().starts_with("/at-home/server/"))
+        .count();
+    assert_eq!(at_home_requests, 3);
+
+    let res = ArchiveDownload::new(CHAPTER_ID)
+        .set_stall_timeout(None)
+        .set_deadline(Some(Duration::from_millis(50)))
+        .request_with(&client(&fixtures))
+        .await;
+    assert!(matches!(res, Err(Error::DeadlineExceeded(_))));
+}
+
+#[tokio::test]
+async fn archive_download_cancelled() {
+    let fixtures = at_home_fixtures()
+        .with_fixture(
+            format!("/data/{CHAPTER_HASH}/{FIRST_PAGE}"),
+            &b"first page"[..],
+        )
+        .with_stalled(format!("/data/{CHAPTER_HASH}/{SECOND_PAGE}"));
+    let progress = Arc::new(NoProgress);
+    let cancellation_token = tokio_util::sync::CancellationToken::new();
+
+    let client = client(&fixtures);
+    let download = ArchiveDownload::new(CHAPTER_ID)
+        .set_stall_timeout(None)
+        .set_progress(progress.clone())
+        .set_cancellation_token(cancellation_token.clone())
+        .request_with(&client);
+    let cancel = async {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        cancellation_token.cancel();
+    };
+    let (res, ()) = tokio::join!(download, cancel);
+    assert!(matches!(res, Err(Error::Cancelled)));
+
+    // The page tasks are aborted, the stalled one doesn't hold the progress sink anymore
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(Arc::strong_count(&progress), 1);
+}
+
+#[tokio::test]
+async fn archive_download_data_saver() {
+    let fixtures = at_home_fixtures()
+        .with_fixture(
+            format!("/data/{CHAPTER_HASH}/{FIRST_PAGE}"),
+            &b"first page"[..],
+        )
+        .with_fixture(
+            format!("/data-saver/{CHAPTER_HASH}/{SECOND_PAGE}"),
+            &b"second page, compressed"[..],
+        );
+    let (tx, mut rx) = mpsc::unbounded_channel();
+
+    ArchiveDownload::new(CHAPTER_ID)
+        .set_sender(tx)
+        .request_with(&client(&fixtures))
+        .await
+        .unwrap();
+
+    let mut events = Vec::new();
+    while let Some(event) = rx.recv().await {
+        events.push(event);
+    }
+    assert!(events.contains(&archive_download::Event::DataSaver(SECOND_PAGE.to_string())));
+    assert_eq!(tally(&events).1, 2);
+}
+
+#[tokio::test]
+async fn dexter() {
+    let fixtures = chapter_fixtures()
+        .with_fixture(
+            format!("/manga/{MANGA_ID}"),
+            include_str!("fixtures/manga.json"),
+        )
+        .with_fixture("/chapter", include_str!("fixtures/chapters.json"))
+        .with_fixture(
+            format!("/chapter/{CHAPTER_ID}"),
+            include_str!("fixtures/chapter.json"),
+        );
+    let dexter = Dexter::new(client(&fixtures))
+        .set_min_request_interval(Duration::ZERO)
+        .set_if_exists(IfExists::Verify);
+    let link = format!("https://mangadex.org/title/{MANGA_ID}/detective-conan");
+
+    let manga = dexter.manga(&link).await.unwrap();
+    let chapters = dexter.chapters(&link, ["en"], 0, 10).await.unwrap();
+
+    assert!(chapters
+        .data
+        .windows(2)
+        .all(|pair| pair[0].cmp_by_number(&pair[1]).is_ge()));
+    assert_eq!(
+        Dexter::chapter_file_name(&manga.data, &chapters.data[0]),
+        format!(
+            "Detective Conan - {} - {}.cbz",
+            chapters.data[0]
+                .attributes
+                .chapter
+                .as_deref()
+                .unwrap_or("unknown"),
+            chapters.data[0]
+                .attributes
+                .title
+                .as_deref()
+                .unwrap_or("unknown"),
+        )
+    );
+
+    let path = Utf8PathBuf::try_from(std::env::temp_dir())
+        .unwrap()
+        .join(format!("dexter-facade-{}.cbz", std::process::id()));
+    let chapter_link = format!("https://mangadex.org/chapter/{CHAPTER_ID}");
+    let first = dexter
+        .download_chapter_to(&chapter_link, &path, Arc::new(NoProgress))
+        .await
+        .unwrap();
+    let mut archive = zip::ZipArchive::new(std::fs::File::open(&path).unwrap()).unwrap();
+    let mut comic_info = String::new();
+    std::io::Read::read_to_string(
+        &mut archive.by_name("ComicInfo.xml").unwrap(),
+        &mut comic_info,
+    )
+    .unwrap();
+    assert!(comic_info.contains("<Series>Detective Conan</Series>"));
+    let second = dexter
+        .download_chapter_to(&chapter_link, &path, Arc::new(NoProgress))
+        .await
+        .unwrap();
+    let renamed = dexter
+        .clone()
+        .set_if_exists(IfExists::Rename)
+        .download_chapter_to(&chapter_link, &path, Arc::new(NoProgress))
+        .await
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let Some(Decision::Renamed(renamed_path)) = renamed else {
+        panic!("unexpected decision {renamed:?}");
+    };
+    std::fs::remove_file(&renamed_path).unwrap();
+    assert_eq!(first, None);
+    assert_eq!(second, Some(Decision::Verified));
+    assert_eq!(
+        renamed_path.file_name(),
+        Some(format!("dexter-facade-{} (1).cbz", std::process::id()).as_str())
+    );
+    // The existing archive is verified against the image links, its pages are not downloaded again
+    assert_eq!(fixtures.requested_urls().len(), 11);
+    assert!(matches!(
+        dexter.manga("detective conan").await,
+        Err(Error::InvalidId(_))
+    ));
+}
@@ -0,0 +1,61 @@
+use std::io::Cursor;
+
+use dexter_core::{
+    transform::{Downscale, Grayscale, Page, PageTransform, Transforms},
+    Error,
+};
+use image::{DynamicImage, ImageFormat, RgbImage};
+
+fn page(filename: &str, width: u32, height: u32) -> Page {
+    let image = DynamicImage::ImageRgb8(RgbImage::from_pixel(width, height, [200, 30, 30].into()));
+    let mut bytes = Cursor::new(Vec::new());
+    image.write_to(&mut bytes, ImageFormat::Png).unwrap();
+    Page {
+        filename: filename.to_string(),
+        bytes: bytes.into_inner().into(),
+    }
+}
+
+#[test]
+fn grayscale() {
+    let page = Grayscale.apply(page("1-0a1b2c3d4e5f.png", 4, 4)).unwrap();
+
+    assert_eq!(page.filename, "1-0a1b2c3d4e5f.png");
+    let image = image::load_from_memory(&page.bytes).unwrap();
+    assert!(!image.color().has_color());
+}
+
+#[test]
+fn downscale() {
+    let downscale = Downscale {
+        max_width: 10,
+        max_height: 10,
+    };
+
+    let page = downscale.apply(page("1-0a1b2c3d4e5f.png", 40, 20)).unwrap();
+    let image = image::load_from_memory(&page.bytes).unwrap();
+    assert_eq!((image.width(), image.height()), (10, 5));
+
+    // Small enough pages are not re-encoded
+    let small_page = self::page("2-6a7b8c9d0e1f.png", 8, 8);
+    assert_eq!(downscale.apply(small_page.clone()).unwrap(), small_page);
+}
+
+#[test]
+fn composed() {
+    let transforms = Transforms::new().with(Grayscale).with(Downscale {
+        max_width: 2,
+        max_height: 2,
+    });
+
+    let page = transforms.apply(page("1-0a1b2c3d4e5f.png", 4, 4)).unwrap();
+    let image = image::load_from_memory(&page.bytes).unwrap();
+    assert_eq!((image.width(), image.height()), (2, 2));
+    assert!(!image.color().has_color());
+
+    let res = transforms.apply(Page {
+        filename: "3-7b8c9d0e1f2a.png".to_string(),
+        bytes: "not an image".into(),
+    });
+    assert!(matches!(res, Err(Error::Transform(_))));
+}
@@ -0,0 +1,218 @@
+use dexter_core::{
+    api::{
+        report::Category, upload::ChapterDraft, AbandonUploadSession, BeginUploadSession,
+        CommitUploadSession, GetReportReasons, ReportContent, UploadPages,
+    },
+    mock::FixtureMiddleware,
+    Client, Error, Request,
+};
+use http::StatusCode;
+use serde_json::json;
+
+static MANGA_ID: &str = "7f30dfc3-0b80-4dcc-a3b9-0cd746fac005";
+static CHAPTER_ID: &str = "07bf2a09-f30d-410f-aba1-025e2d27a88f";
+static GROUP_ID: &str = "2b9c4f3e-8a1d-4e6f-9c7b-5d3a2e1f0b4c";
+static SESSION_ID: &str = "9e4c1b2a-3d5f-4a6b-8c7d-0e1f2a3b4c5d";
+
+/// Ids of the pages listed in `fixtures/uploaded_pages.json`
+static FIRST_PAGE_ID: &str = "a1b2c3d4-e5f6-4a7b-8c9d-0e1f2a3b4c5d";
+static SECOND_PAGE_ID: &str = "b2c3d4e5-f6a7-4b8c-9d0e-1f2a3b4c5d6e";
+
+fn client(fixtures: &FixtureMiddleware) -> Client {
+    Client::new()
+        .with_access_token("token")
+        .with_middleware(fixtures.clone())
+}
+
+/// The json body of the only request sent
+fn body(fixtures: &FixtureMiddleware) -> serde_json::Value {
+    let bodies = fixtures.requested_bodies();
+    assert_eq!(bodies.len(), 1);
+    serde_json::from_slice(bodies[0].as_ref().unwrap()).unwrap()
+}
+
+#[tokio::test]
+async fn begin_upload_session() {
+    let fixtures = FixtureMiddleware::new().with_fixture(
+        "/upload/begin",
+        include_str!("fixtures/upload_session.json"),
+    );
+
+    let response = BeginUploadSession::new(MANGA_ID)
+        .push_group(GROUP_ID)
+        .request_with(&client(&fixtures))
+        .await
+        .unwrap();
+
+    assert_eq!(response.data.id, SESSION_ID);
+    assert_eq!(
+        body(&fixtures),
+        json!({ "groups": [GROUP_ID], "manga": MANGA_ID })
+    );
+}
+
+#[tokio::test]
+async fn upload_pages() {
+    let fixtures = FixtureMiddleware::new().with_fixture(
+        format!("/upload/{SESSION_ID}"),
+        include_str!("fixtures/uploaded_pages.json"),
+    );
+
+    let response = UploadPages::new(SESSION_ID)
+        .push_page("0000.png", &b"first page"[..])
+        .push_page("0001.jpg", &b"second page"[..])
+        .request_with(&client(&fixtures))
+        .await
+        .unwrap();
+
+    // The pages are listed in any order, along with the name they were uploaded with
+    let uploaded = response
+        .data
+        .iter()
+        .map(|page| {
+            (
+                page.id.as_str(),
+                page.attributes.original_file_name.as_str(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        uploaded,
+        [(SECOND_PAGE_ID, "0001.jpg"), (FIRST_PAGE_ID, "0000.png")]
+    );
+    // Multipart bodies are streamed, they are not recorded
+    assert_eq!(fixtures.requested_bodies(), [None]);
+}
+
+#[tokio::test]
+async fn commit_upload_session() {
+    let fixtures = FixtureMiddleware::new().with_fixture(
+        format!("/upload/{SESSION_ID}/commit"),
+        include_str!("fixtures/committed_chapter.json"),
+    );
+    let chapter_draft = ChapterDraft {
+        volume: Some("1".to_string()),
+        chapter: Some("2".to_string()),
+        title: None,
+        translated_language: "en".to_string(),
+    };
+
+    let response =
+        CommitUploadSession::new(SESSION_ID, chapter_draft, [FIRST_PAGE_ID, SECOND_PAGE_ID])
+            .request_with(&client(&fixtures))
+            .await
+            .unwrap();
+
+    assert_eq!(response.data.id, CHAPTER_ID);
+    assert_eq!(
+        body(&fixtures),
+        json!({
+            "chapterDraft": {
+                "volume": "1",
+                "chapter": "2",
+                "title": null,
+                "translatedLanguage": "en",
+            },
+            "pageOrder": [FIRST_PAGE_ID, SECOND_PAGE_ID],
+        })
+    );
+}
+
+#[tokio::test]
+async fn commit_upload_session_failure() {
+    // No fixture, the api answers with a 404
+    let fixtures = FixtureMiddleware::new();
+
+    let res = CommitUploadSession::new(SESSION_ID, ChapterDraft::default(), [FIRST_PAGE_ID])
+        .request_with(&client(&fixtures))
+        .await;
+
+    assert!(matches!(res, Err(Error::Reqwest(err)) if err.status() == Some(StatusCode::NOT_FOUND)));
+}
+
+#[tokio::test]
+async fn upload_session_server_error() {
+    let fixtures = FixtureMiddleware::new()
+        .with_fixture(
+            "/upload/begin",
+            include_str!("fixtures/upload_session.json"),
+        )
+        .with_failures("/upload/begin", StatusCode::SERVICE_UNAVAILABLE, 1);
+    let client = client(&fixtures);
+
+    let res = BeginUploadSession::new(MANGA_ID)
+        .push_group(GROUP_ID)
+        .request_with(&client)
+        .await;
+    assert!(matches!(
+        res,
+        Err(Error::Reqwest(err)) if err.status() == Some(StatusCode::SERVICE_UNAVAILABLE)
+    ));
+
+    BeginUploadSession::new(MANGA_ID)
+        .push_group(GROUP_ID)
+        .request_with(&client)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn abandon_upload_session() {
+    let fixtures = FixtureMiddleware::new()
+        .with_fixture(format!("/upload/{SESSION_ID}"), r#"{ "result": "ok" }"#);
+
+    let response = AbandonUploadSession::new(SESSION_ID)
+        .request_with(&client(&fixtures))
+        .await
+        .unwrap();
+
+    assert_eq!(response.result, "ok");
+    assert_eq!(
+        fixtures.requested_urls()[0].path(),
+        format!("/upload/{SESSION_ID}")
+    );
+}
+
+#[tokio::test]
+async fn get_report_reasons() {
+    let fixtures = FixtureMiddleware::new().with_fixture(
+        "/report/reasons/chapter",
+        include_str!("fixtures/report_reasons.json"),
+    );
+
+    let response = GetReportReasons::new(Category::Chapter)
+        .request_with(&client(&fixtures))
+        .await
+        .unwrap();
+
+    assert_eq!(response.data.len(), 2);
+    assert_eq!(response.data[0].attributes.reason.en, "Missing pages");
+    assert!(!response.data[0].attributes.details_required);
+    assert!(response.data[1].attributes.details_required);
+}
+
+#[tokio::test]
+async fn report_content() {
+    let fixtures = FixtureMiddleware::new().with_fixture("/report", r#"{ "result": "ok" }"#);
+
+    let response = ReportContent::new(
+        Category::Chapter,
+        CHAPTER_ID,
+        "8a7b6c5d-4e3f-4a2b-9c1d-0e9f8a7b6c5d",
+    )
+    .set_details("pages 3 and 4 are swapped")
+    .request_with(&client(&fixtures))
+    .await
+    .unwrap();
+
+    assert_eq!(response.result, "ok");
+    assert_eq!(
+        body(&fixtures),
+        json!({
+            "category": "chapter",
+            "reason": "8a7b6c5d-4e3f-4a2b-9c1d-0e9f8a7b6c5d",
+            "objectId": CHAPTER_ID,
+            "details": "pages 3 and 4 are swapped",
+        })
+    );
+}
@@ -0,0 +1,129 @@
+use std::{cmp::Ordering, fs::File, io::Read};
+
+use anyhow::Result;
+use camino::Utf8Path;
+use dexter_core::output::is_page;
+use zip::{result::ZipError, ZipArchive};
+
+#[derive(Debug)]
+pub struct Page {
+    pub name: String,
+    pub bytes: Vec<u8>,
+}
+
+/// Reads all the pages contained in the archive, in archive order.
+/// The other files, such as `ComicInfo.xml`, and the directories are skipped.
+pub fn read_pages(path: &Utf8Path) -> Result<Vec<Page>> {
+    let mut archive = ZipArchive::new(File::open(path)?)?;
+    let mut pages = Vec::with_capacity(archive.len());
+
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index)?;
+        if file.is_dir() || !is_page(file.name()) {
+            continue;
+        }
+        let mut bytes = Vec::with_capacity(usize::try_from(file.size()).unwrap_or_default());
+        file.read_to_end(&mut bytes)?;
+        pages.push(Page {
+            name: file.name().to_string(),
+            bytes,
+        });
+    }
+
+    Ok(pages)
+}
+
+/// Splits `name` in runs of digits and runs of other characters
+fn chunks(name: &str) -> impl Iterator<Item = &str> {
+    let mut rest = name;
+    std::iter::from_fn(move || {
+        let is_digit = rest.chars().next()?.is_ascii_digit();
+        let end = rest
+            .find(|c: char| c.is_ascii_digit() != is_digit)
+            .unwrap_or(rest.len());
+        let (chunk, tail) = rest.split_at(end);
+        rest = tail;
+        Some(chunk)
+    })
+}
+
+/// Compares the names with their numbers by value, so that `2.png` comes before `10.png`
+pub(crate) fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a_chunks, mut b_chunks) = (chunks(a), chunks(b));
+    loop {
+        let ordering = match (a_chunks.next(), b_chunks.next()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(a), Some(b))
+                if a.starts_with(|c: char| c.is_ascii_digit())
+                    && b.starts_with(|c: char| c.is_ascii_digit()) =>
+            {
+                let (a, b) = (a.trim_start_matches('0'), b.trim_start_matches('0'));
+                a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+            }
+            (Some(a), Some(b)) => a.cmp(b),
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+/// Reads the `index`th image of the archive, the images being sorted by name, numbers included
+pub fn read_page(path: &Utf8Path, index: usize) -> Result<Option<Vec<u8>>> {
+    let mut archive = ZipArchive::new(File::open(path)?)?;
+    let mut names = archive
+        .file_names()
+        .filter(|name| is_page(name))
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    names.sort_by(|a, b| natural_cmp(a, b));
+    let Some(name) = names.get(index) else {
+        return Ok(None);
+    };
+
+    let mut file = archive.by_name(name)?;
+    let mut bytes = Vec::with_capacity(usize::try_from(file.size()).unwrap_or_default());
+    file.read_to_end(&mut bytes)?;
+
+    Ok(Some(bytes))
+}
+
+/// Reads the `ComicInfo.xml` metadata file of the archive, if any
+pub fn read_comic_info(path: &Utf8Path) -> Result<Option<String>> {
+    let mut archive = ZipArchive::new(File::open(path)?)?;
+    let mut file = match archive.by_name("ComicInfo.xml") {
+        Ok(file) => file,
+        Err(ZipError::FileNotFound) => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+    let mut comic_info = String::new();
+    file.read_to_string(&mut comic_info)?;
+
+    Ok(Some(comic_info))
+}
+
+pub fn extension(filename: &str) -> String {
+    Utf8Path::new(filename)
+        .extension()
+        .map(ToString::to_string)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn natural_order() {
+        let mut names = vec!["10.png", "2.png", "1.png", "cover.png", "01b.png", "1a.png"];
+
+        names.sort_by(|a, b| natural_cmp(a, b));
+
+        assert_eq!(
+            names,
+            ["1.png", "1a.png", "01b.png", "2.png", "10.png", "cover.png"]
+        );
+    }
+}
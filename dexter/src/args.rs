@@ -1,10 +1,17 @@
 use camino::Utf8PathBuf;
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
+use dexter_core::{
+    link::{chapter_id, manga_id},
+    ChapterSelection,
+};
+
+use crate::layout::Layout;
 
 #[derive(Parser, Debug)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct InteractiveSearch {
-    /// Skips manga search and use manga id as reference
-    #[clap(long)]
+    /// Skips manga search and use manga id, or title link, as reference
+    #[clap(long, value_parser = manga_id)]
     pub manga_id: Option<String>,
     /// Used with the `--manga-id` and `--chapter-number` to refine chapter search
     #[clap(long)]
@@ -24,6 +31,16 @@ pub struct InteractiveSearch {
     /// Max retries if image download fails
     #[clap(long, default_value_t = 3)]
     pub max_download_retries: u32,
+    /// Overwrite the destination file if it already exists (default)
+    #[clap(long, overrides_with = "no_clobber")]
+    pub overwrite: bool,
+    /// Fail instead of overwriting the destination file if it already exists
+    #[clap(long, overrides_with = "overwrite")]
+    pub no_clobber: bool,
+    /// Run as a full screen terminal ui, with panes for the search, the chapters, the download queue, and the logs.
+    /// Only `--outdir`, `--language`, `--max-download-retries`, and `--no-clobber` are used in this mode
+    #[clap(long)]
+    pub tui: bool,
 }
 
 #[derive(Parser, Debug)]
@@ -34,12 +51,27 @@ pub struct Search {
     /// Limit how many results are displayed (lower is faster)
     #[clap(short, long, default_value = "5")]
     pub limit: u32,
+    /// Also display the year, status, content rating, and a description snippet
+    #[clap(short, long)]
+    pub detailed: bool,
+    /// Comma separated languages used to pick the displayed title and description, in order of preference
+    #[clap(long, value_delimiter = ',', default_value = "en")]
+    pub lang_priority: Vec<String>,
+}
+
+/// Field the chapters are sorted by, most recent or highest first
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ChapterOrder {
+    #[default]
+    Chapter,
+    Published,
+    Created,
 }
 
 #[derive(Parser, Debug)]
 pub struct Chapters {
-    /// Display the chapters for a specified manga id
-    #[clap(short, long)]
+    /// Display the chapters for a specified manga id, or title link
+    #[clap(short, long, value_parser = manga_id)]
     pub manga_id: String,
     /// Limit how many chapters are displayed (lower is faster)
     #[clap(short, long, default_value = "100")]
@@ -51,19 +83,38 @@ pub struct Chapters {
     #[allow(clippy::struct_field_names)]
     #[clap(short, long)]
     pub chapters: Vec<String>,
+    /// Only display the chapters of these scanlation group(s), by name or id
+    #[clap(short, long = "group")]
+    pub groups: Vec<String>,
+    /// Sort the chapters by number, publication date, or upload date
+    #[clap(short, long, value_enum, default_value_t = ChapterOrder::Chapter)]
+    pub order: ChapterOrder,
+}
+
+/// What to do with a chapter whose archive already exists
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum IfExists {
+    /// Keep the archive, the chapter is not downloaded
+    Skip,
+    /// Download the chapter again and replace the archive
+    Overwrite,
+    /// Download the chapter next to the archive, e.g. `chapter (1).cbz`
+    Rename,
+    /// Keep the archive if it has as many pages as the chapter, download it again otherwise
+    Verify,
 }
 
 #[derive(Parser, Debug)]
 pub struct ImageLinks {
-    /// Display the image links for a specified chapter id
-    #[clap(short, long)]
+    /// Display the image links for a specified chapter id, or chapter link
+    #[clap(short, long, value_parser = chapter_id)]
     pub chapter_id: String,
 }
 
 #[derive(Parser, Debug)]
 pub struct Download {
-    /// Download and pack all the images for the provided chapter id
-    #[clap(short, long)]
+    /// Download and pack all the images for the provided chapter id, or chapter link
+    #[clap(short, long, value_parser = chapter_id)]
     pub chapter_id: String,
     /// Filename of the downloaded file archived
     #[clap(short, long, default_value = "chapter.cbz")]
@@ -77,6 +128,316 @@ pub struct Download {
     /// Max retries if image download fails
     #[clap(long, default_value_t = 3)]
     pub max_download_retries: u32,
+    /// Retry an image download on another MD@Home node when no bytes are received for this many seconds, `0` waiting forever
+    #[clap(long, default_value_t = 30)]
+    pub stall_timeout: u64,
+    /// Fail a chapter download if it isn't over within this many seconds
+    #[clap(long)]
+    pub chapter_deadline: Option<u64>,
+    /// Overwrite the destination file if it already exists (default)
+    #[clap(long, overrides_with = "no_clobber")]
+    pub overwrite: bool,
+    /// Fail instead of overwriting the destination file if it already exists
+    #[clap(long, overrides_with = "overwrite")]
+    pub no_clobber: bool,
+    /// What to do if the destination file already exists, decided before downloading the chapter
+    #[clap(long, value_enum, conflicts_with_all = ["overwrite", "no_clobber"])]
+    pub if_exists: Option<IfExists>,
+}
+
+/// Format of the report printed once the command is done
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Output {
+    #[default]
+    Text,
+    Json,
+}
+
+/// Parses a `WIDTHxHEIGHT` page size
+fn page_size(value: &str) -> Result<(u32, u32), String> {
+    value
+        .split_once('x')
+        .and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?)))
+        .filter(|&(width, height)| width > 0 && height > 0)
+        .ok_or_else(|| format!("invalid page size {value}, expected WIDTHxHEIGHT, e.g. 1264x1680"))
+}
+
+#[derive(Parser, Debug)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct BatchDownload {
+    /// Ids, or links, of the chapters to download, each chapter is packed in its own archive
+    #[clap(short, long, required_unless_present = "manga_id", value_parser = chapter_id)]
+    pub chapter_ids: Vec<String>,
+    /// Download all the chapters of this manga (id or title link) instead, and report the chapter numbers missing in each language
+    #[clap(short, long, conflicts_with = "chapter_ids", value_parser = manga_id)]
+    pub manga_id: Option<String>,
+    /// Language(s) of the chapters to download with `--manga-id`
+    #[clap(long = "language", default_value = "en")]
+    pub languages: Vec<String>,
+    /// Chapter numbers to download with `--manga-id`, e.g. `1-20,45,60-`
+    #[clap(long, requires = "manga_id")]
+    pub chapters: Option<ChapterSelection>,
+    /// Only download this many of the most recent chapters with `--manga-id`, after the `--chapters` selection
+    #[clap(long, requires = "manga_id")]
+    pub latest: Option<usize>,
+    /// Destination directory, defaults to the current directory
+    #[clap(long)]
+    pub outdir: Option<Utf8PathBuf>,
+    /// Max retries if image download fails
+    #[clap(long, default_value_t = 3)]
+    pub max_download_retries: u32,
+    /// Retry an image download on another MD@Home node when no bytes are received for this many seconds, `0` waiting forever
+    #[clap(long, default_value_t = 30)]
+    pub stall_timeout: u64,
+    /// Fail a chapter download if it isn't over within this many seconds
+    #[clap(long)]
+    pub chapter_deadline: Option<u64>,
+    /// Overwrite the destination files if they already exist (default)
+    #[clap(long, overrides_with = "no_clobber")]
+    pub overwrite: bool,
+    /// Don't download the chapters whose destination file already exists, same as `--if-exists skip`
+    #[clap(long, overrides_with = "overwrite")]
+    pub no_clobber: bool,
+    /// What to do with the chapters whose destination file already exists, the decisions are listed in the summary
+    #[clap(long, value_enum, conflicts_with_all = ["overwrite", "no_clobber"])]
+    pub if_exists: Option<IfExists>,
+    /// Don't check the free space of the destination before and during the downloads,
+    /// which are otherwise cancelled as soon as the remaining chapters can't fit
+    #[clap(long)]
+    pub skip_space_check: bool,
+    /// Also write the download summary as json to this path
+    #[clap(long)]
+    pub summary: Option<Utf8PathBuf>,
+    /// Keep the pages in this directory, addressed by their checksum, so that pages identical
+    /// across chapters (e.g. credit pages) are only downloaded once, in this run and the next ones
+    #[clap(long)]
+    pub page_store: Option<Utf8PathBuf>,
+    /// Shell command run after each chapter archive is written, `{path}` and `{chapter_id}` are replaced by their values,
+    /// e.g. `komga-import {path}`, it's killed after a minute
+    #[clap(long, env = "DEXTER_ON_CHAPTER_DOWNLOADED")]
+    pub on_chapter_downloaded: Option<String>,
+    /// Url receiving a json `POST` after each chapter archive is written, with the `event`, the `chapter_id`, and the `path`,
+    /// given up after a minute
+    #[clap(long, env = "DEXTER_WEBHOOK")]
+    pub webhook: Option<String>,
+    /// Format of the summary printed once the downloads are over
+    #[clap(long, value_enum, default_value_t = Output::Text)]
+    pub output: Output,
+    /// Convert the pages to grayscale, e.g. for e-ink readers
+    #[clap(long)]
+    pub grayscale: bool,
+    /// Shrink the pages larger than `WIDTHxHEIGHT`, e.g. `1264x1680` for the screen of an e-reader, keeping their aspect ratio
+    #[clap(long, value_parser = page_size)]
+    pub max_page_size: Option<(u32, u32)>,
+    /// Arrange the archives by series and volume, archives are named after their chapter id otherwise.
+    /// The language is added to the names when several are downloaded, and the chapter id to chapters without number
+    #[clap(long, value_enum)]
+    pub layout: Option<Layout>,
+}
+
+#[derive(Parser, Debug)]
+pub struct LibrarySearch {
+    /// Text to fuzzy match against the series, titles, and authors of the archives
+    pub query: String,
+    /// Library directory, searched recursively, defaults to the current directory
+    #[clap(short, long)]
+    pub dir: Option<Utf8PathBuf>,
+    /// Limit how many paths are displayed
+    #[clap(short, long, default_value = "20")]
+    pub limit: usize,
+}
+
+#[derive(Parser, Debug)]
+pub struct LibraryStats {
+    /// Library directory, scanned recursively, defaults to the current directory
+    #[clap(short, long)]
+    pub dir: Option<Utf8PathBuf>,
+    /// Read all the archives again, instead of the metadata cataloged in `.dexter-library.json` for the unchanged ones
+    #[clap(long)]
+    pub rescan: bool,
+}
+
+#[derive(Parser, Debug)]
+pub struct LibraryNormalize {
+    /// Folder structure to move the archives to, based on their `ComicInfo.xml` metadata, or on their current path when it follows a layout
+    #[clap(short, long, value_enum)]
+    pub layout: Layout,
+    /// Library directory, scanned recursively, defaults to the current directory
+    #[clap(short, long)]
+    pub dir: Option<Utf8PathBuf>,
+    /// Print the renames without moving any file
+    #[clap(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Parser, Debug)]
+pub struct LibraryRename {
+    /// Path template relative to the library, without extension, e.g. `{series}/{series} v{volume:2} c{chapter:3}`.
+    /// Available fields: series, volume, chapter, title, language, and filename, read from `ComicInfo.xml`,
+    /// or from the archive path when it follows a layout
+    #[clap(short, long)]
+    pub template: String,
+    /// Library directory, scanned recursively, defaults to the current directory
+    #[clap(short, long)]
+    pub dir: Option<Utf8PathBuf>,
+    /// Print the renames without moving any file
+    #[clap(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Parser, Debug)]
+pub struct LibraryThumbnails {
+    /// Library directory, scanned recursively, defaults to the current directory
+    #[clap(short, long)]
+    pub dir: Option<Utf8PathBuf>,
+    /// Page used as thumbnail, starting at 1
+    #[clap(short, long, default_value_t = 1)]
+    pub page: usize,
+    /// Max width and height of the thumbnails, in pixels
+    #[clap(short, long, default_value_t = 300)]
+    pub size: u32,
+    /// Where the thumbnails are cached, defaults to `dexter/thumbnails` in the user cache directory
+    #[clap(long)]
+    pub cache_dir: Option<Utf8PathBuf>,
+}
+
+#[cfg(feature = "ocr")]
+#[derive(Parser, Debug)]
+pub struct LibraryOcr {
+    /// Library directory, scanned recursively, defaults to the current directory
+    #[clap(short, long)]
+    pub dir: Option<Utf8PathBuf>,
+    /// Tesseract language code of the text, e.g. `eng` or `jpn`
+    #[clap(short, long, default_value = "eng")]
+    pub language: String,
+    /// Index the archives again, even the ones unchanged since they were indexed
+    #[clap(long)]
+    pub force: bool,
+}
+
+#[derive(Parser, Debug)]
+pub struct LibraryGrep {
+    /// Text searched in the pages, case insensitively
+    pub keyword: String,
+    /// Library directory, indexed with `dexter library ocr`, defaults to the current directory
+    #[clap(short, long)]
+    pub dir: Option<Utf8PathBuf>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum LibrarySubcommands {
+    /// Fuzzy search the downloaded archives, and print the matching paths, best match first
+    #[clap(alias = "s")]
+    Search(LibrarySearch),
+    /// Count the series, chapters, pages, and size of the downloaded archives
+    Stats(LibraryStats),
+    /// Move the downloaded archives to the folder structure expected by a media server
+    Normalize(LibraryNormalize),
+    /// Rename the downloaded archives according to a template
+    Rename(LibraryRename),
+    /// Generate the missing thumbnails of the downloaded archives, and print their paths
+    Thumbnails(LibraryThumbnails),
+    /// Extract the text of the archive pages, so that it can be searched with `dexter library grep`
+    #[cfg(feature = "ocr")]
+    Ocr(LibraryOcr),
+    /// Search the text extracted from the archive pages, and print the matching archives, pages, and lines
+    Grep(LibraryGrep),
+}
+
+#[derive(Parser, Debug)]
+pub struct Library {
+    #[clap(subcommand)]
+    pub command: LibrarySubcommands,
+}
+
+#[derive(Parser, Debug)]
+pub struct Send {
+    /// Mount point of the device, e.g. `/run/media/user/KOBOeReader`
+    #[clap(long)]
+    pub device: Utf8PathBuf,
+    /// Series to send, matched against the `ComicInfo.xml` series, or the one read from the archive path
+    #[clap(short, long)]
+    pub series: String,
+    /// Only send this volume
+    #[clap(short, long)]
+    pub volume: Option<String>,
+    /// Library directory, scanned recursively, defaults to the current directory
+    #[clap(short, long)]
+    pub library: Option<Utf8PathBuf>,
+    /// Convert the pages to grayscale on the way, for e-ink readers
+    #[clap(long)]
+    pub grayscale: bool,
+    /// Shrink the pages larger than `WIDTHxHEIGHT` on the way, e.g. `1264x1680` for the screen of the device
+    #[clap(long, value_parser = page_size)]
+    pub max_page_size: Option<(u32, u32)>,
+}
+
+#[derive(Parser, Debug)]
+pub struct Feed {
+    /// Generate the feed of this manga id, or title link
+    #[clap(short, long, value_parser = manga_id)]
+    pub manga_id: String,
+    /// Feed file to write, the feed is printed to the standard output otherwise
+    #[clap(short, long)]
+    pub out: Option<Utf8PathBuf>,
+    /// Comma separated languages of the chapters listed in the feed
+    #[clap(long, value_delimiter = ',', default_value = "en")]
+    pub languages: Vec<String>,
+    /// How many of the most recent chapters are listed in the feed
+    #[clap(short, long, default_value = "20")]
+    pub limit: u32,
+}
+
+#[derive(Parser, Debug)]
+pub struct Verify {
+    /// Path to the archive to verify
+    #[clap(short, long)]
+    pub path: Utf8PathBuf,
+    /// Id of the chapter the archive was downloaded from
+    #[clap(short, long, value_parser = chapter_id)]
+    pub chapter_id: String,
+    /// Download the missing or broken pages again and rewrite the archive
+    #[clap(long)]
+    pub repair: bool,
+    /// Max retries if image download fails
+    #[clap(long, default_value_t = 3)]
+    pub max_download_retries: u32,
+}
+
+#[derive(Parser, Debug)]
+pub struct Upload {
+    /// Id of the manga the chapter belongs to
+    #[clap(short, long, value_parser = manga_id)]
+    pub manga_id: String,
+    /// Id(s) of the scanlation group(s) credited for the chapter
+    #[clap(short, long)]
+    pub groups: Vec<String>,
+    /// Archive containing the pages to upload
+    #[clap(
+        short,
+        long,
+        required_unless_present = "images",
+        conflicts_with = "images"
+    )]
+    pub path: Option<Utf8PathBuf>,
+    /// Glob matching the images to upload, pages are sorted by file name
+    #[clap(short, long)]
+    pub images: Option<String>,
+    /// Volume number of the chapter
+    #[clap(short, long)]
+    pub volume: Option<String>,
+    /// Chapter number
+    #[clap(short, long)]
+    pub chapter: Option<String>,
+    /// Chapter title
+    #[clap(short, long)]
+    pub title: Option<String>,
+    /// Language the chapter is translated to
+    #[clap(long, default_value = "en")]
+    pub language: String,
+    /// `MangaDex` access token, uploads are made on behalf of its owner
+    #[clap(long, env = "MANGADEX_ACCESS_TOKEN", hide_env_values = true)]
+    pub access_token: String,
 }
 
 #[derive(Subcommand, Debug)]
@@ -96,6 +457,22 @@ pub enum Subcommands {
     /// Download and pack all the images contained in a chapter
     #[clap(alias = "d")]
     Download(Download),
+    /// Download and pack several chapters, and print a summary of the downloads
+    #[clap(alias = "bd")]
+    BatchDownload(BatchDownload),
+    /// Check that a downloaded archive contains all the pages of a chapter, and optionally repair it
+    #[clap(alias = "v")]
+    Verify(Verify),
+    /// Browse the archives downloaded locally
+    #[clap(alias = "l")]
+    Library(Library),
+    /// Copy the archives of a series to an e-reader, skipping the ones already on it
+    Send(Send),
+    /// Upload a chapter from an archive or a folder of images
+    #[clap(alias = "u")]
+    Upload(Upload),
+    /// Generate an Atom feed of the new chapters of a manga, for feed readers
+    Feed(Feed),
 }
 
 #[derive(Parser, Debug)]
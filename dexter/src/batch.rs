@@ -0,0 +1,676 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::{self, Display},
+    fs::create_dir_all,
+    io::Cursor,
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+use dexter_core::{
+    api::{batch_archive_download, get_aggregate, get_chapter_by_id, GetAggregate, GetChapterById},
+    naming,
+    output::{ensure_available_space, write_bytes_atomically, Decision, ESTIMATED_CHAPTER_SIZE},
+    page_store::PageStore,
+    summary::Summary,
+    transform::{Downscale, Grayscale, Transforms},
+    write_atomically_with_comic_info, BatchArchiveDownload, ChapterNumber, ChapterSelection,
+    Client, ComicInfo, IfExists, Request, WritePolicy,
+};
+use eco_cbz::CbzWriter;
+use futures::StreamExt;
+use indicatif::{HumanBytes, ProgressBar, ProgressStyle};
+use serde::Serialize;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, warn};
+
+use crate::{
+    args::{BatchDownload, Output},
+    cancel_on_ctrl_c,
+    hooks::Hooks,
+    if_exists,
+    language::check_chapter_language,
+    layout::Layout,
+    stall_timeout,
+};
+
+/// Progress events waiting to be displayed, the following ones are merged until the progress bar catches up
+const EVENTS_CAPACITY: usize = 64;
+
+/// Chapter numbers missing from a series in one language
+#[derive(Debug, Serialize)]
+struct Gaps {
+    language: String,
+    /// Whole chapter numbers that are not available on `MangaDex`
+    unavailable: Vec<u64>,
+    /// Chapter numbers that are available but couldn't be downloaded
+    failed: Vec<String>,
+    /// Chapter numbers listed on `MangaDex` but hosted on an external website, they are not downloaded
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    external: Vec<String>,
+}
+
+impl Gaps {
+    fn new(
+        language: String,
+        aggregate: &get_aggregate::Response,
+        summary: &Summary,
+        external: &[String],
+    ) -> Self {
+        let numbers = aggregate
+            .chapters()
+            .map(get_aggregate::Chapter::chapter_number)
+            .collect::<Vec<_>>();
+        let failed = aggregate
+            .chapters()
+            .filter(|chapter| summary.failed.contains(&chapter.id))
+            .map(|chapter| chapter.chapter.clone())
+            .collect();
+        let external = aggregate
+            .chapters()
+            .filter(|chapter| external.contains(&chapter.id))
+            .map(|chapter| chapter.chapter.clone())
+            .collect();
+
+        Self {
+            language,
+            unavailable: ChapterNumber::gaps(&numbers),
+            failed,
+            external,
+        }
+    }
+}
+
+impl Display for Gaps {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Gaps ({}): unavailable", self.language)?;
+        if self.unavailable.is_empty() {
+            write!(f, " none")?;
+        }
+        for (index, number) in self.unavailable.iter().enumerate() {
+            write!(f, "{} {number}", if index > 0 { "," } else { "" })?;
+        }
+        write!(f, ", failed")?;
+        if self.failed.is_empty() {
+            write!(f, " none")?;
+        }
+        for (index, number) in self.failed.iter().enumerate() {
+            write!(f, "{} {number}", if index > 0 { "," } else { "" })?;
+        }
+        if !self.external.is_empty() {
+            write!(f, ", external")?;
+        }
+        for (index, number) in self.external.iter().enumerate() {
+            write!(f, "{} {number}", if index > 0 { "," } else { "" })?;
+        }
+        Ok(())
+    }
+}
+
+/// Pages read from the page store instead of being downloaded
+#[derive(Debug, Serialize)]
+struct Reused {
+    pages: usize,
+    bytes_saved: u64,
+}
+
+/// The download summary, and the gaps when a whole series is downloaded
+#[derive(Debug, Serialize)]
+struct Report {
+    #[serde(flatten)]
+    summary: Summary,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reused: Option<Reused>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    gaps: Vec<Gaps>,
+}
+
+impl Report {
+    /// Completes the summary with the pages reused from the store, and the gaps of the downloaded series
+    fn new(
+        summary: Summary,
+        aggregates: Vec<(String, get_aggregate::Response)>,
+        external: &[String],
+        page_store: Option<&PageStore>,
+    ) -> Self {
+        let gaps = aggregates
+            .into_iter()
+            .map(|(language, aggregate)| Gaps::new(language, &aggregate, &summary, external))
+            .collect();
+
+        Self {
+            summary,
+            reused: page_store.map(|page_store| Reused {
+                pages: page_store.pages_reused(),
+                bytes_saved: page_store.bytes_saved(),
+            }),
+            gaps,
+        }
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.summary)?;
+        if let Some(reused) = &self.reused {
+            write!(
+                f,
+                "\nReused: {} page(s) from the page store, {} saved",
+                reused.pages,
+                HumanBytes(reused.bytes_saved)
+            )?;
+        }
+        for gaps in &self.gaps {
+            write!(f, "\n{gaps}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The chapters of a series, one upload per chapter number and language
+struct SeriesChapters {
+    chapter_ids: Vec<String>,
+    /// Language each chapter was requested in
+    languages: HashMap<String, String>,
+    /// Aggregates used to detect the gaps once the downloads are over
+    aggregates: Vec<(String, get_aggregate::Response)>,
+}
+
+async fn series_chapters(
+    manga_id: &str,
+    languages: Vec<String>,
+    selection: Option<&ChapterSelection>,
+    latest: Option<usize>,
+) -> Result<SeriesChapters> {
+    let mut series_chapters = SeriesChapters {
+        chapter_ids: Vec::new(),
+        languages: HashMap::new(),
+        aggregates: Vec::new(),
+    };
+    for language in languages {
+        let aggregate = GetAggregate::new(manga_id)
+            .push_language(&language)
+            .request()
+            .await?;
+        for chapter in aggregate.select(selection, latest) {
+            series_chapters.chapter_ids.push(chapter.id.clone());
+            series_chapters
+                .languages
+                .insert(chapter.id.clone(), language.clone());
+        }
+        series_chapters.aggregates.push((language, aggregate));
+    }
+    Ok(series_chapters)
+}
+
+/// Fetches the chapter information, `None` if it can't be fetched
+async fn chapter_info(chapter_id: &str) -> Option<get_chapter_by_id::Data> {
+    match GetChapterById::new(chapter_id).request().await {
+        Ok(get_chapter_by_id::Response { data }) => Some(data),
+        Err(err) => {
+            error!("failed to get the information of chapter {chapter_id}: {err}");
+            None
+        }
+    }
+}
+
+/// Returns the path of the chapter archive relative to the output directory,
+/// falling back to the chapter id without layout or chapter information.
+///
+/// The language is added to the file name when several languages are downloaded,
+/// and the chapter id when the chapter has no number, e.g. oneshots, so that no two chapters share a path
+fn chapter_path(
+    chapter_id: &str,
+    chapter: Option<&get_chapter_by_id::Data>,
+    layout: Option<Layout>,
+    tag_language: bool,
+) -> Utf8PathBuf {
+    match (layout, chapter) {
+        (Some(layout), Some(chapter)) => {
+            let mut tags = Vec::new();
+            if tag_language {
+                tags.extend(chapter.attributes.translated_language.as_deref());
+            }
+            if chapter.attributes.chapter.is_none() {
+                tags.push(chapter_id);
+            }
+            layout.path(
+                chapter.manga_title().unwrap_or("unknown"),
+                chapter.attributes.volume.as_deref(),
+                chapter.attributes.chapter.as_deref(),
+                &tags,
+            )
+        }
+        _ => Utf8PathBuf::from(naming::file_name_with_extension(chapter_id, "cbz")),
+    }
+}
+
+/// Returns the path of the chapter archive relative to the output directory, and the chapter metadata,
+/// after checking the chapter is in the language it was requested in, if any
+fn checked_chapter_path(
+    chapter_id: &str,
+    chapter: Option<&get_chapter_by_id::Data>,
+    requested_language: Option<&String>,
+    layout: Option<Layout>,
+    tag_language: bool,
+) -> (Utf8PathBuf, Option<ComicInfo>) {
+    if let (Some(chapter), Some(requested_language)) = (chapter, requested_language) {
+        check_chapter_language(
+            chapter_id,
+            chapter.attributes.title.as_deref(),
+            chapter.attributes.translated_language.as_deref(),
+            requested_language,
+        );
+    }
+
+    (
+        chapter_path(chapter_id, chapter, layout, tag_language),
+        chapter.map(ComicInfo::from),
+    )
+}
+
+/// Where to write a chapter archive
+struct Destination {
+    path: Utf8PathBuf,
+    /// Metadata written to the archive, when the chapter information could be fetched
+    comic_info: Option<ComicInfo>,
+}
+
+/// Chapters to download, and where to write them
+struct Destinations {
+    chapter_ids: Vec<String>,
+    paths: HashMap<String, Destination>,
+    /// Decisions taken for the chapters whose archive already exists
+    decisions: Vec<(String, Decision)>,
+    /// Chapters hosted on an external website, they have no pages to download
+    external: Vec<String>,
+}
+
+/// Computes the path of each chapter archive, and applies the `--if-exists` policy to the existing ones
+async fn destinations(
+    chapter_ids: Vec<String>,
+    languages: &HashMap<String, String>,
+    layout: Option<Layout>,
+    outdir: &Utf8Path,
+    if_exists: IfExists,
+) -> Result<Destinations> {
+    let client = Client::shared();
+    let tag_language = languages.values().collect::<HashSet<_>>().len() > 1;
+    let mut destinations = Destinations {
+        chapter_ids: Vec::with_capacity(chapter_ids.len()),
+        paths: HashMap::with_capacity(chapter_ids.len()),
+        decisions: Vec::new(),
+        external: Vec::new(),
+    };
+
+    for chapter_id in chapter_ids {
+        let chapter = chapter_info(&chapter_id).await;
+        if chapter
+            .as_ref()
+            .is_some_and(|chapter| !chapter.attributes.is_hosted())
+        {
+            warn!("chapter {chapter_id} is hosted on an external website, skipping it");
+            destinations.external.push(chapter_id);
+            continue;
+        }
+        let language = languages.get(&chapter_id);
+        let (path, comic_info) = checked_chapter_path(
+            &chapter_id,
+            chapter.as_ref(),
+            language,
+            layout,
+            tag_language,
+        );
+        let path = outdir.join(path);
+        let decision = if_exists.decide(&chapter_id, &path, &client).await?;
+        let destination = match &decision {
+            Some(decision) => decision.destination(&path).map(Utf8Path::to_path_buf),
+            None => Some(path),
+        };
+        if let Some(destination) = destination {
+            destinations.chapter_ids.push(chapter_id.clone());
+            destinations.paths.insert(
+                chapter_id.clone(),
+                Destination {
+                    path: destination,
+                    comic_info,
+                },
+            );
+        }
+        if let Some(decision) = decision {
+            destinations.decisions.push((chapter_id, decision));
+        }
+    }
+
+    Ok(destinations)
+}
+
+/// Checks the free space of the output directory as the archives are written
+struct SpaceCheck {
+    outdir: Utf8PathBuf,
+    remaining: u64,
+    written: u64,
+    written_bytes: u64,
+}
+
+impl SpaceCheck {
+    /// Checks there is room for all the chapters, assuming they weigh [`ESTIMATED_CHAPTER_SIZE`]
+    fn new(outdir: &Utf8Path, chapters: usize) -> dexter_core::Result<Self> {
+        let chapters = chapters as u64;
+        ensure_available_space(outdir, ESTIMATED_CHAPTER_SIZE.saturating_mul(chapters))?;
+
+        Ok(Self {
+            outdir: outdir.to_path_buf(),
+            remaining: chapters,
+            written: 0,
+            written_bytes: 0,
+        })
+    }
+
+    /// Records a chapter, written or not, and checks there is still room for the remaining ones,
+    /// assuming they weigh as much as the archives written so far on average
+    fn record(&mut self, path: Option<&Utf8Path>) -> dexter_core::Result<()> {
+        self.remaining = self.remaining.saturating_sub(1);
+        if let Some(metadata) = path.and_then(|path| path.metadata().ok()) {
+            self.written += 1;
+            self.written_bytes += metadata.len();
+        }
+        if self.remaining == 0 || self.written == 0 {
+            return Ok(());
+        }
+
+        ensure_available_space(
+            &self.outdir,
+            (self.written_bytes / self.written).saturating_mul(self.remaining),
+        )
+    }
+}
+
+/// Writes the chapter archive and runs the hooks, returning its path
+async fn write_chapter(
+    hooks: &Hooks,
+    chapter_id: &str,
+    cbz_writer: CbzWriter<Cursor<Vec<u8>>>,
+    Destination { path, comic_info }: Destination,
+    write_policy: WritePolicy,
+) -> Result<Utf8PathBuf> {
+    if let Some(parent) = path.parent() {
+        create_dir_all(parent)?;
+    }
+    write_atomically_with_comic_info(cbz_writer, &path, write_policy, comic_info.as_ref())?;
+    hooks.chapter_downloaded(chapter_id, &path).await;
+    Ok(path)
+}
+
+/// Displays the progress of the batch in a progress bar, and returns the summary once the batch is done
+async fn display_progress(
+    mut rx: mpsc::Receiver<batch_archive_download::Event>,
+) -> Result<Summary> {
+    let mut summary = Summary::new();
+    let mut bar = ProgressBar::new(0);
+
+    while let Some(event) = rx.recv().await {
+        summary.record(&event);
+        match event {
+            batch_archive_download::Event::Init(len) => {
+                bar = ProgressBar::new(len as u64);
+                bar.set_style(
+                    ProgressStyle::default_bar()
+                        .template("[{elapsed_precise}] [{wide_bar}] {pos}/{len} {msg}")
+                        .map_err(|err| anyhow!("couldn't set progress template: {err}"))?,
+                );
+            }
+            batch_archive_download::Event::Chapter(..) => {
+                bar.set_message(HumanBytes(summary.bytes_downloaded).to_string());
+            }
+            batch_archive_download::Event::ChapterDone(_)
+            | batch_archive_download::Event::ChapterFailed(_) => bar.inc(1),
+            batch_archive_download::Event::Done => bar.finish(),
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Prints the report in the requested format, and writes it to the `summary` file as json if any
+fn print_report(report: &Report, output: Output, summary: Option<&Utf8Path>) -> Result<()> {
+    match output {
+        Output::Text => println!("{report}"),
+        Output::Json => println!("{}", serde_json::to_string_pretty(report)?),
+    }
+
+    if let Some(summary) = summary {
+        write_bytes_atomically(
+            serde_json::to_vec_pretty(report)?,
+            summary,
+            WritePolicy::Overwrite,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Built-in transforms selected by `--grayscale` and `--max-page-size`, grayscale conversion going first
+pub fn page_transforms(grayscale: bool, max_page_size: Option<(u32, u32)>) -> Transforms {
+    let mut transforms = Transforms::new();
+    if grayscale {
+        transforms = transforms.with(Grayscale);
+    }
+    if let Some((max_width, max_height)) = max_page_size {
+        transforms = transforms.with(Downscale {
+            max_width,
+            max_height,
+        });
+    }
+    transforms
+}
+
+/// Returns the output directory, defaulting to the current directory, after creating it if needed
+fn output_dir(outdir: Option<Utf8PathBuf>) -> Result<Utf8PathBuf> {
+    let outdir = match outdir {
+        Some(outdir) => outdir,
+        None => std::env::current_dir()?.try_into()?,
+    };
+    if !outdir.exists() {
+        create_dir_all(&outdir)?;
+    }
+
+    Ok(outdir)
+}
+
+/// Downloads all the chapters, writing one archive per chapter in `outdir`, and prints a summary
+pub async fn batch_download(
+    BatchDownload {
+        chapter_ids,
+        manga_id,
+        languages,
+        chapters,
+        latest,
+        outdir,
+        max_download_retries,
+        stall_timeout: stall_timeout_secs,
+        chapter_deadline,
+        overwrite: _,
+        no_clobber,
+        if_exists: if_exists_arg,
+        skip_space_check,
+        summary,
+        page_store,
+        on_chapter_downloaded,
+        webhook,
+        output,
+        layout,
+        grayscale,
+        max_page_size,
+    }: BatchDownload,
+) -> Result<()> {
+    let SeriesChapters {
+        chapter_ids,
+        languages,
+        aggregates,
+    } = match manga_id {
+        Some(manga_id) => series_chapters(&manga_id, languages, chapters.as_ref(), latest).await?,
+        None => SeriesChapters {
+            chapter_ids,
+            languages: HashMap::new(),
+            aggregates: Vec::new(),
+        },
+    };
+
+    let outdir = output_dir(outdir)?;
+    let if_exists = if_exists(if_exists_arg, no_clobber);
+    let Destinations {
+        chapter_ids,
+        mut paths,
+        decisions,
+        external,
+    } = destinations(chapter_ids, &languages, layout, &outdir, if_exists).await?;
+    let mut space_check = (!skip_space_check)
+        .then(|| SpaceCheck::new(&outdir, chapter_ids.len()))
+        .transpose()?;
+    let mut out_of_space = None;
+    let hooks = Hooks {
+        on_chapter_downloaded,
+        webhook,
+    };
+
+    let (tx, rx) = mpsc::channel(EVENTS_CAPACITY);
+    let progress_handle = tokio::spawn(display_progress(rx));
+
+    let cancellation_token = CancellationToken::new();
+    let ctrl_c_listener = cancel_on_ctrl_c(cancellation_token.clone());
+
+    let mut written = Vec::new();
+    let mut write_failures = Vec::new();
+    let page_store = page_store.map(PageStore::new).transpose()?.map(Arc::new);
+    let mut batch_archive_download = BatchArchiveDownload::new(chapter_ids)
+        .set_max_download_retries(max_download_retries)
+        .set_stall_timeout(stall_timeout(stall_timeout_secs))
+        .set_chapter_deadline(chapter_deadline.map(Duration::from_secs))
+        .set_transforms(page_transforms(grayscale, max_page_size))
+        .set_bounded_sender(tx)
+        .set_cancellation_token(cancellation_token.clone());
+    if let Some(page_store) = &page_store {
+        batch_archive_download = batch_archive_download.set_page_store(Arc::clone(page_store));
+    }
+    let mut downloads = batch_archive_download.request().await?;
+
+    while let Some((chapter_id, cbz_writer)) = downloads.next().await {
+        let path = match (cbz_writer, paths.remove(&chapter_id)) {
+            (Ok(cbz_writer), Some(destination)) => {
+                let write_policy = if_exists.write_policy();
+                match write_chapter(&hooks, &chapter_id, cbz_writer, destination, write_policy)
+                    .await
+                {
+                    Ok(path) => Some(path),
+                    Err(err) => {
+                        error!("failed to write chapter {chapter_id}: {err}");
+                        write_failures.push(chapter_id);
+                        None
+                    }
+                }
+            }
+            _ => None,
+        };
+        if let (Some(space_check), None) = (&mut space_check, &out_of_space) {
+            if let Err(err) = space_check.record(path.as_deref()) {
+                error!("{err}, cancelling the remaining downloads");
+                cancellation_token.cancel();
+                out_of_space = Some(err);
+            }
+        }
+        written.extend(path);
+    }
+    drop(downloads);
+
+    drop(ctrl_c_listener);
+
+    let mut batch_summary = progress_handle.await??;
+    for path in written {
+        batch_summary.record_path(path);
+    }
+    for chapter_id in write_failures {
+        batch_summary.record_write_failure(chapter_id);
+    }
+    for (chapter_id, decision) in decisions {
+        batch_summary.record_decision(chapter_id, decision);
+    }
+    let report = Report::new(batch_summary, aggregates, &external, page_store.as_deref());
+
+    print_report(&report, output, summary.as_deref())?;
+
+    if let Some(err) = out_of_space {
+        Err(err.into())
+    } else if report.summary.failed.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow!("{} chapter(s) failed", report.summary.failed.len()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dexter_core::api::{get_chapter, get_chapter_by_id, get_manga};
+
+    use super::*;
+
+    fn chapter(id: &str, chapter: Option<&str>, language: &str) -> get_chapter_by_id::Data {
+        get_chapter_by_id::Data {
+            id: id.to_string(),
+            attributes: get_chapter::Attributes {
+                volume: Some("1".to_string()),
+                chapter: chapter.map(ToString::to_string),
+                title: None,
+                translated_language: Some(language.to_string()),
+                publish_at: None,
+                readable_at: None,
+                created_at: None,
+                pages: Some(12),
+                external_url: None,
+            },
+            relationships: vec![get_chapter_by_id::Relationship {
+                id: "7f30dfc3-0b80-4dcc-a3b9-0cd746fac005".to_string(),
+                type_: "manga".to_string(),
+                attributes: Some(get_manga::Attributes {
+                    title: get_manga::Title {
+                        en: "Detective Conan".to_string(),
+                    },
+                }),
+            }],
+        }
+    }
+
+    #[test]
+    fn chapter_paths_in_several_languages() {
+        let en = chapter("en-id", Some("1"), "en");
+        let fr = chapter("fr-id", Some("1"), "fr");
+
+        let en_path = chapter_path("en-id", Some(&en), Some(Layout::Komga), true);
+        let fr_path = chapter_path("fr-id", Some(&fr), Some(Layout::Komga), true);
+
+        assert_eq!(en_path, "Detective Conan/Detective Conan v01 c001 [en].cbz");
+        assert_eq!(fr_path, "Detective Conan/Detective Conan v01 c001 [fr].cbz");
+        assert_eq!(
+            chapter_path("en-id", Some(&en), Some(Layout::Komga), false),
+            "Detective Conan/Detective Conan v01 c001.cbz"
+        );
+    }
+
+    #[test]
+    fn chapter_paths_without_number() {
+        let first = chapter("first-id", None, "en");
+        let second = chapter("second-id", None, "en");
+
+        assert_eq!(
+            chapter_path("first-id", Some(&first), Some(Layout::Plain), false),
+            "Detective Conan - Vol.01 [first-id].cbz"
+        );
+        assert_eq!(
+            chapter_path("second-id", Some(&second), Some(Layout::Plain), false),
+            "Detective Conan - Vol.01 [second-id].cbz"
+        );
+    }
+}
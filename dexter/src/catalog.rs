@@ -0,0 +1,171 @@
+use std::{collections::BTreeMap, time::UNIX_EPOCH};
+
+use anyhow::Result;
+use camino::{Utf8Path, Utf8PathBuf};
+use dexter_core::{output::page_count, WritePolicy};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::library::{ComicInfo, Entry};
+
+/// Name of the catalog file, at the root of the library
+static CATALOG_FILE_NAME: &str = ".dexter-library.json";
+
+/// Metadata of an archive, as read when it was cataloged
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CatalogedArchive {
+    pub size: u64,
+    /// Modification time of the archive when it was cataloged, in seconds since the epoch
+    pub modified: u64,
+    pub comic_info: ComicInfo,
+    /// `None` if the pages couldn't be counted
+    pub pages: Option<usize>,
+}
+
+/// Metadata of the library archives, indexed by path relative to the library,
+/// so that the archives which didn't change since are not opened again
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Catalog {
+    archives: BTreeMap<String, CatalogedArchive>,
+}
+
+impl Catalog {
+    fn path(dir: &Utf8Path) -> Utf8PathBuf {
+        dir.join(CATALOG_FILE_NAME)
+    }
+
+    /// Reads the catalog of the library, empty if the library was never cataloged or if the catalog is invalid
+    pub fn load(dir: &Utf8Path) -> Self {
+        let path = Self::path(dir);
+        let Ok(content) = std::fs::read(&path) else {
+            return Self::default();
+        };
+        match serde_json::from_slice(&content) {
+            Ok(catalog) => catalog,
+            Err(err) => {
+                warn!("invalid catalog {path}, the archives are read again: {err}");
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self, dir: &Utf8Path) -> Result<()> {
+        dexter_core::output::write_bytes_atomically(
+            serde_json::to_vec(self)?,
+            &Self::path(dir),
+            WritePolicy::Overwrite,
+        )?;
+
+        Ok(())
+    }
+
+    /// Returns the entry of the archive along with its cataloged metadata,
+    /// the archive is only read if it changed since it was cataloged, or if it never was
+    pub fn read(
+        &mut self,
+        dir: &Utf8Path,
+        path: Utf8PathBuf,
+    ) -> Result<(Entry, &CatalogedArchive)> {
+        let metadata = std::fs::metadata(&path)?;
+        let size = metadata.len();
+        let modified = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let key = path.strip_prefix(dir).unwrap_or(&path).to_string();
+
+        let entry = match self.archives.get(&key) {
+            Some(archive) if archive.size == size && archive.modified == modified => {
+                Entry::new(path, archive.comic_info.clone())
+            }
+            _ => {
+                let pages = page_count(&path)
+                    .inspect_err(|err| warn!("failed to count the pages of {path}: {err}"))
+                    .ok();
+                let entry = Entry::read(path);
+                self.archives.insert(
+                    key.clone(),
+                    CatalogedArchive {
+                        size,
+                        modified,
+                        comic_info: entry.comic_info.clone(),
+                        pages,
+                    },
+                );
+                entry
+            }
+        };
+
+        Ok((entry, &self.archives[&key]))
+    }
+
+    /// Forgets the archives which are not in `paths` anymore
+    pub fn retain(&mut self, dir: &Utf8Path, paths: &[Utf8PathBuf]) {
+        let keys = paths
+            .iter()
+            .map(|path| path.strip_prefix(dir).unwrap_or(path).as_str())
+            .collect::<Vec<_>>();
+        self.archives.retain(|key, _| keys.contains(&key.as_str()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::File, io::Write};
+
+    use zip::{write::FileOptions, ZipWriter};
+
+    use super::*;
+
+    #[test]
+    fn read_unchanged_archives_from_the_catalog() {
+        let dir = Utf8PathBuf::try_from(std::env::temp_dir())
+            .unwrap()
+            .join(format!("dexter-catalog-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("chapter.cbz");
+        let mut writer = ZipWriter::new(File::create(&path).unwrap());
+        writer.start_file("1.png", FileOptions::default()).unwrap();
+        writer.write_all(b"page").unwrap();
+        writer
+            .start_file("ComicInfo.xml", FileOptions::default())
+            .unwrap();
+        writer
+            .write_all(b"<ComicInfo><Series>Detective Conan</Series></ComicInfo>")
+            .unwrap();
+        writer.finish().unwrap();
+
+        let mut catalog = Catalog::default();
+        catalog.read(&dir, path.clone()).unwrap();
+        catalog.save(&dir).unwrap();
+
+        // Same size and modification time, the archive isn't read again
+        let file = File::options().write(true).open(&path).unwrap();
+        let modified = file.metadata().unwrap().modified().unwrap();
+        let size = file.metadata().unwrap().len();
+        (&file)
+            .write_all(&vec![0; usize::try_from(size).unwrap()])
+            .unwrap();
+        file.set_modified(modified).unwrap();
+        drop(file);
+
+        let mut catalog = Catalog::load(&dir);
+        let (entry, archive) = catalog.read(&dir, path.clone()).unwrap();
+        assert_eq!(entry.series(), "Detective Conan");
+        assert_eq!(archive.pages, Some(1));
+
+        // Changed since, the archive is read again
+        std::fs::write(&path, "not an archive").unwrap();
+        let (entry, archive) = catalog.read(&dir, path.clone()).unwrap();
+        assert_eq!(
+            entry.series(),
+            format!("dexter-catalog-{}", std::process::id())
+        );
+        assert_eq!(archive.pages, None);
+
+        catalog.retain(&dir, &[]);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(catalog.archives.is_empty());
+    }
+}
@@ -0,0 +1,45 @@
+use anyhow::Result;
+use dexter_core::{
+    api::get_chapters::{Order, DEFAULT_CHAPTERS_LIMIT},
+    feed::atom,
+    output::write_bytes_atomically,
+    GetChapters, GetManga, Request, WritePolicy,
+};
+use futures::{StreamExt, TryStreamExt};
+use tracing::info;
+
+use crate::args::Feed;
+
+/// Writes an Atom feed of the most recent chapters of a manga, to the `out` file or to the standard output.
+///
+/// Feed readers only need the file, served or synced anywhere, no `MangaDex` account is involved.
+pub async fn feed(
+    Feed {
+        manga_id,
+        out,
+        languages,
+        limit,
+    }: Feed,
+) -> Result<()> {
+    let manga = GetManga::new(&manga_id).request().await?;
+    let chapters = GetChapters::new(&manga_id)
+        .set_limit(limit.min(DEFAULT_CHAPTERS_LIMIT))
+        .with_languages(languages)
+        .set_order(Order::ReadableAt)
+        .request_all()
+        .take(usize::try_from(limit).unwrap_or(usize::MAX))
+        .try_collect::<Vec<_>>()
+        .await?;
+
+    let feed = atom(&manga.data, &chapters);
+    match out {
+        Some(out) => {
+            // Feed readers polling the file never see it half written
+            write_bytes_atomically(feed, &out, WritePolicy::Overwrite)?;
+            info!("feed of {} chapters written to {out}", chapters.len());
+        }
+        None => print!("{feed}"),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,196 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use camino::Utf8Path;
+use dexter_core::Client;
+use serde::Serialize;
+use tokio::{process::Command, time::timeout};
+use tracing::{info, warn};
+
+/// Commands and webhooks taking longer are killed, so that a stuck hook doesn't hold the remaining downloads
+const HOOK_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Payload posted to the webhook
+#[derive(Debug, Serialize)]
+struct Payload<'a> {
+    event: &'static str,
+    chapter_id: &'a str,
+    path: &'a Utf8Path,
+}
+
+/// Commands and webhooks run once a chapter archive is written, their failures are logged but don't fail the download
+#[derive(Debug, Default)]
+pub struct Hooks {
+    pub on_chapter_downloaded: Option<String>,
+    pub webhook: Option<String>,
+}
+
+/// Quotes `value` for a posix shell
+#[cfg(not(windows))]
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+/// Quotes `value` for `cmd`, `%` is left out of the quotes so that it isn't expanded as a variable
+#[cfg(windows)]
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('"', "\"\"").replace('%', "\"^%\""))
+}
+
+#[cfg(not(windows))]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    // The command is already quoted for cmd, it must not be quoted again as a program argument
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").raw_arg(command);
+    shell
+}
+
+impl Hooks {
+    /// Runs the hooks for a chapter archive written at `path`
+    pub async fn chapter_downloaded(&self, chapter_id: &str, path: &Utf8Path) {
+        if let Some(command) = &self.on_chapter_downloaded {
+            if let Err(err) = run_command(command, chapter_id, path, HOOK_TIMEOUT).await {
+                warn!("on chapter downloaded hook failed for chapter {chapter_id}: {err}");
+            }
+        }
+        if let Some(url) = &self.webhook {
+            let payload = Payload {
+                event: "chapter_downloaded",
+                chapter_id,
+                path,
+            };
+            if let Err(err) = post_webhook(url, &payload).await {
+                warn!("webhook failed for chapter {chapter_id}: {err}");
+            }
+        }
+    }
+}
+
+/// Replaces `{path}` and `{chapter_id}` with their quoted values, in one pass so that the values are never substituted
+fn substitute(command: &str, chapter_id: &str, path: &Utf8Path) -> String {
+    let mut substituted = String::with_capacity(command.len());
+    let mut rest = command;
+    while let Some(start) = rest.find('{') {
+        substituted.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if let Some(after) = rest.strip_prefix("{path}") {
+            substituted.push_str(&quote(path.as_str()));
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("{chapter_id}") {
+            substituted.push_str(&quote(chapter_id));
+            rest = after;
+        } else {
+            substituted.push('{');
+            rest = &rest[1..];
+        }
+    }
+    substituted.push_str(rest);
+    substituted
+}
+
+/// Runs `command` in a shell with the placeholders substituted, it's killed after `max_duration`
+async fn run_command(
+    command: &str,
+    chapter_id: &str,
+    path: &Utf8Path,
+    max_duration: Duration,
+) -> Result<()> {
+    let command = substitute(command, chapter_id, path);
+    info!("Running {command}");
+    let mut child = shell(&command).kill_on_drop(true).spawn()?;
+    let status = timeout(max_duration, child.wait())
+        .await
+        .map_err(|_| anyhow!("`{command}` timed out after {max_duration:?}"))??;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(anyhow!("`{command}` exited with {status}"))
+    }
+}
+
+async fn post_webhook(url: &str, payload: &Payload<'_>) -> Result<()> {
+    Client::shared()
+        .inner()
+        .post(url)
+        .timeout(HOOK_TIMEOUT)
+        .json(payload)
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+#[cfg(all(test, not(windows)))]
+mod tests {
+    use camino::Utf8PathBuf;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn command_placeholders() {
+        let path = Utf8PathBuf::try_from(std::env::temp_dir())
+            .unwrap()
+            .join(format!("dexter hook's {}.txt", std::process::id()));
+
+        run_command(
+            "printf '%s' {chapter_id} > {path}",
+            "it's-an-id",
+            &path,
+            HOOK_TIMEOUT,
+        )
+        .await
+        .unwrap();
+
+        let written = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(written, "it's-an-id");
+    }
+
+    #[tokio::test]
+    async fn failing_command() {
+        let res = run_command(
+            "exit 3",
+            "first-id",
+            Utf8Path::new("chapter.cbz"),
+            HOOK_TIMEOUT,
+        )
+        .await;
+
+        assert!(res.unwrap_err().to_string().contains("exited with"));
+    }
+
+    #[test]
+    fn placeholders_in_values() {
+        // The path contains a placeholder itself, it's not replaced by the chapter id
+        let command = substitute(
+            "import {path} --id {chapter_id} {unknown}",
+            "first-id",
+            Utf8Path::new("{chapter_id}.cbz"),
+        );
+
+        assert_eq!(
+            command,
+            "import '{chapter_id}.cbz' --id 'first-id' {unknown}"
+        );
+    }
+
+    #[tokio::test]
+    async fn command_timeout() {
+        let res = run_command(
+            "sleep 5",
+            "first-id",
+            Utf8Path::new("chapter.cbz"),
+            Duration::from_millis(100),
+        )
+        .await;
+
+        assert!(res.unwrap_err().to_string().contains("timed out"));
+    }
+}
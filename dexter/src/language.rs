@@ -0,0 +1,38 @@
+use isolang::Language;
+use tracing::warn;
+
+/// Detects the language of the text, as an ISO 639-1 code, `None` if the detection isn't reliable,
+/// short texts such as titles only being reliably detected when written in a distinctive script
+pub fn detect(text: &str) -> Option<&'static str> {
+    let info = whatlang::detect(text)?;
+    if !info.is_reliable() {
+        return None;
+    }
+
+    Language::from_639_3(info.lang().code())?.to_639_1()
+}
+
+/// Warns when the chapter doesn't look like it's in the requested language,
+/// either because of its `translatedLanguage`, or because of the language its title is written in,
+/// some groups mislabeling their uploads
+pub fn check_chapter_language(
+    chapter_id: &str,
+    title: Option<&str>,
+    translated_language: Option<&str>,
+    requested_language: &str,
+) {
+    if let Some(translated_language) = translated_language {
+        if translated_language != requested_language {
+            warn!("chapter {chapter_id} is in {translated_language}, not in the requested {requested_language}");
+            return;
+        }
+    }
+
+    // Regional variants, e.g. `pt-br`, aren't detected
+    let requested_primary_language = requested_language.split('-').next().unwrap_or_default();
+    if let Some(detected_language) = title.and_then(detect) {
+        if detected_language != requested_primary_language {
+            warn!("chapter {chapter_id} is labeled {requested_language} but its title looks {detected_language}, the upload may be mislabeled");
+        }
+    }
+}
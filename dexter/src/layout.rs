@@ -0,0 +1,205 @@
+use std::fmt::Write as _;
+
+use camino::{Utf8Path, Utf8PathBuf};
+use clap::ValueEnum;
+use dexter_core::naming::{self, file_name_with_extension, series_name};
+
+/// Metadata read back from an archive path written by any of the layouts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedPath {
+    pub series: String,
+    pub volume: Option<String>,
+    pub chapter: Option<String>,
+    pub tags: Vec<String>,
+}
+
+/// Folder structure of the archives, following the naming conventions of the media servers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Layout {
+    /// `Series/Series v01 c001.cbz`, as Komga treats every folder as a series
+    Komga,
+    /// `Series/Series Vol.01/Series Vol.01 Ch.001.cbz`
+    Kavita,
+    /// `Series - Vol.01 Ch.001.cbz`, all in the same folder
+    Plain,
+}
+
+impl Layout {
+    /// Returns the path of the chapter archive, relative to the library root.
+    ///
+    /// The `tags` are appended to the file name between brackets, e.g. `Series v01 c001 [fr].cbz`,
+    /// to tell apart the chapters that would share a path otherwise. Media servers ignore them.
+    pub fn path(
+        self,
+        series: &str,
+        volume: Option<&str>,
+        chapter: Option<&str>,
+        tags: &[&str],
+    ) -> Utf8PathBuf {
+        let series = series_name(series);
+        let (volume_prefix, chapter_prefix) = match self {
+            Self::Komga => ("v", "c"),
+            Self::Kavita | Self::Plain => ("Vol.", "Ch."),
+        };
+        let volume = volume.map(|volume| format!("{volume_prefix}{}", pad(volume, 2)));
+        let chapter = chapter.map(|chapter| format!("{chapter_prefix}{}", pad(chapter, 3)));
+        let numbers = volume
+            .iter()
+            .chain(chapter.iter())
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join(" ");
+        let tags = tags.iter().fold(String::new(), |mut tags, tag| {
+            let _ = write!(tags, " [{tag}]");
+            tags
+        });
+
+        let file_name = match (self, numbers.is_empty()) {
+            (_, true) => file_name_with_extension(&format!("{series}{tags}"), "cbz"),
+            (Self::Komga | Self::Kavita, false) => {
+                file_name_with_extension(&format!("{series} {numbers}{tags}"), "cbz")
+            }
+            (Self::Plain, false) => {
+                file_name_with_extension(&format!("{series} - {numbers}{tags}"), "cbz")
+            }
+        };
+
+        match (self, volume) {
+            (Self::Plain, _) => Utf8PathBuf::from(file_name),
+            (Self::Kavita, Some(volume)) => [
+                series.clone(),
+                naming::file_name(&format!("{series} {volume}")),
+                file_name,
+            ]
+            .iter()
+            .collect(),
+            (Self::Komga | Self::Kavita, _) => [series, file_name].iter().collect(),
+        }
+    }
+}
+
+/// Reads the series, numbers and tags back from an archive path written by [`Layout::path`],
+/// for the archives without `ComicInfo.xml`.
+///
+/// Returns `None` if the file name has neither a volume nor a chapter number, unless its series
+/// is the name of the parent directory, so that arbitrary archives aren't given a made up series.
+pub fn parse(path: &Utf8Path) -> Option<ParsedPath> {
+    let mut rest = path.file_stem()?;
+
+    let mut tags = Vec::new();
+    while let Some((head, tag)) = rest
+        .strip_suffix(']')
+        .and_then(|rest| rest.rsplit_once(" ["))
+    {
+        if tag.is_empty() || tag.contains(['[', ']']) {
+            break;
+        }
+        tags.insert(0, tag.to_string());
+        rest = head;
+    }
+
+    let mut number = |prefixes: &[&str]| {
+        let (head, token) = rest.rsplit_once(' ')?;
+        let number = prefixes
+            .iter()
+            .find_map(|prefix| token.strip_prefix(prefix))
+            .filter(|number| number.starts_with(|c: char| c.is_ascii_digit()))?;
+        rest = head;
+        Some(unpad(number))
+    };
+    let chapter = number(&["Ch.", "c"]);
+    let volume = number(&["Vol.", "v"]);
+
+    let series = rest.strip_suffix(" -").unwrap_or(rest);
+    let in_series_dir = path
+        .parent()
+        .and_then(Utf8Path::file_name)
+        .is_some_and(|dir| dir == series);
+    if series.is_empty() || (volume.is_none() && chapter.is_none() && !in_series_dir) {
+        return None;
+    }
+
+    Some(ParsedPath {
+        series: series.to_string(),
+        volume,
+        chapter,
+        tags,
+    })
+}
+
+/// Zero pads the whole part of the number to `width`, `1` is `001` and `1.5` is `001.5`
+pub fn pad(number: &str, width: usize) -> String {
+    let (whole, fraction) = number.split_at(number.find('.').unwrap_or(number.len()));
+    format!("{whole:0>width$}{fraction}")
+}
+
+/// Removes the zero padding added by [`Layout::path`], `001` is `1` and `00.5` is `0.5`
+fn unpad(number: &str) -> String {
+    let unpadded = number.trim_start_matches('0');
+    if unpadded.is_empty() || unpadded.starts_with('.') {
+        format!("0{unpadded}")
+    } else {
+        unpadded.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_layout_paths() {
+        for layout in [Layout::Komga, Layout::Kavita, Layout::Plain] {
+            for (volume, chapter, tags) in [
+                (Some("1"), Some("10.5"), vec!["en"]),
+                (None, Some("0"), vec![]),
+                (Some("12"), None, vec!["fr", "first-id"]),
+            ] {
+                let path = layout.path("Detective Conan", volume, chapter, &tags);
+                assert_eq!(
+                    parse(&path),
+                    Some(ParsedPath {
+                        series: "Detective Conan".to_string(),
+                        volume: volume.map(ToString::to_string),
+                        chapter: chapter.map(ToString::to_string),
+                        tags: tags.iter().map(ToString::to_string).collect(),
+                    }),
+                    "{path}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn pad_decimal_chapters() {
+        assert_eq!(
+            Layout::Komga.path("Detective Conan", Some("1"), Some("1.5"), &[]),
+            Utf8PathBuf::from("Detective Conan/Detective Conan v01 c001.5.cbz")
+        );
+        assert_eq!(
+            Layout::Plain.path("Detective Conan", None, Some("10.25"), &[]),
+            Utf8PathBuf::from("Detective Conan - Ch.010.25.cbz")
+        );
+        assert_eq!(pad("1234", 3), "1234");
+        assert_eq!(pad(".5", 3), "000.5");
+    }
+
+    #[test]
+    fn parse_unnumbered_paths() {
+        assert_eq!(
+            parse(&Layout::Komga.path("Detective Conan", None, None, &["first-id"])),
+            Some(ParsedPath {
+                series: "Detective Conan".to_string(),
+                volume: None,
+                chapter: None,
+                tags: vec!["first-id".to_string()],
+            })
+        );
+        // Nothing tells that the file name is a series
+        assert_eq!(
+            parse(&Layout::Plain.path("Detective Conan", None, None, &[])),
+            None
+        );
+        assert_eq!(parse(Utf8Path::new("downloads/scan_final.cbz")), None);
+    }
+}
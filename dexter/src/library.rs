@@ -0,0 +1,549 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use anyhow::{anyhow, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+use dexter_core::{naming, output::rename_no_clobber, Error};
+use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
+use indicatif::HumanBytes;
+use tracing::warn;
+
+use crate::{
+    archive::read_comic_info,
+    args::{
+        Library, LibraryGrep, LibraryNormalize, LibraryRename, LibrarySearch, LibraryStats,
+        LibrarySubcommands, LibraryThumbnails,
+    },
+    catalog::Catalog,
+    language::detect,
+    layout::{self, ParsedPath},
+    ocr::TextIndex,
+    thumbnails::Thumbnails,
+};
+
+/// The `ComicInfo.xml` fields matched against the query
+static SEARCHED_FIELDS: [&str; 5] = ["Series", "Title", "Writer", "Penciller", "Translator"];
+
+/// How many series are listed in the biggest series section of the stats
+static BIGGEST_SERIES: usize = 5;
+
+/// Top level fields of a `ComicInfo.xml` file, indexed by tag name
+pub type ComicInfo = HashMap<String, String>;
+
+/// A downloaded archive, with its metadata when available
+#[derive(Debug)]
+pub struct Entry {
+    pub path: Utf8PathBuf,
+    pub comic_info: ComicInfo,
+    /// Tags of the file name, e.g. the language in `Series v01 c001 [fr].cbz`
+    pub tags: Vec<String>,
+}
+
+impl Entry {
+    /// Reads the `ComicInfo.xml` of the archive, see [`Entry::new`]
+    pub fn read(path: Utf8PathBuf) -> Self {
+        let comic_info = match read_comic_info(&path) {
+            Ok(Some(comic_info)) => parse_comic_info(&comic_info),
+            Ok(None) => ComicInfo::new(),
+            Err(err) => {
+                warn!("failed to read the metadata of {path}: {err}");
+                ComicInfo::new()
+            }
+        };
+
+        Self::new(path, comic_info)
+    }
+
+    /// The series and numbers missing from the metadata are read from the path
+    /// when it follows one of the layouts
+    pub fn new(path: Utf8PathBuf, mut comic_info: ComicInfo) -> Self {
+        let tags = match layout::parse(&path) {
+            Some(ParsedPath {
+                series,
+                volume,
+                chapter,
+                tags,
+            }) => {
+                for (field, value) in [
+                    ("Series", Some(series)),
+                    ("Volume", volume),
+                    ("Number", chapter),
+                ] {
+                    if let Some(value) = value {
+                        comic_info.entry(field.to_string()).or_insert(value);
+                    }
+                }
+                tags
+            }
+            None => Vec::new(),
+        };
+
+        Self {
+            path,
+            comic_info,
+            tags,
+        }
+    }
+
+    /// Best score among the file name and the searched fields, `None` if nothing matches
+    fn score(&self, matcher: &SkimMatcherV2, query: &str) -> Option<i64> {
+        self.path
+            .file_stem()
+            .into_iter()
+            .chain(
+                SEARCHED_FIELDS
+                    .iter()
+                    .filter_map(|field| self.comic_info.get(*field).map(String::as_str)),
+            )
+            .filter_map(|field| matcher.fuzzy_match(field, query))
+            .max()
+    }
+
+    /// The series from the metadata, or the name of the directory containing the archive
+    pub fn series(&self) -> String {
+        self.comic_info
+            .get("Series")
+            .map(String::as_str)
+            .or_else(|| self.path.parent().and_then(Utf8Path::file_name))
+            .unwrap_or("unknown")
+            .to_string()
+    }
+
+    /// Language detected from the extracted text of the pages when indexed, or from the title and summary otherwise
+    fn detected_language(&self, dir: &Utf8Path, index: &TextIndex) -> Option<&'static str> {
+        let key = self.path.strip_prefix(dir).unwrap_or(&self.path);
+        let text = match index.get(key.as_str()) {
+            Some(archive) => archive.pages.join("\n"),
+            None => ["Title", "Summary"]
+                .iter()
+                .filter_map(|field| self.comic_info.get(*field).map(String::as_str))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        };
+
+        detect(&text)
+    }
+
+    fn language(&self) -> String {
+        self.comic_info
+            .get("LanguageISO")
+            .map_or_else(|| String::from("unknown"), Clone::clone)
+    }
+}
+
+fn parse_comic_info(comic_info: &str) -> ComicInfo {
+    let document = match roxmltree::Document::parse(comic_info) {
+        Ok(document) => document,
+        Err(err) => {
+            warn!("invalid ComicInfo.xml: {err}");
+            return ComicInfo::new();
+        }
+    };
+
+    document
+        .root_element()
+        .children()
+        .filter_map(|node| Some((node.tag_name().name().to_string(), node.text()?.to_string())))
+        .collect()
+}
+
+pub fn library_dir(dir: Option<Utf8PathBuf>) -> Result<Utf8PathBuf> {
+    match dir {
+        Some(dir) => Ok(dir),
+        None => Ok(std::env::current_dir()?.try_into()?),
+    }
+}
+
+/// Lists all the archives in `dir` and its subdirectories
+pub fn archives(dir: &Utf8Path) -> Result<Vec<Utf8PathBuf>> {
+    let pattern = dir.join("**").join("*.cbz");
+    let mut paths = Vec::new();
+
+    for path in glob::glob(pattern.as_str())? {
+        match Utf8PathBuf::try_from(path?) {
+            Ok(path) => paths.push(path),
+            Err(err) => warn!("skipping non utf-8 path {}", err.as_path().display()),
+        }
+    }
+
+    Ok(paths)
+}
+
+fn search(LibrarySearch { query, dir, limit }: LibrarySearch) -> Result<()> {
+    let matcher = SkimMatcherV2::default();
+    let mut results = archives(&library_dir(dir)?)?
+        .into_iter()
+        .map(Entry::read)
+        .filter_map(|entry| Some((entry.score(&matcher, &query)?, entry.path)))
+        .collect::<Vec<_>>();
+    results.sort_by(|(a, _), (b, _)| b.cmp(a));
+
+    for (_, path) in results.into_iter().take(limit) {
+        println!("{path}");
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Default)]
+struct SeriesStats {
+    chapters: usize,
+    size: u64,
+}
+
+/// Computes the stats from the library catalog, only the archives which changed since they were cataloged are read
+fn stats(LibraryStats { dir, rescan }: LibraryStats) -> Result<()> {
+    let mut series = HashMap::<String, SeriesStats>::new();
+    let mut languages = BTreeMap::<String, usize>::new();
+    let mut detected_languages = BTreeMap::<&str, usize>::new();
+    let mut mislabeled = Vec::new();
+    let mut chapters = 0;
+    let mut pages = 0;
+    let mut size = 0;
+
+    let dir = library_dir(dir)?;
+    let index = TextIndex::load(&dir)?;
+    let mut catalog = if rescan {
+        Catalog::default()
+    } else {
+        Catalog::load(&dir)
+    };
+    let paths = archives(&dir)?;
+    for path in &paths {
+        let (entry, archive) = catalog.read(&dir, path.clone())?;
+        let entry_size = archive.size;
+        pages += archive.pages.unwrap_or_default();
+
+        chapters += 1;
+        size += entry_size;
+        *languages.entry(entry.language()).or_default() += 1;
+        let detected_language = entry.detected_language(&dir, &index);
+        *detected_languages
+            .entry(detected_language.unwrap_or("unknown"))
+            .or_default() += 1;
+        if let (Some(detected_language), Some(language)) =
+            (detected_language, entry.comic_info.get("LanguageISO"))
+        {
+            if !language.starts_with(detected_language) {
+                mislabeled.push((entry.path.clone(), language.clone(), detected_language));
+            }
+        }
+        let series_stats = series.entry(entry.series()).or_default();
+        series_stats.chapters += 1;
+        series_stats.size += entry_size;
+    }
+    catalog.retain(&dir, &paths);
+    catalog.save(&dir)?;
+
+    println!("Series: {}", series.len());
+    println!("Chapters: {chapters}");
+    println!("Pages: {pages}");
+    println!("Total size: {}", HumanBytes(size));
+    println!("Languages:");
+    for (language, chapters) in languages {
+        println!("  {language}: {chapters} chapter(s)");
+    }
+    println!("Detected languages:");
+    for (language, chapters) in detected_languages {
+        println!("  {language}: {chapters} chapter(s)");
+    }
+    if !mislabeled.is_empty() {
+        println!("Possibly mislabeled:");
+        for (path, language, detected_language) in mislabeled {
+            println!("  {path}: labeled {language}, looks {detected_language}");
+        }
+    }
+
+    let mut series = series.into_iter().collect::<Vec<_>>();
+    series.sort_by_key(|(_, series_stats)| std::cmp::Reverse(series_stats.size));
+    println!("Biggest series:");
+    for (name, series_stats) in series.into_iter().take(BIGGEST_SERIES) {
+        println!(
+            "  {name}: {} ({} chapter(s))",
+            HumanBytes(series_stats.size),
+            series_stats.chapters
+        );
+    }
+
+    Ok(())
+}
+
+/// Moves the archives to their place in the layout, never overwriting an existing file.
+///
+/// The tags of the file names are kept, so that chapters told apart by their language still are.
+fn normalize(
+    LibraryNormalize {
+        layout,
+        dir,
+        dry_run,
+    }: LibraryNormalize,
+) -> Result<()> {
+    let dir = library_dir(dir)?;
+
+    let moves = archives(&dir)?
+        .into_iter()
+        .map(Entry::read)
+        .filter_map(|entry| {
+            let Some(series) = entry.comic_info.get("Series") else {
+                warn!("skipping {}, its series is unknown", entry.path);
+                return None;
+            };
+            let tags = entry.tags.iter().map(String::as_str).collect::<Vec<_>>();
+            let target = dir.join(layout.path(
+                series,
+                entry.comic_info.get("Volume").map(String::as_str),
+                entry.comic_info.get("Number").map(String::as_str),
+                &tags,
+            ));
+            Some((entry.path, target))
+        });
+
+    move_archives(moves, dry_run)
+}
+
+/// Renders a naming template such as `{series}/{series} v{volume:2} c{chapter:3}` for the entry,
+/// the numbers after the colons are the widths the values are zero padded to.
+///
+/// Returns `None` if a field used by the template is missing from the entry metadata.
+fn render_template(template: &str, entry: &Entry) -> Result<Option<Utf8PathBuf>> {
+    let mut segments = Vec::new();
+
+    for segment in template.split('/') {
+        let mut rendered = String::new();
+        let mut rest = segment;
+
+        while let Some(start) = rest.find('{') {
+            rendered.push_str(&rest[..start]);
+            let end = rest[start..]
+                .find('}')
+                .ok_or_else(|| anyhow!("unclosed placeholder in template {template}"))?
+                + start;
+            let (name, width) = match rest[start + 1..end].split_once(':') {
+                Some((name, width)) => (name, width.parse::<usize>()?),
+                None => (&rest[start + 1..end], 0),
+            };
+            let value = match name {
+                "series" => Some(entry.series()),
+                "volume" => entry.comic_info.get("Volume").cloned(),
+                "chapter" => entry.comic_info.get("Number").cloned(),
+                "title" => entry.comic_info.get("Title").cloned(),
+                "language" => entry.comic_info.get("LanguageISO").cloned(),
+                "filename" => entry.path.file_stem().map(ToString::to_string),
+                _ => {
+                    return Err(anyhow!(
+                        "unknown placeholder {{{name}}} in template {template}"
+                    ))
+                }
+            };
+            let Some(value) = value else {
+                return Ok(None);
+            };
+            rendered.push_str(&layout::pad(&value, width));
+            rest = &rest[end + 1..];
+        }
+        rendered.push_str(rest);
+
+        segments.push(rendered);
+    }
+    // Not using `set_extension`, which would replace anything after a dot, as in `Vol.01`
+    let Some(file_name) = segments.pop() else {
+        return Ok(None);
+    };
+
+    Ok(Some(
+        segments
+            .iter()
+            .map(|segment| naming::file_name(segment))
+            .chain([naming::file_name_with_extension(&file_name, "cbz")])
+            .collect(),
+    ))
+}
+
+/// Renames the archives to match the template, never overwriting an existing file
+fn rename(
+    LibraryRename {
+        template,
+        dir,
+        dry_run,
+    }: LibraryRename,
+) -> Result<()> {
+    let dir = library_dir(dir)?;
+
+    let mut moves = Vec::new();
+    for entry in archives(&dir)?.into_iter().map(Entry::read) {
+        if let Some(target) = render_template(&template, &entry)? {
+            moves.push((entry.path, dir.join(target)));
+        } else {
+            warn!(
+                "skipping {}, the template uses missing metadata",
+                entry.path
+            );
+        }
+    }
+
+    move_archives(moves, dry_run)
+}
+
+/// Moves the archives to their targets, skipping the ones whose target already exists
+/// or is claimed by another archive, the move never overwrites a file created in the meantime
+fn move_archives(
+    moves: impl IntoIterator<Item = (Utf8PathBuf, Utf8PathBuf)>,
+    dry_run: bool,
+) -> Result<()> {
+    let mut targets = HashSet::new();
+
+    for (path, target) in moves {
+        if target == path {
+            continue;
+        }
+        if target.exists() || !targets.insert(target.clone()) {
+            warn!("skipping {path}, {target} already exists");
+            continue;
+        }
+
+        println!("{path} -> {target}");
+
+        if !dry_run {
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            match rename_no_clobber(&path, &target) {
+                Err(Error::AlreadyExists(_)) => warn!("skipping {path}, {target} already exists"),
+                res => res?,
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn thumbnails(
+    LibraryThumbnails {
+        dir,
+        page,
+        size,
+        cache_dir,
+    }: LibraryThumbnails,
+) -> Result<()> {
+    let thumbnails = Thumbnails::new(match cache_dir {
+        Some(cache_dir) => cache_dir,
+        None => Thumbnails::default_dir()?,
+    });
+
+    for path in archives(&library_dir(dir)?)? {
+        match thumbnails.get(&path, page, size) {
+            Ok(thumbnail) => println!("{path}\t{thumbnail}"),
+            Err(err) => warn!("failed to generate the thumbnail of {path}: {err}"),
+        }
+    }
+
+    Ok(())
+}
+
+/// Extracts the text of the archives which changed since they were last indexed
+#[cfg(feature = "ocr")]
+fn ocr(
+    crate::args::LibraryOcr {
+        dir,
+        language,
+        force,
+    }: crate::args::LibraryOcr,
+) -> Result<()> {
+    use crate::ocr::{recognize, IndexedArchive};
+
+    let dir = library_dir(dir)?;
+    let mut index = TextIndex::load(&dir)?;
+
+    for path in archives(&dir)? {
+        let modified = std::fs::metadata(&path)?
+            .modified()?
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let key = path.strip_prefix(&dir).unwrap_or(&path).to_string();
+        if !force
+            && index
+                .get(&key)
+                .is_some_and(|archive| archive.modified == modified)
+        {
+            continue;
+        }
+
+        match recognize(&path, &language) {
+            Ok(pages) => {
+                println!("{path}: {} page(s) indexed", pages.len());
+                index.insert(key, IndexedArchive { modified, pages });
+                // Saved after each archive so that an interrupted run doesn't lose the work done
+                index.save(&dir)?;
+            }
+            Err(err) => warn!("failed to extract the text of {path}: {err}"),
+        }
+    }
+
+    Ok(())
+}
+
+fn grep(LibraryGrep { keyword, dir }: LibraryGrep) -> Result<()> {
+    let index = TextIndex::load(&library_dir(dir)?)?;
+
+    for hit in index.grep(&keyword) {
+        println!("{}:{}: {}", hit.path, hit.page, hit.line);
+    }
+
+    Ok(())
+}
+
+pub fn library(Library { command }: Library) -> Result<()> {
+    match command {
+        LibrarySubcommands::Search(args) => search(args),
+        LibrarySubcommands::Stats(args) => stats(args),
+        LibrarySubcommands::Normalize(args) => normalize(args),
+        LibrarySubcommands::Rename(args) => rename(args),
+        LibrarySubcommands::Thumbnails(args) => thumbnails(args),
+        #[cfg(feature = "ocr")]
+        LibrarySubcommands::Ocr(args) => ocr(args),
+        LibrarySubcommands::Grep(args) => grep(args),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static TEMPLATE: &str = "{series}/{series} Vol.{volume:2} Ch.{chapter:3}";
+
+    #[test]
+    fn render_template_from_comic_info() {
+        let entry = Entry::new(
+            "library/chapter.cbz".into(),
+            ComicInfo::from([
+                ("Series".to_string(), "Detective Conan".to_string()),
+                ("Volume".to_string(), "1".to_string()),
+                ("Number".to_string(), "10.5".to_string()),
+            ]),
+        );
+
+        assert_eq!(
+            render_template(TEMPLATE, &entry).unwrap(),
+            Some(Utf8PathBuf::from(
+                "Detective Conan/Detective Conan Vol.01 Ch.010.5.cbz"
+            ))
+        );
+        assert_eq!(render_template("{title}", &entry).unwrap(), None);
+        assert!(render_template("{author}", &entry).is_err());
+    }
+
+    #[test]
+    fn render_template_from_layout_path() {
+        let entry = Entry::new(
+            "library/Detective Conan/Detective Conan v01 c001 [en].cbz".into(),
+            ComicInfo::new(),
+        );
+
+        assert_eq!(
+            render_template(TEMPLATE, &entry).unwrap(),
+            Some(Utf8PathBuf::from(
+                "Detective Conan/Detective Conan Vol.01 Ch.001.cbz"
+            ))
+        );
+    }
+}
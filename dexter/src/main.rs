@@ -1,34 +1,62 @@
 #![deny(clippy::all)]
 #![deny(clippy::pedantic)]
 
-use std::{
-    env::current_dir,
-    fs::{create_dir_all, OpenOptions},
-};
+use std::{env::current_dir, fs::create_dir_all, sync::Arc, time::Duration};
 
-use anyhow::{anyhow, Error, Result};
+use anyhow::{anyhow, Result};
 use async_recursion::async_recursion;
-use camino::Utf8Path;
+use camino::{Utf8Path, Utf8PathBuf};
 use clap::Parser;
 use cli_table::{print_stdout, WithTitle};
 use dexter_core::{
-    api::archive_download, ArchiveDownload as DexterArchiveDownload,
-    GetChapter as DexterGetChapter, GetChapters as DexterGetChapters,
-    GetImageLinks as DexterGetImageLinks, GetManga as DexterGetManga, Request,
-    Search as DexterSearch,
+    api::{archive_download::DEFAULT_STALL_TIMEOUT, get_chapters},
+    link::is_uuid,
+    naming,
+    progress::IndicatifProgress,
+    write_atomically_with_comic_info, ArchiveDownload as DexterArchiveDownload, Client, ComicInfo,
+    Error as DexterError, GetChapter as DexterGetChapter, GetChapters as DexterGetChapters,
+    GetImageLinks as DexterGetImageLinks, GetManga as DexterGetManga, IfExists as DexterIfExists,
+    Request, Search as DexterSearch, SearchGroups as DexterSearchGroups, WritePolicy,
 };
 use dialoguer::theme::ColorfulTheme;
 use dialoguer::{Input, Select};
 use eco_view::{view, ViewOptions};
+use futures::{StreamExt, TryStreamExt};
 use indicatif::{ProgressBar, ProgressStyle};
-use tokio::sync::mpsc;
+use tokio::{signal::ctrl_c, task::JoinHandle};
+use tokio_util::sync::CancellationToken;
+use tracing::warn;
 use types::{Chapter, ImageLink};
 
-use crate::args::{Args, Chapters, Download, ImageLinks, InteractiveSearch, Search, Subcommands};
-use crate::types::Manga;
-
+use crate::args::{
+    Args, ChapterOrder, Chapters, Download, IfExists, ImageLinks, InteractiveSearch, Search,
+    Subcommands,
+};
+use crate::batch::batch_download;
+use crate::feed::feed;
+use crate::language::check_chapter_language;
+use crate::library::library;
+use crate::send::send;
+use crate::types::{DetailedManga, Manga};
+use crate::upload::upload;
+use crate::verify::verify;
+
+mod archive;
 mod args;
+mod batch;
+mod catalog;
+mod feed;
+mod hooks;
+mod language;
+mod layout;
+mod library;
+mod ocr;
+mod send;
+mod thumbnails;
+mod tui;
 mod types;
+mod upload;
+mod verify;
 
 #[async_recursion]
 async fn find_manga() -> Result<Manga> {
@@ -64,12 +92,14 @@ async fn find_manga() -> Result<Manga> {
 async fn find_chapter(manga: &Manga) -> Result<Chapter> {
     let chapter_number: String = Input::new().with_prompt("Chapter number").interact_text()?;
 
-    let chapter_response = DexterGetChapters::new(&manga.id)
+    let mut chapter_response = DexterGetChapters::new(&manga.id)
         .set_limit(10)
         .push_chapter(chapter_number)
         .request()
         .await?;
 
+    chapter_response.data.sort_by(|a, b| b.cmp_by_number(a));
+
     let chapters = chapter_response
         .data
         .into_iter()
@@ -91,56 +121,138 @@ async fn find_chapter(manga: &Manga) -> Result<Chapter> {
     }
 }
 
+/// Returns the id of the scanlation group, looked up by name unless it's already an id,
+/// preferring the group whose name matches exactly over the most relevant one
+async fn resolve_group(group: String) -> Result<String> {
+    if is_uuid(&group) {
+        return Ok(group);
+    }
+
+    let groups = DexterSearchGroups::new(&group)
+        .with_limit(10)
+        .request()
+        .await?
+        .data;
+    groups
+        .iter()
+        .find(|data| data.attributes.name.eq_ignore_ascii_case(&group))
+        .or_else(|| groups.first())
+        .map(|data| data.id.clone())
+        .ok_or_else(|| anyhow!("no scanlation group named {group}"))
+}
+
+/// Listens to Ctrl-C until dropped, dropping it on every exit path of the download
+struct CtrlCListener(JoinHandle<()>);
+
+impl Drop for CtrlCListener {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// Cancels the token on Ctrl-C, as long as the returned listener is alive
+fn cancel_on_ctrl_c(cancellation_token: CancellationToken) -> CtrlCListener {
+    CtrlCListener(tokio::spawn(async move {
+        if ctrl_c().await.is_ok() {
+            cancellation_token.cancel();
+        }
+    }))
+}
+
+fn write_policy(no_clobber: bool) -> WritePolicy {
+    if no_clobber {
+        WritePolicy::NoClobber
+    } else {
+        WritePolicy::Overwrite
+    }
+}
+
+/// Converts the `--if-exists` policy, `--no-clobber` skipping the existing files when it's not provided
+fn if_exists(if_exists: Option<IfExists>, no_clobber: bool) -> DexterIfExists {
+    let default = if no_clobber {
+        IfExists::Skip
+    } else {
+        IfExists::Overwrite
+    };
+    match if_exists.unwrap_or(default) {
+        IfExists::Skip => DexterIfExists::Skip,
+        IfExists::Overwrite => DexterIfExists::Overwrite,
+        IfExists::Rename => DexterIfExists::Rename,
+        IfExists::Verify => DexterIfExists::Verify,
+    }
+}
+
+/// Applies the `--if-exists` policy to the chapter archive, returning the path to write it to, if any
+async fn destination(
+    chapter_id: &str,
+    filepath: Utf8PathBuf,
+    if_exists: DexterIfExists,
+) -> Result<Option<Utf8PathBuf>> {
+    let Some(decision) = if_exists
+        .decide(chapter_id, &filepath, &Client::shared())
+        .await?
+    else {
+        return Ok(Some(filepath));
+    };
+    println!("{filepath} already exists: {decision}");
+
+    Ok(decision.destination(&filepath).map(Utf8Path::to_path_buf))
+}
+
+/// Converts the `--stall-timeout` seconds, `0` disabling the stall detection
+fn stall_timeout(secs: u64) -> Option<Duration> {
+    (secs > 0).then(|| Duration::from_secs(secs))
+}
+
 async fn download(
     chapter_id: &str,
     filepath: &Utf8Path,
     max_download_retries: u32,
+    stall_timeout: Option<Duration>,
+    deadline: Option<Duration>,
+    write_policy: WritePolicy,
     open: bool,
 ) -> Result<()> {
-    let (tx, mut rx) = mpsc::unbounded_channel();
-
-    let progress_handle = tokio::spawn(async move {
-        let mut bar = ProgressBar::new(0);
-
-        while let Some(event) = rx.recv().await {
-            match event {
-                archive_download::Event::Init(len) => {
-                    bar = ProgressBar::new((len * 2) as u64);
-
-                    bar.set_style(
-                        ProgressStyle::default_bar()
-                            .template("[{elapsed_precise}] [{wide_bar}] {percent}%")
-                            .map_err(|err| {
-                                anyhow::anyhow!("couldn't set progress template: {err}")
-                            })?,
-                    );
-                }
-                archive_download::Event::Download | archive_download::Event::Zip => {
-                    bar.inc(1);
-                }
-                archive_download::Event::Done => {
-                    bar.finish();
-                }
-            }
-        }
+    write_policy.ensure_writable(filepath)?;
 
-        Ok::<(), Error>(())
-    });
+    let bar = ProgressBar::new(0).with_style(
+        ProgressStyle::default_bar()
+            .template("[{elapsed_precise}] [{wide_bar}] {percent}% {msg}")
+            .map_err(|err| anyhow!("couldn't set progress template: {err}"))?,
+    );
 
-    let cbz_writer = DexterArchiveDownload::new(chapter_id)
+    let cancellation_token = CancellationToken::new();
+    let ctrl_c_listener = cancel_on_ctrl_c(cancellation_token.clone());
+
+    let cbz_writer = match DexterArchiveDownload::new(chapter_id)
         .set_max_download_retries(max_download_retries)
-        .set_sender(tx)
+        .set_stall_timeout(stall_timeout)
+        .set_deadline(deadline)
+        .set_progress(Arc::new(IndicatifProgress::new(bar.clone())))
+        .set_cancellation_token(cancellation_token)
         .request()
-        .await?;
+        .await
+    {
+        Ok(cbz_writer) => cbz_writer,
+        Err(DexterError::Cancelled) => {
+            bar.abandon();
+            eprintln!("\nDownload interrupted, nothing was written to {filepath}");
+            eprintln!("Run the command again to restart the download of chapter {chapter_id}");
+            return Err(anyhow!("download cancelled"));
+        }
+        Err(err) => return Err(err.into()),
+    };
 
-    let file = OpenOptions::new()
-        .read(true)
-        .write(true)
-        .truncate(true)
-        .create(true)
-        .open(filepath)?;
+    drop(ctrl_c_listener);
 
-    cbz_writer.write_to(&file)?;
+    let comic_info = match ComicInfo::fetch(chapter_id, &Client::shared()).await {
+        Ok(comic_info) => Some(comic_info),
+        Err(err) => {
+            warn!("metadata of chapter {chapter_id} not written to {filepath}: {err}");
+            None
+        }
+    };
+    write_atomically_with_comic_info(cbz_writer, filepath, write_policy, comic_info.as_ref())?;
 
     if open {
         view(ViewOptions {
@@ -149,18 +261,28 @@ async fn download(
         })?;
     }
 
-    progress_handle.await??;
-
     Ok(())
 }
 
 #[tokio::main]
 #[allow(clippy::too_many_lines)]
 async fn main() -> Result<()> {
-    tracing_subscriber::fmt::init();
-
     let args = Args::parse();
 
+    let logs = tui::Logs::default();
+    if matches!(
+        args.command,
+        Subcommands::InteractiveSearch(InteractiveSearch { tui: true, .. })
+    ) {
+        let logs = logs.clone();
+        tracing_subscriber::fmt()
+            .with_ansi(false)
+            .with_writer(move || logs.clone())
+            .init();
+    } else {
+        tracing_subscriber::fmt::init();
+    }
+
     match args.command {
         Subcommands::InteractiveSearch(InteractiveSearch {
             manga_id,
@@ -170,7 +292,31 @@ async fn main() -> Result<()> {
             outdir,
             language,
             max_download_retries,
+            overwrite: _,
+            no_clobber,
+            tui,
         }) => {
+            if tui {
+                let outdir = match outdir {
+                    Some(outdir) => outdir,
+                    None => current_dir()?.try_into()?,
+                };
+                if !outdir.exists() {
+                    create_dir_all(&outdir)?;
+                }
+
+                return tui::run(
+                    tui::Options {
+                        outdir,
+                        language,
+                        max_download_retries,
+                        write_policy: write_policy(no_clobber),
+                    },
+                    logs,
+                )
+                .await;
+            }
+
             let manga = match manga_id {
                 Some(manga_id) => DexterGetManga::new(manga_id).request().await?.data.into(),
                 None => find_manga().await?,
@@ -187,13 +333,20 @@ async fn main() -> Result<()> {
                     let Some(chapter) = chapter_response.data.pop() else {
                         panic!("chapter number {chapter_number} not found for manga {manga} and language {language}");
                     };
+                    check_chapter_language(
+                        &chapter.id,
+                        chapter.attributes.title.as_deref(),
+                        chapter.attributes.translated_language.as_deref(),
+                        &language,
+                    );
 
                     chapter.into()
                 }
                 None => find_chapter(&manga).await?,
             };
 
-            let default_filename = sanitize_filename::sanitize(format!("{manga} - {chapter}.cbz"));
+            let default_filename =
+                naming::file_name_with_extension(&format!("{manga} - {chapter}"), "cbz");
             let filename = if accepts_default_filename {
                 default_filename
             } else {
@@ -216,37 +369,85 @@ async fn main() -> Result<()> {
 
             let filepath = outdir.join(filename);
 
-            download(&chapter.id, &filepath, max_download_retries, false).await?;
+            download(
+                &chapter.id,
+                &filepath,
+                max_download_retries,
+                Some(DEFAULT_STALL_TIMEOUT),
+                None,
+                write_policy(no_clobber),
+                false,
+            )
+            .await?;
 
             println!("CBZ file created");
         }
 
-        Subcommands::Search(Search { limit, title }) => {
+        Subcommands::Search(Search {
+            limit,
+            title,
+            detailed,
+            lang_priority,
+        }) => {
             let search_response = DexterSearch::new(title).with_limit(limit).request().await?;
 
-            let mangas = search_response
-                .data
-                .into_iter()
-                .map(Into::into)
-                .collect::<Vec<Manga>>();
+            if detailed {
+                let mangas = search_response
+                    .data
+                    .into_iter()
+                    .map(|data| DetailedManga::localized(data, &lang_priority))
+                    .collect::<Vec<_>>();
+
+                print_stdout(mangas.with_title())?;
+            } else {
+                let mangas = search_response
+                    .data
+                    .into_iter()
+                    .map(|data| Manga::localized(data, &lang_priority))
+                    .collect::<Vec<_>>();
 
-            print_stdout(mangas.with_title())?;
+                print_stdout(mangas.with_title())?;
+            }
         }
         Subcommands::Chapters(Chapters {
             limit,
             manga_id,
             chapters,
             volumes,
+            groups,
+            order,
         }) => {
-            let chapter_response = DexterGetChapters::new(manga_id)
-                .set_limit(limit)
+            let mut group_ids = Vec::with_capacity(groups.len());
+            for group in groups {
+                group_ids.push(resolve_group(group).await?);
+            }
+
+            // The api returns at most 100 chapters at once, the following pages are requested as needed
+            let mut chapter_data = DexterGetChapters::new(manga_id)
+                .set_limit(limit.min(get_chapters::DEFAULT_CHAPTERS_LIMIT))
                 .with_volumes(volumes)
                 .with_chapters(chapters)
-                .request()
+                .with_groups(group_ids)
+                .set_order(match order {
+                    ChapterOrder::Chapter => get_chapters::Order::Chapter,
+                    ChapterOrder::Published => get_chapters::Order::PublishAt,
+                    ChapterOrder::Created => get_chapters::Order::CreatedAt,
+                })
+                .request_all()
+                .take(usize::try_from(limit).unwrap_or(usize::MAX))
+                .try_collect::<Vec<_>>()
                 .await?;
 
-            let chapters = chapter_response
-                .data
+            // The dates share the same format and offset, they can be compared as strings
+            match order {
+                ChapterOrder::Chapter => chapter_data.sort_by(|a, b| b.cmp_by_number(a)),
+                ChapterOrder::Published => chapter_data
+                    .sort_by(|a, b| b.attributes.publish_at.cmp(&a.attributes.publish_at)),
+                ChapterOrder::Created => chapter_data
+                    .sort_by(|a, b| b.attributes.created_at.cmp(&a.attributes.created_at)),
+            }
+
+            let chapters = chapter_data
                 .into_iter()
                 .map(Into::into)
                 .collect::<Vec<Chapter>>();
@@ -269,6 +470,11 @@ async fn main() -> Result<()> {
             open,
             outdir,
             max_download_retries,
+            stall_timeout: stall_timeout_secs,
+            chapter_deadline,
+            overwrite: _,
+            no_clobber,
+            if_exists: if_exists_arg,
         }) => {
             let outdir = if let Some(outdir) = outdir {
                 outdir
@@ -281,12 +487,36 @@ async fn main() -> Result<()> {
                 create_dir_all(&outdir)?;
             }
 
-            let filepath = outdir.join(filename);
+            let mut filepath = outdir.join(filename);
+            let mut policy = write_policy(no_clobber);
+            if let Some(if_exists_arg) = if_exists_arg {
+                let existing = if_exists(Some(if_exists_arg), no_clobber);
+                let Some(destination) = destination(&chapter_id, filepath, existing).await? else {
+                    return Ok(());
+                };
+                filepath = destination;
+                policy = existing.write_policy();
+            }
 
-            download(&chapter_id, &filepath, max_download_retries, open).await?;
+            download(
+                &chapter_id,
+                &filepath,
+                max_download_retries,
+                stall_timeout(stall_timeout_secs),
+                chapter_deadline.map(Duration::from_secs),
+                policy,
+                open,
+            )
+            .await?;
 
             println!("CBZ file created");
         }
+        Subcommands::BatchDownload(args) => batch_download(args).await?,
+        Subcommands::Verify(args) => verify(args).await?,
+        Subcommands::Library(args) => library(args)?,
+        Subcommands::Send(args) => send(args)?,
+        Subcommands::Upload(args) => upload(args).await?,
+        Subcommands::Feed(args) => feed(args).await?,
     }
 
     Ok(())
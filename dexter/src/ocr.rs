@@ -0,0 +1,106 @@
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use camino::{Utf8Path, Utf8PathBuf};
+use serde::{Deserialize, Serialize};
+
+/// Name of the text index file, at the root of the library
+static INDEX_FILE_NAME: &str = ".dexter-text.json";
+
+/// Text of an archive, one string per page, in page order
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct IndexedArchive {
+    /// Modification time of the archive when it was indexed, in seconds since the epoch
+    pub modified: u64,
+    pub pages: Vec<String>,
+}
+
+/// A line of a page matching the searched keyword
+#[derive(Debug)]
+pub struct Hit<'a> {
+    pub path: &'a str,
+    /// Page number, starting at 1
+    pub page: usize,
+    pub line: &'a str,
+}
+
+/// Text extracted from the library archives, indexed by path relative to the library
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct TextIndex {
+    archives: BTreeMap<String, IndexedArchive>,
+}
+
+impl TextIndex {
+    fn path(dir: &Utf8Path) -> Utf8PathBuf {
+        dir.join(INDEX_FILE_NAME)
+    }
+
+    /// Reads the index of the library, empty if the library was never indexed
+    pub fn load(dir: &Utf8Path) -> Result<Self> {
+        let path = Self::path(dir);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        Ok(serde_json::from_slice(&std::fs::read(path)?)?)
+    }
+
+    #[cfg(feature = "ocr")]
+    pub fn save(&self, dir: &Utf8Path) -> Result<()> {
+        dexter_core::output::write_bytes_atomically(
+            serde_json::to_vec(self)?,
+            &Self::path(dir),
+            dexter_core::WritePolicy::Overwrite,
+        )?;
+
+        Ok(())
+    }
+
+    pub fn get(&self, path: &str) -> Option<&IndexedArchive> {
+        self.archives.get(path)
+    }
+
+    #[cfg(feature = "ocr")]
+    pub fn insert(&mut self, path: String, archive: IndexedArchive) {
+        self.archives.insert(path, archive);
+    }
+
+    /// Lines containing the keyword, case insensitively, in path and page order
+    pub fn grep<'a>(&'a self, keyword: &str) -> impl Iterator<Item = Hit<'a>> {
+        let keyword = keyword.to_lowercase();
+
+        self.archives.iter().flat_map(move |(path, archive)| {
+            let keyword = keyword.clone();
+            archive
+                .pages
+                .iter()
+                .enumerate()
+                .flat_map(move |(index, text)| {
+                    let keyword = keyword.clone();
+                    text.lines()
+                        .filter(move |line| line.to_lowercase().contains(&keyword))
+                        .map(move |line| Hit {
+                            path,
+                            page: index + 1,
+                            line: line.trim(),
+                        })
+                })
+        })
+    }
+}
+
+/// Runs the OCR over the archive images, in natural name order, `language` being a Tesseract language code, e.g. `eng` or `jpn`
+#[cfg(feature = "ocr")]
+pub fn recognize(archive: &Utf8Path, language: &str) -> Result<Vec<String>> {
+    let mut tesseract = leptess::LepTess::new(None, language)?;
+    let mut pages = crate::archive::read_pages(archive)?;
+    pages.sort_by(|a, b| crate::archive::natural_cmp(&a.name, &b.name));
+
+    let mut texts = Vec::with_capacity(pages.len());
+    for page in pages {
+        tesseract.set_image_from_mem(&page.bytes)?;
+        texts.push(tesseract.get_utf8_text()?);
+    }
+
+    Ok(texts)
+}
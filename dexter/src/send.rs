@@ -0,0 +1,214 @@
+use std::{
+    fs::File,
+    io::{Cursor, Read, Write},
+};
+
+use anyhow::{anyhow, Result};
+use camino::Utf8Path;
+use dexter_core::{
+    naming::series_name,
+    output::{is_page, write_bytes_atomically},
+    transform::{self, PageTransform, Transforms},
+    ChapterNumber, WritePolicy,
+};
+use tracing::info;
+use zip::{write::FileOptions, ZipArchive, ZipWriter};
+
+use crate::{
+    args::Send,
+    batch::page_transforms,
+    library::{archives, library_dir, Entry},
+};
+
+/// Whether the entry belongs to the series, and to the volume if any, compared as numbers so that `3` is `03`
+fn is_selected(entry: &Entry, series: &str, volume: Option<&ChapterNumber>) -> bool {
+    entry.series().eq_ignore_ascii_case(series)
+        && volume.map_or(true, |volume| {
+            ChapterNumber::from(entry.comic_info.get("Volume").map(String::as_str)) == *volume
+        })
+}
+
+/// Whether the archive is already on the device, with the same size if copied as is,
+/// or written after the library one if its pages are transformed on the way
+fn is_sent(archive: &Utf8Path, target: &Utf8Path, transformed: bool) -> Result<bool> {
+    if !target.exists() {
+        return Ok(false);
+    }
+    let (archive, target) = (std::fs::metadata(archive)?, std::fs::metadata(target)?);
+
+    Ok(if transformed {
+        target.modified()? >= archive.modified()?
+    } else {
+        target.len() == archive.len()
+    })
+}
+
+/// Rewrites the archive with its pages transformed, the other files such as `ComicInfo.xml` are kept as is
+fn transform_archive(archive: &Utf8Path, transforms: &Transforms) -> Result<Vec<u8>> {
+    let mut archive = ZipArchive::new(File::open(archive)?)?;
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index)?;
+        if file.is_dir() || !is_page(file.name()) {
+            writer.raw_copy_file(file)?;
+            continue;
+        }
+        let mut bytes = Vec::with_capacity(usize::try_from(file.size()).unwrap_or_default());
+        file.read_to_end(&mut bytes)?;
+        let page = transforms.apply(transform::Page {
+            filename: file.name().to_string(),
+            bytes: bytes.into(),
+        })?;
+        writer.start_file(page.filename, FileOptions::default())?;
+        writer.write_all(&page.bytes)?;
+    }
+
+    Ok(writer.finish()?.into_inner())
+}
+
+/// Copies the archives of a series to a mounted device, skipping the ones already on it.
+///
+/// The archives are sent as cbz files, which `KOReader` and most readers open directly,
+/// with their pages converted for e-ink screens if requested.
+/// They are written to a temporary file first, so that unplugging the device never leaves a truncated archive.
+pub fn send(
+    Send {
+        device,
+        series,
+        volume,
+        library,
+        grayscale,
+        max_page_size,
+    }: Send,
+) -> Result<()> {
+    if !device.is_dir() {
+        return Err(anyhow!("{device} is not a mounted device directory"));
+    }
+
+    let volume = volume.as_deref().map(ChapterNumber::parse);
+    let entries = archives(&library_dir(library)?)?
+        .into_iter()
+        .map(Entry::read)
+        .filter(|entry| is_selected(entry, &series, volume.as_ref()))
+        .collect::<Vec<_>>();
+
+    if entries.is_empty() {
+        return Err(anyhow!("no archive found for {series}"));
+    }
+
+    let dir = device.join(series_name(&series));
+    std::fs::create_dir_all(&dir)?;
+    let transforms = page_transforms(grayscale, max_page_size);
+
+    let mut copied = 0;
+    for entry in entries {
+        let Some(file_name) = entry.path.file_name() else {
+            continue;
+        };
+        let target = dir.join(file_name);
+
+        if is_sent(&entry.path, &target, !transforms.is_empty())? {
+            info!("{target} already on the device, skipping");
+            continue;
+        }
+
+        println!("{} -> {target}", entry.path);
+        let bytes = if transforms.is_empty() {
+            std::fs::read(&entry.path)?
+        } else {
+            transform_archive(&entry.path, &transforms)?
+        };
+        write_bytes_atomically(bytes, &target, WritePolicy::Overwrite)?;
+        copied += 1;
+    }
+
+    println!("{copied} archive(s) sent to {device}");
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use camino::Utf8PathBuf;
+
+    use crate::library::ComicInfo;
+
+    use super::*;
+
+    #[test]
+    fn select_entries() {
+        let with_metadata = Entry::new(
+            "library/old/chapter.cbz".into(),
+            ComicInfo::from([
+                ("Series".to_string(), "Detective Conan".to_string()),
+                ("Volume".to_string(), "3".to_string()),
+            ]),
+        );
+        // No `ComicInfo.xml`, as written by older versions of dexter
+        let without_metadata = Entry::new(
+            "library/Detective Conan/Detective Conan v03 c021 [en].cbz".into(),
+            ComicInfo::new(),
+        );
+        let other_volume = Entry::new(
+            "library/Detective Conan/Detective Conan v04 c031.cbz".into(),
+            ComicInfo::new(),
+        );
+
+        for entry in [&with_metadata, &without_metadata] {
+            assert!(is_selected(entry, "detective conan", None));
+            assert!(is_selected(
+                entry,
+                "Detective Conan",
+                Some(&ChapterNumber::parse("03"))
+            ));
+        }
+        assert!(is_selected(&other_volume, "Detective Conan", None));
+        assert!(!is_selected(
+            &other_volume,
+            "Detective Conan",
+            Some(&ChapterNumber::parse("3"))
+        ));
+        assert!(!is_selected(&with_metadata, "Case Closed", None));
+    }
+
+    #[test]
+    fn transform_pages() {
+        let path = Utf8PathBuf::try_from(std::env::temp_dir())
+            .unwrap()
+            .join(format!("dexter-send-{}.cbz", std::process::id()));
+        let mut page = Cursor::new(Vec::new());
+        image::RgbImage::from_pixel(8, 4, image::Rgb([255, 0, 0]))
+            .write_to(&mut page, image::ImageFormat::Png)
+            .unwrap();
+        let mut writer = ZipWriter::new(File::create(&path).unwrap());
+        writer.start_file("1.png", FileOptions::default()).unwrap();
+        writer.write_all(page.get_ref()).unwrap();
+        writer
+            .start_file("ComicInfo.xml", FileOptions::default())
+            .unwrap();
+        writer.write_all(b"<ComicInfo/>").unwrap();
+        writer.finish().unwrap();
+
+        let bytes = transform_archive(&path, &page_transforms(true, Some((4, 4)))).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let mut archive = ZipArchive::new(Cursor::new(bytes)).unwrap();
+        let mut page = Vec::new();
+        archive
+            .by_name("1.png")
+            .unwrap()
+            .read_to_end(&mut page)
+            .unwrap();
+        let page = image::load_from_memory(&page).unwrap();
+        assert_eq!((page.width(), page.height()), (4, 2));
+        assert_eq!(page.color(), image::ColorType::L8);
+        let mut comic_info = String::new();
+        archive
+            .by_name("ComicInfo.xml")
+            .unwrap()
+            .read_to_string(&mut comic_info)
+            .unwrap();
+        assert_eq!(comic_info, "<ComicInfo/>");
+    }
+}
@@ -0,0 +1,190 @@
+use std::{
+    fmt::Write,
+    fs::{create_dir_all, metadata, read, remove_file, rename, File},
+    time::UNIX_EPOCH,
+};
+
+use anyhow::{anyhow, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+use dexter_core::{
+    output::{part_path, write_bytes_atomically},
+    WritePolicy,
+};
+use image::ImageFormat;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::warn;
+
+use crate::archive::read_page;
+
+/// Checksum of an archive, valid as long as the archive keeps its size and modification time
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedChecksum {
+    size: u64,
+    /// Modification time of the archive, in seconds since the epoch
+    modified: u64,
+    checksum: String,
+}
+
+/// Thumbnails of archive pages, cached on disk.
+///
+/// Thumbnails are keyed by the checksum of the archive, so that they are generated again
+/// when the archive is replaced, and shared by the copies of an archive.
+/// The checksums are cached too, by archive path, so that the archives are only read when they change.
+#[derive(Debug)]
+pub struct Thumbnails {
+    dir: Utf8PathBuf,
+}
+
+impl Thumbnails {
+    pub fn new(dir: impl Into<Utf8PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// `dexter/thumbnails` in the user cache directory, e.g. `$XDG_CACHE_HOME` on Linux
+    pub fn default_dir() -> Result<Utf8PathBuf> {
+        let cache_dir = dirs::cache_dir().ok_or_else(|| anyhow!("cache directory not found"))?;
+        Ok(Utf8PathBuf::try_from(cache_dir)?
+            .join("dexter")
+            .join("thumbnails"))
+    }
+
+    /// Returns the checksum of the archive, computed again only if its size or modification time changed
+    fn checksum(&self, archive: &Utf8Path) -> Result<String> {
+        let metadata = metadata(archive)?;
+        let size = metadata.len();
+        let modified = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let path = archive
+            .canonicalize_utf8()
+            .unwrap_or_else(|_| archive.to_path_buf());
+        let cache_path = self
+            .dir
+            .join("checksums")
+            .join(format!("{}.json", hex(&Sha256::digest(path.as_str()))));
+
+        if let Some(cached) = read(&cache_path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<CachedChecksum>(&bytes).ok())
+            .filter(|cached| cached.size == size && cached.modified == modified)
+        {
+            return Ok(cached.checksum);
+        }
+
+        let mut hasher = Sha256::new();
+        std::io::copy(&mut File::open(archive)?, &mut hasher)?;
+        let checksum = hex(&hasher.finalize());
+
+        let cached = CachedChecksum {
+            size,
+            modified,
+            checksum,
+        };
+        if let Err(err) = save_checksum(&cache_path, &cached) {
+            warn!("failed to cache the checksum of {archive}: {err}");
+        }
+
+        Ok(cached.checksum)
+    }
+
+    fn key(&self, archive: &Utf8Path, page: usize, size: u32) -> Result<String> {
+        Ok(format!("{}-{page}-{size}.jpg", self.checksum(archive)?))
+    }
+
+    /// Returns the path of the thumbnail of the `page`th page (starting at 1) of the archive,
+    /// fitting in a `size` pixels square, and generates it if it's not cached yet
+    pub fn get(&self, archive: &Utf8Path, page: usize, size: u32) -> Result<Utf8PathBuf> {
+        let path = self.dir.join(self.key(archive, page, size)?);
+        if path.exists() {
+            return Ok(path);
+        }
+
+        let bytes = read_page(archive, page.saturating_sub(1))?
+            .ok_or_else(|| anyhow!("{archive} has no page {page}"))?;
+        let thumbnail = image::load_from_memory(&bytes)?.thumbnail(size, size);
+
+        create_dir_all(&self.dir)?;
+        let part_path = part_path(&path);
+        if let Err(err) = thumbnail
+            .to_rgb8()
+            .save_with_format(&part_path, ImageFormat::Jpeg)
+        {
+            let _ = remove_file(&part_path);
+            return Err(err.into());
+        }
+        rename(&part_path, &path)?;
+
+        Ok(path)
+    }
+}
+
+/// Writes the cached checksum to `path`, creating its directory if needed
+fn save_checksum(path: &Utf8Path, cached: &CachedChecksum) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        create_dir_all(parent)?;
+    }
+    write_bytes_atomically(serde_json::to_vec(cached)?, path, WritePolicy::Overwrite)?;
+
+    Ok(())
+}
+
+/// Lowercase hexadecimal representation of the bytes
+fn hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .fold(String::with_capacity(bytes.len() * 2), |mut hex, byte| {
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use zip::{write::FileOptions, ZipWriter};
+
+    use super::*;
+
+    #[test]
+    fn cached_checksum() {
+        let dir = Utf8PathBuf::try_from(std::env::temp_dir())
+            .unwrap()
+            .join(format!("dexter-thumbnails-{}", std::process::id()));
+        create_dir_all(&dir).unwrap();
+        let archive = dir.join("chapter.cbz");
+        let mut page = Cursor::new(Vec::new());
+        image::RgbImage::from_pixel(8, 8, image::Rgb([255, 0, 0]))
+            .write_to(&mut page, ImageFormat::Png)
+            .unwrap();
+        let mut writer = ZipWriter::new(File::create(&archive).unwrap());
+        writer.start_file("1.png", FileOptions::default()).unwrap();
+        writer.write_all(page.get_ref()).unwrap();
+        writer.finish().unwrap();
+
+        let thumbnails = Thumbnails::new(dir.join("cache"));
+        let thumbnail = thumbnails.get(&archive, 1, 4).unwrap();
+
+        // Same size and modification time, the archive isn't read again
+        let file = File::options().write(true).open(&archive).unwrap();
+        let modified = file.metadata().unwrap().modified().unwrap();
+        let len = file.metadata().unwrap().len();
+        (&file)
+            .write_all(&vec![0; usize::try_from(len).unwrap()])
+            .unwrap();
+        file.set_modified(modified).unwrap();
+        drop(file);
+        let cached = thumbnails.get(&archive, 1, 4);
+
+        // Changed since, the archive is read again
+        std::fs::write(&archive, "not an archive").unwrap();
+        let changed = thumbnails.get(&archive, 1, 4);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(cached.unwrap(), thumbnail);
+        assert!(changed.is_err());
+    }
+}
@@ -0,0 +1,546 @@
+use std::{
+    collections::VecDeque,
+    io::{self, Stdout},
+    sync::{Arc, Mutex, PoisonError},
+    thread,
+    time::Duration,
+};
+
+use anyhow::Result;
+use camino::Utf8PathBuf;
+use dexter_core::{
+    api::archive_download, naming, write_atomically, ArchiveDownload, GetChapters, Request, Search,
+    WritePolicy,
+};
+use futures::TryStreamExt;
+use ratatui::{
+    backend::CrosstermBackend,
+    crossterm::{
+        event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+        execute,
+        terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    },
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::Line,
+    widgets::{Block, List, ListItem, ListState, Paragraph},
+    Frame, Terminal,
+};
+use tokio::sync::mpsc;
+use tracing::error;
+
+use crate::types::{Chapter, Manga};
+
+/// Max amount of lines kept in the logs pane
+const MAX_LOG_LINES: usize = 500;
+
+/// How often the screen is redrawn when nothing happens, so that the logs stay up to date
+const TICK_RATE: Duration = Duration::from_millis(250);
+
+/// Progress events waiting to be displayed per download, the following ones are merged until the ui catches up
+const EVENTS_CAPACITY: usize = 64;
+
+/// Log lines written by the tracing subscriber while the tui is running, as writing to stderr would break the display
+#[derive(Debug, Clone, Default)]
+pub struct Logs(Arc<Mutex<VecDeque<String>>>);
+
+impl Logs {
+    fn last_lines(&self, len: usize) -> Vec<String> {
+        let lines = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        lines
+            .iter()
+            .skip(lines.len().saturating_sub(len))
+            .cloned()
+            .collect()
+    }
+}
+
+impl io::Write for Logs {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut lines = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        for line in String::from_utf8_lossy(buf).lines() {
+            lines.push_back(line.to_string());
+        }
+        while lines.len() > MAX_LOG_LINES {
+            lines.pop_front();
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct Options {
+    pub outdir: Utf8PathBuf,
+    pub language: String,
+    pub max_download_retries: u32,
+    pub write_policy: WritePolicy,
+}
+
+#[derive(Debug)]
+enum Message {
+    Key(KeyEvent),
+    SearchResults(Result<Vec<Manga>, String>),
+    Chapters(Result<Vec<Chapter>, String>),
+    DownloadProgress(usize, archive_download::Event),
+    DownloadFinished(usize, Result<Utf8PathBuf, String>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Pane {
+    Search,
+    Chapters,
+    Queue,
+}
+
+impl Pane {
+    fn next(self) -> Self {
+        match self {
+            Self::Search => Self::Chapters,
+            Self::Chapters => Self::Queue,
+            Self::Queue => Self::Search,
+        }
+    }
+
+    fn previous(self) -> Self {
+        match self {
+            Self::Search => Self::Queue,
+            Self::Chapters => Self::Search,
+            Self::Queue => Self::Chapters,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum DownloadStatus {
+    Running,
+    Done(Utf8PathBuf),
+    Failed(String),
+}
+
+#[derive(Debug)]
+struct Download {
+    name: String,
+    pages: usize,
+    progress: usize,
+    status: DownloadStatus,
+}
+
+impl Download {
+    fn line(&self) -> String {
+        match &self.status {
+            DownloadStatus::Running if self.pages == 0 => format!("[   ...] {}", self.name),
+            DownloadStatus::Running => {
+                // Each page is downloaded and then packed
+                let percent = self.progress * 100 / (self.pages * 2);
+                format!("[{percent:>5}%] {}", self.name)
+            }
+            DownloadStatus::Done(path) => format!("[  done] {path}"),
+            DownloadStatus::Failed(err) => format!("[failed] {}: {err}", self.name),
+        }
+    }
+}
+
+/// Moves the selection of a list of `len` items up or down, wrapping around
+fn move_selection(state: &mut ListState, len: usize, down: bool) {
+    if len == 0 {
+        state.select(None);
+        return;
+    }
+    let selected = match state.selected() {
+        Some(selected) if down => (selected + 1) % len,
+        Some(selected) => (selected + len - 1) % len,
+        None => 0,
+    };
+    state.select(Some(selected));
+}
+
+struct App {
+    options: Arc<Options>,
+    logs: Logs,
+    sender: mpsc::UnboundedSender<Message>,
+    pane: Pane,
+    query: String,
+    searched_query: Option<String>,
+    mangas: Vec<Manga>,
+    manga_state: ListState,
+    manga: Option<Manga>,
+    chapters: Vec<Chapter>,
+    chapter_state: ListState,
+    downloads: Vec<Download>,
+    download_state: ListState,
+    status: String,
+    quit: bool,
+}
+
+impl App {
+    fn new(options: Options, logs: Logs, sender: mpsc::UnboundedSender<Message>) -> Self {
+        Self {
+            options: Arc::new(options),
+            logs,
+            sender,
+            pane: Pane::Search,
+            query: String::new(),
+            searched_query: None,
+            mangas: Vec::new(),
+            manga_state: ListState::default(),
+            manga: None,
+            chapters: Vec::new(),
+            chapter_state: ListState::default(),
+            downloads: Vec::new(),
+            download_state: ListState::default(),
+            status: String::from("Type a title and press enter to search"),
+            quit: false,
+        }
+    }
+
+    async fn run(
+        mut self,
+        terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+        mut receiver: mpsc::UnboundedReceiver<Message>,
+    ) -> Result<()> {
+        let mut tick = tokio::time::interval(TICK_RATE);
+
+        while !self.quit {
+            terminal.draw(|frame| self.draw(frame))?;
+
+            tokio::select! {
+                Some(message) = receiver.recv() => self.handle(message),
+                _ = tick.tick() => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    fn handle(&mut self, message: Message) {
+        match message {
+            Message::Key(key) => self.handle_key(key),
+            Message::SearchResults(Ok(mangas)) => {
+                self.status = format!("{} manga(s) found", mangas.len());
+                self.mangas = mangas;
+                self.manga_state
+                    .select((!self.mangas.is_empty()).then_some(0));
+            }
+            Message::Chapters(Ok(chapters)) => {
+                self.status = format!("{} chapter(s) found", chapters.len());
+                self.chapters = chapters;
+                self.chapter_state
+                    .select((!self.chapters.is_empty()).then_some(0));
+                self.pane = Pane::Chapters;
+            }
+            Message::SearchResults(Err(err)) | Message::Chapters(Err(err)) => {
+                self.status = format!("Error: {err}");
+            }
+            Message::DownloadProgress(index, event) => {
+                let download = &mut self.downloads[index];
+                match event {
+                    archive_download::Event::Init(pages) => download.pages = pages,
+                    archive_download::Event::Download | archive_download::Event::Zip => {
+                        download.progress += 1;
+                    }
+                    archive_download::Event::Coalesced {
+                        downloads, zips, ..
+                    } => download.progress += downloads + zips,
+                    archive_download::Event::Progress(_)
+                    | archive_download::Event::DataSaver(_)
+                    | archive_download::Event::Done => {}
+                }
+            }
+            Message::DownloadFinished(index, result) => {
+                let download = &mut self.downloads[index];
+                download.status = match result {
+                    Ok(path) => DownloadStatus::Done(path),
+                    Err(err) => DownloadStatus::Failed(err),
+                };
+            }
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.quit = true;
+            }
+            KeyCode::Esc => self.quit = true,
+            KeyCode::Tab => self.pane = self.pane.next(),
+            KeyCode::BackTab => self.pane = self.pane.previous(),
+            KeyCode::Char('q') if self.pane != Pane::Search => self.quit = true,
+            code => match self.pane {
+                Pane::Search => self.handle_search_key(code),
+                Pane::Chapters => self.handle_chapters_key(code),
+                Pane::Queue => match code {
+                    KeyCode::Up => {
+                        move_selection(&mut self.download_state, self.downloads.len(), false);
+                    }
+                    KeyCode::Down => {
+                        move_selection(&mut self.download_state, self.downloads.len(), true);
+                    }
+                    _ => {}
+                },
+            },
+        }
+    }
+
+    fn handle_search_key(&mut self, code: KeyCode) {
+        match code {
+            KeyCode::Char(c) => self.query.push(c),
+            KeyCode::Backspace => {
+                self.query.pop();
+            }
+            KeyCode::Up => move_selection(&mut self.manga_state, self.mangas.len(), false),
+            KeyCode::Down => move_selection(&mut self.manga_state, self.mangas.len(), true),
+            // Enter searches when the query changed, and opens the selected manga otherwise
+            KeyCode::Enter if self.searched_query.as_ref() != Some(&self.query) => self.search(),
+            KeyCode::Enter => {
+                if let Some(manga) = self
+                    .manga_state
+                    .selected()
+                    .and_then(|selected| self.mangas.get(selected))
+                {
+                    self.load_chapters(manga.clone());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_chapters_key(&mut self, code: KeyCode) {
+        match code {
+            KeyCode::Up => move_selection(&mut self.chapter_state, self.chapters.len(), false),
+            KeyCode::Down => move_selection(&mut self.chapter_state, self.chapters.len(), true),
+            KeyCode::Enter => {
+                if let Some(chapter) = self
+                    .chapter_state
+                    .selected()
+                    .and_then(|selected| self.chapters.get(selected))
+                {
+                    self.enqueue(chapter.clone());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn search(&mut self) {
+        let query = self.query.clone();
+        let sender = self.sender.clone();
+        self.searched_query = Some(query.clone());
+        self.status = format!("Searching for {query}...");
+
+        tokio::spawn(async move {
+            let mangas = Search::new(query)
+                .with_limit(20)
+                .request()
+                .await
+                .map(|response| response.data.into_iter().map(Into::into).collect())
+                .map_err(|err| err.to_string());
+            let _ = sender.send(Message::SearchResults(mangas));
+        });
+    }
+
+    fn load_chapters(&mut self, manga: Manga) {
+        let sender = self.sender.clone();
+        let language = self.options.language.clone();
+        self.status = format!("Loading the chapters of {manga}...");
+        self.manga = Some(manga.clone());
+
+        tokio::spawn(async move {
+            let chapters = GetChapters::new(&manga.id)
+                .push_language(language)
+                .request_all()
+                .try_collect::<Vec<_>>()
+                .await
+                .map(|mut data| {
+                    data.sort_by(|a, b| b.cmp_by_number(a));
+                    data.into_iter().map(Into::into).collect()
+                })
+                .map_err(|err| err.to_string());
+            let _ = sender.send(Message::Chapters(chapters));
+        });
+    }
+
+    fn enqueue(&mut self, chapter: Chapter) {
+        let name = match &self.manga {
+            Some(manga) => format!("{manga} - {chapter}"),
+            None => chapter.to_string(),
+        };
+        let index = self.downloads.len();
+        self.downloads.push(Download {
+            name: name.clone(),
+            pages: 0,
+            progress: 0,
+            status: DownloadStatus::Running,
+        });
+        self.status = format!("{name} added to the download queue");
+
+        let sender = self.sender.clone();
+        let options = Arc::clone(&self.options);
+
+        tokio::spawn(async move {
+            let (tx, mut rx) = mpsc::channel(EVENTS_CAPACITY);
+            let forward_handle = {
+                let sender = sender.clone();
+                tokio::spawn(async move {
+                    while let Some(event) = rx.recv().await {
+                        let _ = sender.send(Message::DownloadProgress(index, event));
+                    }
+                })
+            };
+
+            let path = options
+                .outdir
+                .join(naming::file_name_with_extension(&name, "cbz"));
+            let result = async {
+                options.write_policy.ensure_writable(&path)?;
+                let cbz_writer = ArchiveDownload::new(&chapter.id)
+                    .set_max_download_retries(options.max_download_retries)
+                    .set_bounded_sender(tx)
+                    .request()
+                    .await?;
+                write_atomically(cbz_writer, &path, options.write_policy)?;
+                Ok::<_, dexter_core::Error>(path)
+            }
+            .await
+            .map_err(|err| {
+                error!("failed to download {name}: {err}");
+                err.to_string()
+            });
+
+            let _ = forward_handle.await;
+            let _ = sender.send(Message::DownloadFinished(index, result));
+        });
+    }
+
+    fn block(&self, title: &str, pane: Pane) -> Block<'static> {
+        let block = Block::bordered().title(format!(" {title} "));
+        if self.pane == pane {
+            block.border_style(Style::default().fg(Color::Yellow))
+        } else {
+            block
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, logs, status] = Layout::vertical([
+            Constraint::Min(0),
+            Constraint::Length(10),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [search, chapters, queue] = Layout::horizontal([
+            Constraint::Percentage(30),
+            Constraint::Percentage(40),
+            Constraint::Percentage(30),
+        ])
+        .areas(main);
+
+        self.draw_search(frame, search);
+
+        let chapters_title = match &self.manga {
+            Some(manga) => format!("Chapters of {manga}"),
+            None => String::from("Chapters"),
+        };
+        let list = list(&self.chapters, self.block(&chapters_title, Pane::Chapters));
+        frame.render_stateful_widget(list, chapters, &mut self.chapter_state);
+
+        let list = List::new(self.downloads.iter().map(Download::line))
+            .block(self.block("Download queue", Pane::Queue))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, queue, &mut self.download_state);
+
+        let lines = self
+            .logs
+            .last_lines(usize::from(logs.height.saturating_sub(2)))
+            .into_iter()
+            .map(Line::from)
+            .collect::<Vec<_>>();
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title(" Logs ")),
+            logs,
+        );
+
+        frame.render_widget(
+            Paragraph::new(format!(
+                "{} | tab: switch pane, enter: select, esc: quit",
+                self.status
+            )),
+            status,
+        );
+    }
+
+    fn draw_search(&mut self, frame: &mut Frame, area: Rect) {
+        let [input, results] =
+            Layout::vertical([Constraint::Length(3), Constraint::Min(0)]).areas(area);
+
+        frame.render_widget(
+            Paragraph::new(self.query.as_str()).block(self.block("Search", Pane::Search)),
+            input,
+        );
+        if self.pane == Pane::Search {
+            #[allow(clippy::cast_possible_truncation)]
+            frame.set_cursor_position((
+                input.x + 1 + self.query.chars().count() as u16,
+                input.y + 1,
+            ));
+        }
+
+        let list = list(&self.mangas, self.block("Results", Pane::Search));
+        frame.render_stateful_widget(list, results, &mut self.manga_state);
+    }
+}
+
+fn list<'a, T: ToString>(items: &[T], block: Block<'a>) -> List<'a> {
+    List::new(items.iter().map(|item| ListItem::new(item.to_string())))
+        .block(block)
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+}
+
+/// Reads the terminal events on a dedicated thread, until the app stops listening
+fn read_events(sender: mpsc::UnboundedSender<Message>) {
+    thread::spawn(move || {
+        while !sender.is_closed() {
+            match event::poll(TICK_RATE) {
+                Ok(true) => {}
+                Ok(false) => continue,
+                Err(err) => {
+                    error!("failed to poll terminal events: {err}");
+                    break;
+                }
+            }
+            match event::read() {
+                Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => {
+                    if sender.send(Message::Key(key)).is_err() {
+                        break;
+                    }
+                }
+                Ok(_) => {}
+                Err(err) => {
+                    error!("failed to read terminal event: {err}");
+                    break;
+                }
+            }
+        }
+    });
+}
+
+/// Runs the full screen terminal ui, with panes for the search, the chapter list, the download queue, and the logs
+pub async fn run(options: Options, logs: Logs) -> Result<()> {
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+
+    let (tx, rx) = mpsc::unbounded_channel();
+    read_events(tx.clone());
+    let result = App::new(options, logs, tx).run(&mut terminal, rx).await;
+
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    terminal.show_cursor()?;
+
+    result
+}
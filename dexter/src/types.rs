@@ -1,7 +1,7 @@
 use std::fmt::Display;
 
 use cli_table::{format::Justify, Table};
-use dexter_core::api::{get_chapter, get_chapters, get_image_links, get_manga, search};
+use dexter_core::api::{get_chapters, get_image_links, get_manga, search};
 
 fn display_otional_value<Value>(value: &Option<Value>) -> impl Display
 where
@@ -30,6 +30,64 @@ impl From<search::Data> for Manga {
     }
 }
 
+impl Manga {
+    /// Picks the title in the first available language of `languages`
+    pub fn localized(search::Data { attributes, id }: search::Data, languages: &[String]) -> Self {
+        Manga {
+            title: attributes.localized_title(languages).to_string(),
+            id,
+        }
+    }
+}
+
+/// Max length of the description displayed in the detailed search results
+const DESCRIPTION_SNIPPET_LEN: usize = 80;
+
+#[derive(Debug, Clone, Table)]
+pub struct DetailedManga {
+    #[table(title = "Title")]
+    title: String,
+    #[table(title = "ID", justify = "Justify::Right")]
+    id: String,
+    #[table(title = "Year", display_fn = "display_otional_value")]
+    year: Option<u32>,
+    #[table(title = "Status", display_fn = "display_otional_value")]
+    status: Option<String>,
+    #[table(title = "Rating", display_fn = "display_otional_value")]
+    content_rating: Option<String>,
+    #[table(title = "Description", display_fn = "display_otional_value")]
+    description: Option<String>,
+}
+
+impl DetailedManga {
+    /// Picks the title and description in the first available language of `languages`
+    pub fn localized(search::Data { attributes, id }: search::Data, languages: &[String]) -> Self {
+        let description = attributes
+            .localized_description(languages)
+            .map(|description| {
+                let description = description.lines().next().unwrap_or_default();
+                if description.chars().count() > DESCRIPTION_SNIPPET_LEN {
+                    let snippet = description
+                        .chars()
+                        .take(DESCRIPTION_SNIPPET_LEN)
+                        .collect::<String>();
+                    format!("{}...", snippet.trim_end())
+                } else {
+                    description.to_string()
+                }
+            });
+
+        DetailedManga {
+            title: attributes.localized_title(languages).to_string(),
+            id,
+            year: attributes.year,
+            status: attributes.status,
+            content_rating: attributes.content_rating,
+            description,
+        }
+    }
+}
+
 impl From<get_manga::Data> for Manga {
     fn from(get_manga::Data { attributes, id }: get_manga::Data) -> Self {
         Manga {
@@ -58,18 +116,13 @@ pub struct Chapter {
     chapter: Option<String>,
     #[table(title = "Language", display_fn = "display_otional_value")]
     language: Option<String>,
+    #[table(title = "Published", display_fn = "display_otional_value")]
+    published: Option<String>,
 }
 
-impl From<get_chapter::Data> for Chapter {
-    fn from(get_chapter::Data { attributes, id }: get_chapter::Data) -> Self {
-        Chapter {
-            id,
-            title: attributes.title,
-            volume: attributes.volume,
-            chapter: attributes.chapter,
-            language: attributes.translated_language,
-        }
-    }
+/// Keeps the day of an RFC 3339 date, e.g. `2023-06-01` for `2023-06-01T12:00:00+00:00`
+fn date(date_time: Option<String>) -> Option<String> {
+    date_time.map(|date_time| date_time.chars().take(10).collect())
 }
 
 impl From<get_chapters::Data> for Chapter {
@@ -80,6 +133,7 @@ impl From<get_chapters::Data> for Chapter {
             volume: attributes.volume,
             chapter: attributes.chapter,
             language: attributes.translated_language,
+            published: date(attributes.publish_at),
         }
     }
 }
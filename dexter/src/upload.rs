@@ -0,0 +1,208 @@
+use anyhow::{anyhow, Result};
+use camino::Utf8PathBuf;
+use dexter_core::{
+    api::{
+        upload::{self, ChapterDraft, MAX_PAGES_PER_UPLOAD},
+        AbandonUploadSession, BeginUploadSession, CommitUploadSession, UploadPages,
+    },
+    output::is_page,
+    Client, Request,
+};
+use tracing::{error, info};
+
+use crate::{
+    archive::{extension, natural_cmp, read_pages, Page},
+    args::Upload,
+};
+
+/// Reads the pages to upload from either an archive or a glob, skipping non image files,
+/// sorted by name with their numbers by value, so that `2.png` comes before `10.png`
+fn pages(path: Option<Utf8PathBuf>, images: Option<String>) -> Result<Vec<Page>> {
+    let mut pages = match (path, images) {
+        (Some(path), _) => read_pages(&path)?,
+        (None, Some(images)) => glob::glob(&images)?
+            .map(|path| {
+                let path = path?;
+                let name = path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                let bytes = std::fs::read(&path)?;
+                Ok(Page { name, bytes })
+            })
+            .collect::<Result<Vec<_>>>()?,
+        (None, None) => return Err(anyhow!("either an archive or images must be provided")),
+    };
+
+    pages.retain(|page| is_page(&page.name));
+    pages.sort_by(|a, b| natural_cmp(&a.name, &b.name));
+
+    Ok(pages)
+}
+
+/// Uploads the pages in order, and returns the ids of the uploaded pages in the same order
+async fn upload_pages(client: &Client, session_id: &str, pages: Vec<Page>) -> Result<Vec<String>> {
+    let mut page_order = Vec::with_capacity(pages.len());
+    let mut pages = pages.into_iter().enumerate().peekable();
+
+    while pages.peek().is_some() {
+        // The pages are uploaded under their index, their names may collide when the glob spans several directories
+        let (names, chunk): (Vec<_>, Vec<_>) = pages
+            .by_ref()
+            .take(MAX_PAGES_PER_UPLOAD)
+            .map(|(index, page)| {
+                let filename = format!("{index:04}.{}", extension(&page.name));
+                let upload_page = upload::Page {
+                    filename,
+                    bytes: page.bytes,
+                };
+                (page.name, upload_page)
+            })
+            .unzip();
+        let filenames = chunk
+            .iter()
+            .map(|page| page.filename.clone())
+            .collect::<Vec<_>>();
+
+        info!("Uploading {}", names.join(", "));
+
+        let uploaded_pages = UploadPages::new(session_id)
+            .with_pages(chunk)
+            .request_with(client)
+            .await?;
+
+        // The uploaded pages are matched by upload name, as the api doesn't guarantee the response order
+        for (filename, name) in filenames.into_iter().zip(names) {
+            let uploaded_page = uploaded_pages
+                .data
+                .iter()
+                .find(|page| page.attributes.original_file_name == filename)
+                .ok_or_else(|| anyhow!("page {name} was not uploaded"))?;
+            page_order.push(uploaded_page.id.clone());
+        }
+    }
+
+    Ok(page_order)
+}
+
+/// Abandons the upload session, a failure is only logged since the upload already failed
+async fn abandon(client: &Client, session_id: &str) {
+    if let Err(err) = AbandonUploadSession::new(session_id)
+        .request_with(client)
+        .await
+    {
+        error!("failed to abandon upload session {session_id}: {err}");
+    }
+}
+
+/// Uploads a new chapter, the upload session is abandoned if any page fails to upload, or if the commit fails
+pub async fn upload(
+    Upload {
+        manga_id,
+        groups,
+        path,
+        images,
+        volume,
+        chapter,
+        title,
+        language,
+        access_token,
+    }: Upload,
+) -> Result<()> {
+    let pages = pages(path, images)?;
+    if pages.is_empty() {
+        return Err(anyhow!("no pages to upload"));
+    }
+
+    let client = Client::new().with_access_token(access_token);
+
+    let session = BeginUploadSession::new(manga_id)
+        .with_groups(groups)
+        .request_with(&client)
+        .await?
+        .data;
+
+    let page_order = match upload_pages(&client, &session.id, pages).await {
+        Ok(page_order) => page_order,
+        Err(err) => {
+            abandon(&client, &session.id).await;
+            return Err(err);
+        }
+    };
+
+    let chapter_draft = ChapterDraft {
+        volume,
+        chapter,
+        title,
+        translated_language: language,
+    };
+
+    let committed_chapter = match CommitUploadSession::new(&session.id, chapter_draft, page_order)
+        .request_with(&client)
+        .await
+    {
+        Ok(committed_chapter) => committed_chapter,
+        Err(err) => {
+            abandon(&client, &session.id).await;
+            return Err(err.into());
+        }
+    };
+
+    println!("Chapter {} created", committed_chapter.data.id);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use dexter_core::mock::FixtureMiddleware;
+
+    use super::*;
+
+    static SESSION_ID: &str = "9e4c1b2a-3d5f-4a6b-8c7d-0e1f2a3b4c5d";
+
+    #[test]
+    fn pages_in_natural_order() {
+        let dir = Utf8PathBuf::try_from(std::env::temp_dir())
+            .unwrap()
+            .join(format!("dexter-upload-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in ["10.png", "2.png", "1.png", "notes.txt"] {
+            std::fs::write(dir.join(name), name).unwrap();
+        }
+
+        let pages = pages(None, Some(format!("{dir}/*"))).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let names = pages
+            .iter()
+            .map(|page| page.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["1.png", "2.png", "10.png"]);
+    }
+
+    #[tokio::test]
+    async fn page_order_by_upload_index() {
+        // The api lists the uploaded pages in any order
+        let fixtures = FixtureMiddleware::new().with_fixture(
+            format!("/upload/{SESSION_ID}"),
+            r#"{ "data": [
+                { "id": "second-id", "attributes": { "originalFileName": "0001.png" } },
+                { "id": "first-id", "attributes": { "originalFileName": "0000.png" } }
+            ] }"#,
+        );
+        let client = Client::new().with_middleware(fixtures);
+        // Both pages have the same name, e.g. from two directories
+        let pages = ["first", "second"]
+            .into_iter()
+            .map(|bytes| Page {
+                name: "1.png".to_string(),
+                bytes: bytes.into(),
+            })
+            .collect();
+
+        let page_order = upload_pages(&client, SESSION_ID, pages).await.unwrap();
+
+        assert_eq!(page_order, ["first-id", "second-id"]);
+    }
+}
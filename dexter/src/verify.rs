@@ -0,0 +1,268 @@
+use std::collections::HashSet;
+
+use anyhow::{anyhow, Result};
+use camino::Utf8Path;
+use dexter_core::{
+    api::get_image_links, write_atomically_with_comic_info, Client, ComicInfo, DownloadImage,
+    GetImageLinks, Request, WritePolicy,
+};
+use eco_cbz::CbzWriter;
+use futures::{stream, StreamExt, TryStreamExt};
+use tracing::{info, warn};
+
+use crate::{
+    archive::{extension, natural_cmp, read_pages, Page},
+    args::Verify,
+};
+
+const MAX_PARALLEL_DOWNLOAD: usize = 10;
+
+#[derive(Debug)]
+struct Report {
+    expected_pages: usize,
+    pages: usize,
+    ordered: bool,
+    missing_pages: Vec<usize>,
+    broken_pages: Vec<usize>,
+}
+
+impl Report {
+    fn is_valid(&self) -> bool {
+        self.expected_pages == self.pages
+            && self.ordered
+            && self.missing_pages.is_empty()
+            && self.broken_pages.is_empty()
+    }
+
+    fn print(&self) {
+        println!("Pages: {}/{}", self.pages, self.expected_pages);
+        println!(
+            "Page order: {}",
+            if self.ordered { "ok" } else { "not sorted" }
+        );
+        println!("Missing pages: {}", page_list(&self.missing_pages));
+        println!("Broken pages: {}", page_list(&self.broken_pages));
+    }
+}
+
+/// Lists the page numbers, starting at 1
+fn page_list(indexes: &[usize]) -> String {
+    if indexes.is_empty() {
+        return String::from("none");
+    }
+    indexes
+        .iter()
+        .map(|index| (index + 1).to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Sorts the pages in natural name order, so that `2.png` comes before `10.png`,
+/// and returns whether they already were
+fn sort_pages(pages: &mut [Page]) -> bool {
+    let ordered = pages
+        .windows(2)
+        .all(|pages| natural_cmp(&pages[0].name, &pages[1].name).is_le());
+    pages.sort_by(|a, b| natural_cmp(&a.name, &b.name));
+
+    ordered
+}
+
+/// Returns the index in the chapter of each page, read from their numbered names such as `0001.png`.
+///
+/// The numbering starts at 0 or 1 depending on the archive writer, so `None` is returned if it can't be told,
+/// when both the first and the last pages are missing, or if a name isn't a page number of the chapter.
+fn page_indexes(pages: &[Page], expected_pages: usize) -> Option<Vec<usize>> {
+    let numbers = pages
+        .iter()
+        .map(|page| Utf8Path::new(&page.name).file_stem()?.parse::<usize>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    let offset = if numbers.contains(&0) {
+        0
+    } else if numbers.contains(&expected_pages) {
+        1
+    } else {
+        return None;
+    };
+
+    let mut seen = HashSet::new();
+    numbers
+        .into_iter()
+        .map(|number| {
+            let index = number.checked_sub(offset)?;
+            (index < expected_pages && seen.insert(index)).then_some(index)
+        })
+        .collect()
+}
+
+/// Downloads the pages at the given indexes, and returns them along with their index
+async fn download_pages(
+    image_links: &[get_image_links::Description],
+    indexes: Vec<usize>,
+    max_download_retries: u32,
+) -> Result<Vec<(usize, Page)>> {
+    stream::iter(indexes)
+        .map(|index| async move {
+            let description = &image_links[index];
+            let bytes = DownloadImage::new(&description.url)
+                .set_max_download_retries(max_download_retries)
+                .request()
+                .await?;
+            Ok::<_, anyhow::Error>((
+                index,
+                Page {
+                    name: description.filename.clone(),
+                    bytes: bytes.to_vec(),
+                },
+            ))
+        })
+        .buffer_unordered(MAX_PARALLEL_DOWNLOAD)
+        .try_collect()
+        .await
+}
+
+/// Checks that the archive contains all the pages of the chapter, in order, and that they can be decoded.
+/// When `repair` is set, only the missing or broken pages are downloaded again and the archive is rewritten.
+pub async fn verify(
+    Verify {
+        path,
+        chapter_id,
+        repair,
+        max_download_retries,
+    }: Verify,
+) -> Result<()> {
+    let image_links = GetImageLinks::new(&chapter_id).request().await?;
+    let expected_pages = image_links.len();
+    let mut pages = read_pages(&path)?;
+    let page_count = pages.len();
+
+    let ordered = sort_pages(&mut pages);
+
+    // Without numbered names, complete archives are matched to the chapter by position,
+    // while the pages of incomplete ones can't be placed and are all downloaded again
+    let indexes = page_indexes(&pages, expected_pages)
+        .or_else(|| (page_count == expected_pages).then(|| (0..expected_pages).collect()));
+    let mut chapter_pages = (0..expected_pages).map(|_| None).collect::<Vec<_>>();
+    if let Some(indexes) = indexes {
+        for (page, index) in pages.into_iter().zip(indexes) {
+            chapter_pages[index] = Some(page);
+        }
+    } else {
+        warn!("the pages of {path} can't be matched to the chapter pages");
+    }
+
+    let report = Report {
+        expected_pages,
+        pages: page_count,
+        ordered,
+        missing_pages: chapter_pages
+            .iter()
+            .enumerate()
+            .filter(|(_, page)| page.is_none())
+            .map(|(index, _)| index)
+            .collect(),
+        broken_pages: chapter_pages
+            .iter()
+            .enumerate()
+            .filter(|(_, page)| {
+                page.as_ref()
+                    .is_some_and(|page| image::load_from_memory(&page.bytes).is_err())
+            })
+            .map(|(index, _)| index)
+            .collect(),
+    };
+
+    report.print();
+
+    if report.is_valid() {
+        return Ok(());
+    }
+
+    if !repair {
+        return Err(anyhow!("{path} is incomplete or corrupted"));
+    }
+
+    let mut indexes = [report.missing_pages, report.broken_pages].concat();
+    indexes.sort_unstable();
+
+    info!("Downloading {} page(s)", indexes.len());
+
+    for (index, page) in download_pages(&image_links, indexes, max_download_retries).await? {
+        chapter_pages[index] = Some(page);
+    }
+
+    let mut cbz_writer = CbzWriter::default();
+    for page in chapter_pages.into_iter().flatten() {
+        cbz_writer.insert_bytes_with_extension(&page.bytes, &extension(&page.name))?;
+    }
+
+    // The archive is rewritten from its pages, the metadata is fetched again
+    let comic_info = match ComicInfo::fetch(&chapter_id, &Client::shared()).await {
+        Ok(comic_info) => Some(comic_info),
+        Err(err) => {
+            warn!("metadata of chapter {chapter_id} not written to {path}: {err}");
+            None
+        }
+    };
+    write_atomically_with_comic_info(
+        cbz_writer,
+        &path,
+        WritePolicy::Overwrite,
+        comic_info.as_ref(),
+    )?;
+
+    println!("{path} repaired");
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pages(names: &[&str]) -> Vec<Page> {
+        names
+            .iter()
+            .map(|name| Page {
+                name: (*name).to_string(),
+                bytes: Vec::new(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn sort_pages_naturally() {
+        let mut unpadded = pages(&["1.png", "2.png", "10.png"]);
+        assert!(sort_pages(&mut unpadded));
+
+        let mut shuffled = pages(&["10.png", "1.png", "2.png"]);
+        assert!(!sort_pages(&mut shuffled));
+        assert_eq!(
+            shuffled
+                .iter()
+                .map(|page| page.name.as_str())
+                .collect::<Vec<_>>(),
+            ["1.png", "2.png", "10.png"]
+        );
+    }
+
+    #[test]
+    fn page_indexes_from_names() {
+        // Numbered from 0, the last page is missing
+        assert_eq!(
+            page_indexes(&pages(&["0000.png", "0001.png", "0003.jpg"]), 5),
+            Some(vec![0, 1, 3])
+        );
+        // Numbered from 1, the first page is missing
+        assert_eq!(
+            page_indexes(&pages(&["2.png", "4.png", "5.png"]), 5),
+            Some(vec![1, 3, 4])
+        );
+        // Both the first and the last pages are missing
+        assert_eq!(page_indexes(&pages(&["0002.png", "0003.png"]), 5), None);
+        // Not page numbers of the chapter
+        assert_eq!(page_indexes(&pages(&["0000.png", "cover.png"]), 5), None);
+        assert_eq!(page_indexes(&pages(&["0000.png", "0009.png"]), 5), None);
+        assert_eq!(page_indexes(&pages(&["0000.png", "0000.jpg"]), 5), None);
+    }
+}
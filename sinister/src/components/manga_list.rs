@@ -1,29 +1,35 @@
 use dexter_core::api::search;
 use dioxus::prelude::*;
 
+use crate::{settings::Settings, theme::Theme};
+
 #[must_use]
 #[inline_props]
 pub fn MangaList<'a>(
     cx: Scope,
     mangas: UseRef<Option<Vec<search::Data>>>,
+    settings: UseRef<Settings>,
     on_select: EventHandler<'a, String>,
 ) -> Element {
     let Some(mangas) = &*mangas.read() else {
         return None;
     };
+    let languages = settings.read().languages.clone();
+    let theme = Theme::new(&settings.read());
 
     cx.render(rsx! {
         div {
             class: "flex flex-col overflow-y-auto",
             for manga in mangas.iter() {
-                div {
+                button {
                     key: "{manga.id}",
-                    class: "flex flex-row flex-shrink-0 items-center cursor-pointer h-8 w-full hover:bg-slate-600 px-2",
+                    class: "flex flex-row flex-shrink-0 items-center cursor-pointer h-8 w-full text-left {theme.hover} px-2",
+                    r#type: "button",
                     onclick: {
                         let manga_id = manga.id.clone();
                         move |_evt| on_select.call(manga_id.clone())
                     },
-                    "{manga.attributes.title.en}"
+                    manga.attributes.localized_title(&languages)
                 }
             }
         }
@@ -1,18 +1,27 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, fs::create_dir_all, sync::Arc};
 
-use camino::Utf8PathBuf;
-use dexter_core::api::{
-    archive_download, get_chapters, get_manga, ArchiveDownload, GetChapters, Request,
+use dexter_core::{
+    api::{archive_download, get_chapters, get_manga},
+    progress::BoundedSender,
+    Dexter,
 };
 use dioxus::prelude::*;
 use tokio::sync::mpsc;
 use tracing::{error, info};
 
-use crate::CHAPTERS_LIMIT;
+use crate::{
+    dexter,
+    i18n::Text,
+    paths,
+    settings::{self, Settings},
+    theme::Theme,
+    CHAPTERS_LIMIT,
+};
 
 use super::Loader;
 
-const CONCURRENT_IMAGE_DOWNLOAD: u32 = 10;
+/// Progress events waiting to be displayed, the following ones are merged until the ui catches up
+const EVENTS_CAPACITY: usize = 64;
 
 #[must_use]
 #[inline_props]
@@ -20,6 +29,7 @@ pub fn MangaView<'a>(
     cx: Scope,
     manga: UseState<Option<(get_manga::Response, get_chapters::Response)>>,
     download_progress: UseRef<HashMap<String, f32>>,
+    settings: UseRef<Settings>,
     on_close: EventHandler<'a, ()>,
 ) -> Element {
     let manga_state = manga;
@@ -31,28 +41,23 @@ pub fn MangaView<'a>(
             }
         });
     };
+    let locale = settings.read().locale;
+    let theme = Theme::new(&settings.read());
     let page = use_state(cx, || 1);
     let loading = use_state(cx, || false);
-    let language = use_state(cx, || {
-        isolang::Language::Eng.to_639_1().unwrap().to_string()
-    });
+    let language = use_state(cx, || settings.read().language().to_string());
 
     let download = move |chapter: &get_chapters::Data| {
-        if download_progress.read().contains_key(&chapter.id) {
+        let file_name = Dexter::chapter_file_name(&manga.data, chapter);
+        if download_progress.read().contains_key(&file_name) {
             return;
         }
         to_owned![download_progress];
         let chapter_id = chapter.id.clone();
-        let file_name = format!(
-            "{} - {} - {}.cbz",
-            manga.data.attributes.title.en,
-            chapter.attributes.chapter.as_deref().unwrap_or("unknown"),
-            chapter.attributes.title.as_deref().unwrap_or("unknown"),
-        );
         info!("downloading {file_name}");
         download_progress
             .with_mut(|download_progress| download_progress.insert(file_name.clone(), 0.));
-        let (tx, mut rx) = mpsc::unbounded_channel();
+        let (tx, mut rx) = mpsc::channel(EVENTS_CAPACITY);
         {
             let file_name = file_name.clone();
             cx.spawn(async move {
@@ -66,6 +71,8 @@ pub fn MangaView<'a>(
                     )]
                     match event {
                         archive_download::Event::Init(s) => size = s as f32,
+                        archive_download::Event::Progress(_)
+                        | archive_download::Event::DataSaver(_) => {}
                         archive_download::Event::Done => {
                             download_progress
                                 .with_mut(|download_progress| download_progress.remove(&file_name));
@@ -77,31 +84,46 @@ pub fn MangaView<'a>(
                                     .insert(file_name.clone(), progress / (size * 2.0) * 100.0)
                             });
                         }
+                        archive_download::Event::Coalesced {
+                            downloads, zips, ..
+                        } => {
+                            progress += (downloads + zips) as f32;
+                            download_progress.with_mut(|download_progress| {
+                                download_progress
+                                    .insert(file_name.clone(), progress / (size * 2.0) * 100.0)
+                            });
+                        }
                     }
                 }
+                // The sender is dropped once the download task ends, including when it fails
+                download_progress
+                    .with_mut(|download_progress| download_progress.remove(&file_name));
             });
         }
 
         tokio::spawn(async move {
-            let cbz = ArchiveDownload::new(&chapter_id)
-                .set_max_download_retries(CONCURRENT_IMAGE_DOWNLOAD)
-                .set_sender(tx)
-                .request()
+            let Some(downloads_dir) = paths::downloads_dir() else {
+                error!("downloads directory not found, {file_name} is not written");
+                return;
+            };
+            if let Err(err) = create_dir_all(&downloads_dir) {
+                error!("downloads directory creation error: {err}");
+                return;
+            }
+            let path = downloads_dir.join(&file_name);
+            let progress = Arc::new(BoundedSender::new(tx));
+            match dexter()
+                .download_chapter_to(&chapter_id, &path, progress)
                 .await
-                .unwrap();
-            let path = Utf8PathBuf::try_from(home::home_dir().unwrap())
-                .unwrap()
-                .join("Downloads")
-                .join(&file_name);
-            info!("{file_name} downloaded");
-            info!("{} downloaded", path.to_string());
-            if let Err(err) = cbz.write_to_path(path) {
-                error!("cbz creation error: {err}");
+            {
+                Ok(Some(decision)) => info!("{path} already existed: {decision}"),
+                Ok(None) => {}
+                Err(err) => error!("{file_name} download error: {err}"),
             }
         });
     };
 
-    let close = move |_evt| {
+    let close = move |()| {
         if download_progress.read().is_empty() {
             on_close.call(());
         }
@@ -113,10 +135,24 @@ pub fn MangaView<'a>(
         }
     };
 
+    let keys = settings.read().keys.clone();
+    let (offset, limit, total) = (chapters.offset, chapters.limit, chapters.total);
+    let onkeydown = move |evt: KeyboardEvent| {
+        let key = evt.key().to_string();
+        if key == keys.close {
+            close(());
+        } else if key == keys.previous_page && offset > 0 {
+            set_page(**page - 1);
+        } else if key == keys.next_page && offset + limit < total {
+            set_page(**page + 1);
+        }
+    };
+
     let change_language = move |evt: FormEvent| {
         if !**loading {
             page.set(1);
             language.set(evt.value.clone());
+            settings::update(settings, |settings| settings.prefer_language(&evt.value));
         }
     };
 
@@ -124,11 +160,13 @@ pub fn MangaView<'a>(
         to_owned![loading, manga, manga_state];
         loading.set(true);
         async move {
-            let received_chapters = match GetChapters::new(&manga.data.id)
-                .set_limit(CHAPTERS_LIMIT)
-                .push_language(&*language)
-                .set_offset((*page - 1) * CHAPTERS_LIMIT)
-                .request()
+            let received_chapters = match dexter()
+                .chapters(
+                    &manga.data.id,
+                    [&*language],
+                    (*page - 1) * CHAPTERS_LIMIT,
+                    CHAPTERS_LIMIT,
+                )
                 .await
             {
                 Ok(chapters) => chapters,
@@ -147,8 +185,8 @@ pub fn MangaView<'a>(
     });
 
     cx.render(rsx! {
-        div { class: "absolute inset-0 bg-slate-800",
-            div { class: "flex flex w-full flex-shrink-0 justify-between items-center h-16 px-2 border-b border-slate-900 text-xl",
+        div { class: "absolute inset-0 {theme.background}", tabindex: "0", onkeydown: onkeydown,
+            div { class: "flex flex w-full flex-shrink-0 justify-between items-center h-16 px-2 border-b {theme.border} text-xl",
                 div { "{manga.data.attributes.title.en}" }
                 div { class: "flex flex-row items-center gap-2",
                     div {
@@ -177,44 +215,55 @@ pub fn MangaView<'a>(
                             }
                         }
                     }
-                    div { i { class: "bi bi-x-lg cursor-pointer", onclick: close } }
+                    button {
+                        class: "cursor-pointer",
+                        r#type: "button",
+                        title: "{locale.text(Text::Close)}",
+                        aria_label: "{locale.text(Text::Close)}",
+                        onclick: move |_evt| close(()),
+                        i { class: "bi bi-x-lg", aria_hidden: "true" }
+                    }
                 }
             }
             div { class: "h-[calc(100%-8rem)] overflow-y-auto",
                 for chapter in chapters.data.iter() {
                     div { key: "{chapter.id}", class: "flex flex-row gap-1 px-2",
-                        div {
-                            class: "flex items-center",
-                            title: "Download",
+                        button {
+                            class: "flex items-center cursor-pointer",
+                            r#type: "button",
+                            title: "{locale.text(Text::Download)}",
+                            aria_label: "{locale.text(Text::Download)}",
                             onclick: move |_evt| download(chapter),
-                            i { class: "bi bi-download cursor-pointer" }
+                            i { class: "bi bi-download", aria_hidden: "true" }
                         }
-                        div { chapter.attributes.volume.as_deref().unwrap_or("unknown") }
+                        div { chapter.attributes.volume.as_deref().unwrap_or(locale.text(Text::Unknown)) }
                         div { "-" }
-                        div { chapter.attributes.chapter.as_deref().unwrap_or("unknown") }
+                        div { chapter.attributes.chapter.as_deref().unwrap_or(locale.text(Text::Unknown)) }
                         div { "-" }
-                        div { chapter.attributes.title.as_deref().unwrap_or("unknown") }
+                        div { chapter.attributes.title.as_deref().unwrap_or(locale.text(Text::Unknown)) }
                         div { "-" }
-                        div { chapter.attributes.translated_language.as_deref().unwrap_or("unknown") }
+                        div { chapter.attributes.translated_language.as_deref().unwrap_or(locale.text(Text::Unknown)) }
                     }
                 }
             }
-            div { class: "flex items-center justify-center h-16 border-t border-slate-900 gap-2",
+            div { class: "flex items-center justify-center h-16 border-t {theme.border} gap-2",
                 if chapters.offset > 0 {
                     rsx! {
-                        div {
-                            class: "flex justify-center items-center cursor-pointer px-2 border border-slate-900 bg-slate-700 rounded hover:bg-slate-500 w-24",
+                        button {
+                            class: "flex justify-center items-center cursor-pointer px-2 border {theme.border} {theme.secondary_button} rounded w-24",
+                            r#type: "button",
                             onclick: move |_evt| set_page(**page - 1),
-                            "Previous"
+                            locale.text(Text::Previous)
                         }
                     }
                 }
                 if chapters.offset + chapters.limit < chapters.total {
                     rsx! {
-                        div {
-                            class: "flex justify-center items-center cursor-pointer px-2 border border-slate-900 bg-slate-700 rounded hover:bg-slate-500 w-24",
+                        button {
+                            class: "flex justify-center items-center cursor-pointer px-2 border {theme.border} {theme.secondary_button} rounded w-24",
+                            r#type: "button",
                             onclick: move |_evt| set_page(**page + 1),
-                            "Next"
+                            locale.text(Text::Next)
                         }
                     }
                 }
@@ -0,0 +1,105 @@
+use serde::{Deserialize, Serialize};
+
+/// Languages the interface is translated to, independently of the languages of the chapters
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    #[default]
+    En,
+    Fr,
+    Ja,
+}
+
+/// Strings displayed by the interface
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Text {
+    Search,
+    Download,
+    Previous,
+    Next,
+    Unknown,
+    InterfaceLanguage,
+    UiScale,
+    HighContrast,
+    Close,
+    Appearance,
+    System,
+    Dark,
+    Light,
+}
+
+impl Locale {
+    pub const ALL: [Self; 3] = [Self::En, Self::Fr, Self::Ja];
+
+    /// ISO 639-1 code, as stored in the settings
+    #[must_use]
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::En => "en",
+            Self::Fr => "fr",
+            Self::Ja => "ja",
+        }
+    }
+
+    #[must_use]
+    pub fn from_code(code: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|locale| locale.code() == code)
+    }
+
+    /// Name of the language in the language itself
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::En => "English",
+            Self::Fr => "Français",
+            Self::Ja => "日本語",
+        }
+    }
+
+    #[must_use]
+    pub fn text(self, text: Text) -> &'static str {
+        match (self, text) {
+            (Self::En, Text::Search) => "Search",
+            (Self::En, Text::Download) => "Download",
+            (Self::En, Text::Previous) => "Previous",
+            (Self::En, Text::Next) => "Next",
+            (Self::En, Text::Unknown) => "unknown",
+            (Self::En, Text::InterfaceLanguage) => "Interface language",
+            (Self::En, Text::UiScale) => "Interface scale",
+            (Self::En, Text::HighContrast) => "High contrast",
+            (Self::En, Text::Close) => "Close",
+            (Self::En, Text::Appearance) => "Appearance",
+            (Self::En, Text::System) => "System",
+            (Self::En, Text::Dark) => "Dark",
+            (Self::En, Text::Light) => "Light",
+            (Self::Fr, Text::Search) => "Rechercher",
+            (Self::Fr, Text::Download) => "Télécharger",
+            (Self::Fr, Text::Previous) => "Précédent",
+            (Self::Fr, Text::Next) => "Suivant",
+            (Self::Fr, Text::Unknown) => "inconnu",
+            (Self::Fr, Text::InterfaceLanguage) => "Langue de l'interface",
+            (Self::Fr, Text::UiScale) => "Taille de l'interface",
+            (Self::Fr, Text::HighContrast) => "Contraste élevé",
+            (Self::Fr, Text::Close) => "Fermer",
+            (Self::Fr, Text::Appearance) => "Apparence",
+            (Self::Fr, Text::System) => "Système",
+            (Self::Fr, Text::Dark) => "Sombre",
+            (Self::Fr, Text::Light) => "Clair",
+            (Self::Ja, Text::Search) => "検索",
+            (Self::Ja, Text::Download) => "ダウンロード",
+            (Self::Ja, Text::Previous) => "前へ",
+            (Self::Ja, Text::Next) => "次へ",
+            (Self::Ja, Text::Unknown) => "不明",
+            (Self::Ja, Text::InterfaceLanguage) => "表示言語",
+            (Self::Ja, Text::UiScale) => "表示サイズ",
+            (Self::Ja, Text::HighContrast) => "ハイコントラスト",
+            (Self::Ja, Text::Close) => "閉じる",
+            (Self::Ja, Text::Appearance) => "外観",
+            (Self::Ja, Text::System) => "システム",
+            (Self::Ja, Text::Dark) => "ダーク",
+            (Self::Ja, Text::Light) => "ライト",
+        }
+    }
+}
@@ -3,23 +3,45 @@
 #![allow(non_snake_case)]
 #![allow(clippy::ignored_unit_patterns)]
 
-use std::{collections::HashMap, time::Duration};
+use std::{collections::HashMap, sync::OnceLock};
 
-use dexter_core::{GetChapters, GetManga, Request, Search};
+use dexter_core::{api::GetChapterById, Dexter, Link, Request};
 use dioxus::prelude::*;
 use dioxus_desktop::{Config, WindowBuilder};
-use tokio::time::sleep;
 use tracing::error;
 
 use crate::components::{Loader, MangaList, MangaView, Progress};
+use crate::i18n::{Locale, Text};
+use crate::settings::{Settings, UI_SCALES};
+use crate::theme::{Appearance, Theme};
 
 pub mod components;
+pub mod i18n;
+pub mod paths;
+pub mod settings;
+pub mod theme;
 
 static MANGAS_LENGTH: u32 = 50;
 pub(crate) static CHAPTERS_LIMIT: u32 = 100;
 
+static DEXTER: OnceLock<Dexter> = OnceLock::new();
+
+/// Returns the facade shared by the whole app, so that all the api requests are rate limited together
+pub(crate) fn dexter() -> &'static Dexter {
+    DEXTER.get_or_init(Dexter::default)
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("json error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("dexter error: {0}")]
+    Dexter(#[from] dexter_core::Error),
+
     #[error("unknown error: {0}")]
     Unknown(String),
 }
@@ -52,10 +74,57 @@ fn App(cx: Scope<AppProps>) -> Element {
     let manga_search_loading = use_state(cx, || false);
     let manga_loading = use_state(cx, || false);
     let download_progress = use_ref(cx, HashMap::<String, f32>::new);
+    let settings = use_ref(cx, Settings::load);
+
+    let locale = settings.read().locale;
+    let ui_scale = settings.read().ui_scale;
+    let high_contrast = settings.read().high_contrast;
+    let appearance = settings.read().appearance;
+    let theme = Theme::new(&settings.read());
+
+    let change_locale = move |evt: FormEvent| {
+        if let Some(locale) = Locale::from_code(&evt.value) {
+            settings::update(settings, |settings| settings.locale = locale);
+        }
+    };
+
+    let change_ui_scale = move |evt: FormEvent| {
+        if let Ok(ui_scale) = evt.value.parse() {
+            settings::update(settings, |settings| settings.ui_scale = ui_scale);
+        }
+    };
+
+    let toggle_high_contrast = move |_evt: FormEvent| {
+        settings::update(settings, |settings| {
+            settings.high_contrast = !settings.high_contrast;
+        });
+    };
+
+    let change_appearance = move |evt: FormEvent| {
+        if let Some(appearance) = Appearance::from_code(&evt.value) {
+            settings::update(settings, |settings| settings.appearance = appearance);
+        }
+    };
 
     let onsubmit = move |evt: FormEvent| {
-        if !**manga_search_loading {
-            mangas_search.set(evt.values["title"][0].clone());
+        if **manga_search_loading {
+            return;
+        }
+        let title = evt.values["title"][0].clone();
+        // Pasted links open their manga straight away instead of searching
+        match Link::parse(&title) {
+            Some(Link::Title(manga_id)) => selected_manga_id.set(Some(manga_id)),
+            Some(Link::Chapter(chapter_id)) => {
+                to_owned![selected_manga_id];
+                cx.spawn(async move {
+                    match GetChapterById::new(&chapter_id).request().await {
+                        Ok(response) => selected_manga_id
+                            .set(response.data.manga().map(|manga| manga.id.clone())),
+                        Err(err) => error!("chapter get error: {err}"),
+                    }
+                });
+            }
+            None => mangas_search.set(title),
         }
     };
 
@@ -66,7 +135,7 @@ fn App(cx: Scope<AppProps>) -> Element {
             to_owned![form_classes];
             async move {
                 if mangas.read().is_some() || *manga_search_loading {
-                    form_classes.set("h-16 border-b border-slate-900");
+                    form_classes.set("h-16 border-b");
                 }
             }
         },
@@ -81,12 +150,7 @@ fn App(cx: Scope<AppProps>) -> Element {
             }
             mangas.set(None);
             manga_search_loading.set(true);
-            sleep(Duration::from_secs(1)).await;
-            let received_mangas = match Search::new(&*mangas_search)
-                .with_limit(MANGAS_LENGTH)
-                .request()
-                .await
-            {
+            let received_mangas = match dexter().search(&mangas_search, Some(MANGAS_LENGTH)).await {
                 Ok(mangas) => mangas,
                 Err(err) => {
                     error!("manga search error: {err}");
@@ -99,24 +163,22 @@ fn App(cx: Scope<AppProps>) -> Element {
     });
 
     use_future!(cx, |selected_manga_id| {
-        to_owned![selected_manga, manga_loading];
+        to_owned![selected_manga, manga_loading, settings];
         async move {
             let Some(manga_id) = &*selected_manga_id else {
                 return;
             };
             manga_loading.set(true);
-            sleep(Duration::from_secs(1)).await;
-            let received_manga = match GetManga::new(manga_id).request().await {
+            let received_manga = match dexter().manga(manga_id).await {
                 Ok(manga) => manga,
                 Err(err) => {
                     error!("manga get error: {err}");
                     return;
                 }
             };
-            let received_chapters = match GetChapters::new(manga_id)
-                .set_limit(CHAPTERS_LIMIT)
-                .push_language("en")
-                .request()
+            let languages = settings.read().languages.clone();
+            let received_chapters = match dexter()
+                .chapters(manga_id, languages, 0, CHAPTERS_LIMIT)
                 .await
             {
                 Ok(chapters) => chapters,
@@ -131,7 +193,9 @@ fn App(cx: Scope<AppProps>) -> Element {
     });
 
     cx.render(rsx! {
-        div { class: "w-screen h-screen flex flex-col text-slate-400",
+        div {
+            class: "w-screen h-screen flex flex-col {theme.text} {theme.background}",
+            style: "zoom: {ui_scale}%",
             if !download_progress.read().is_empty() {
                 rsx! {
                     div {
@@ -146,7 +210,7 @@ fn App(cx: Scope<AppProps>) -> Element {
                     }
                 }
             }
-            div { class: "flex flex-shrink-0 w-full items-center justify-center transition-[height] {form_classes}",
+            div { class: "flex flex-shrink-0 w-full items-center justify-center transition-[height] {form_classes} {theme.border}",
                 form {
                     onsubmit: onsubmit,
                     prevent_default: "onsubmit",
@@ -160,10 +224,51 @@ fn App(cx: Scope<AppProps>) -> Element {
                         name: "title"
                     }
                     button {
-                        class: "h-full px-2 bg-slate-900 hover:bg-slate-600",
+                        class: "h-full px-2 {theme.button}",
                         r#type: "submit",
                         disabled: "{manga_search_loading}",
-                        "Search"
+                        locale.text(Text::Search)
+                    }
+                    select {
+                        class: "h-full px-2 text-slate-900 outline-none text-sm",
+                        name: "locale",
+                        title: "{locale.text(Text::InterfaceLanguage)}",
+                        aria_label: "{locale.text(Text::InterfaceLanguage)}",
+                        oninput: change_locale,
+                        value: "{locale.code()}",
+                        for locale in Locale::ALL {
+                            option { value: "{locale.code()}", "{locale.name()}" }
+                        }
+                    }
+                    select {
+                        class: "h-full px-2 text-slate-900 outline-none text-sm",
+                        name: "ui_scale",
+                        title: "{locale.text(Text::UiScale)}",
+                        aria_label: "{locale.text(Text::UiScale)}",
+                        oninput: change_ui_scale,
+                        value: "{ui_scale}",
+                        for scale in UI_SCALES {
+                            option { value: "{scale}", "{scale}%" }
+                        }
+                    }
+                    label { class: "flex flex-row items-center gap-1 text-sm",
+                        input {
+                            r#type: "checkbox",
+                            checked: "{high_contrast}",
+                            oninput: toggle_high_contrast,
+                        }
+                        locale.text(Text::HighContrast)
+                    }
+                    select {
+                        class: "h-full px-2 text-slate-900 outline-none text-sm",
+                        name: "appearance",
+                        title: "{locale.text(Text::Appearance)}",
+                        aria_label: "{locale.text(Text::Appearance)}",
+                        oninput: change_appearance,
+                        value: "{appearance.code()}",
+                        for appearance in Appearance::ALL {
+                            option { value: "{appearance.code()}", "{locale.text(appearance.label())}" }
+                        }
                     }
                 }
             }
@@ -179,6 +284,7 @@ fn App(cx: Scope<AppProps>) -> Element {
                 rsx! {
                     MangaList {
                         mangas: mangas.clone(),
+                        settings: settings.clone(),
                         on_select: move |manga_id| selected_manga_id.set(Some(manga_id)),
                     }
                 }
@@ -188,6 +294,7 @@ fn App(cx: Scope<AppProps>) -> Element {
                     MangaView {
                         manga: selected_manga.clone(),
                         download_progress: download_progress.clone(),
+                        settings: settings.clone(),
                         on_close: move |()| {
                             selected_manga_id.set(None);
                             selected_manga.set(None);
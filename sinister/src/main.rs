@@ -5,11 +5,16 @@ use clap::Parser;
 
 #[derive(Parser, Debug)]
 #[clap(about, author, version)]
-pub struct Args;
+pub struct Args {
+    /// Store the settings and the downloads in a `sinister-data` folder next to the executable
+    #[clap(long)]
+    pub portable: bool,
+}
 
 fn main() {
     tracing_subscriber::fmt::init();
-    let _args = Args::parse();
+    let args = Args::parse();
+    sinister::paths::set_portable(args.portable);
     let rt = tokio::runtime::Runtime::new().unwrap();
     let _guard = rt.enter();
 
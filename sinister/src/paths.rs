@@ -0,0 +1,46 @@
+use std::{env::current_exe, sync::OnceLock};
+
+use camino::Utf8PathBuf;
+
+static PORTABLE: OnceLock<bool> = OnceLock::new();
+
+/// Name of the folder created next to the executable in portable mode
+static PORTABLE_DIR: &str = "sinister-data";
+
+/// Stores everything next to the executable instead of the platform directories, e.g. for usb sticks.
+///
+/// Must be called before the first path is resolved, later calls are ignored.
+pub fn set_portable(portable: bool) {
+    let _ = PORTABLE.set(portable);
+}
+
+fn portable_dir() -> Option<Utf8PathBuf> {
+    if !*PORTABLE.get_or_init(|| false) {
+        return None;
+    }
+    let exe = Utf8PathBuf::try_from(current_exe().ok()?).ok()?;
+    Some(exe.parent()?.join(PORTABLE_DIR))
+}
+
+fn platform_dir(dir: Option<std::path::PathBuf>) -> Option<Utf8PathBuf> {
+    Utf8PathBuf::try_from(dir?).ok()
+}
+
+/// Settings directory, `$XDG_CONFIG_HOME/sinister` on Linux
+#[must_use]
+pub fn config_dir() -> Option<Utf8PathBuf> {
+    match portable_dir() {
+        Some(portable_dir) => Some(portable_dir.join("config")),
+        None => Some(platform_dir(dirs::config_dir())?.join("sinister")),
+    }
+}
+
+/// Where the downloaded chapters are written, the user download directory unless in portable mode
+#[must_use]
+pub fn downloads_dir() -> Option<Utf8PathBuf> {
+    match portable_dir() {
+        Some(portable_dir) => Some(portable_dir.join("downloads")),
+        None => platform_dir(dirs::download_dir())
+            .or_else(|| Some(platform_dir(dirs::home_dir())?.join("Downloads"))),
+    }
+}
@@ -0,0 +1,135 @@
+use std::fs::{create_dir_all, read_to_string};
+
+use camino::Utf8PathBuf;
+use dexter_core::{output::write_bytes_atomically, WritePolicy};
+use dioxus::prelude::UseRef;
+use serde::{Deserialize, Serialize};
+use tracing::{error, warn};
+
+use crate::{i18n::Locale, paths, theme::Appearance, Error, Result};
+
+/// User preferences, stored as json in `settings.json`, in the [`paths::config_dir`]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Settings {
+    /// Languages used for the chapters and the titles, in order of preference
+    pub languages: Vec<String>,
+    /// Language of the interface
+    #[serde(default)]
+    pub locale: Locale,
+    /// Zoom of the whole interface, in percent
+    #[serde(default = "default_ui_scale")]
+    pub ui_scale: u16,
+    /// Black and white theme, for low vision users, takes precedence over the appearance
+    #[serde(default)]
+    pub high_contrast: bool,
+    #[serde(default)]
+    pub appearance: Appearance,
+    /// Keyboard shortcuts of the chapters view
+    #[serde(default)]
+    pub keys: KeyBindings,
+}
+
+/// Keyboard shortcuts, as the DOM `key` values (`ArrowLeft`, `Escape`, `n`...), remappable in `settings.json`
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyBindings {
+    pub previous_page: String,
+    pub next_page: String,
+    pub close: String,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            previous_page: "ArrowLeft".to_string(),
+            next_page: "ArrowRight".to_string(),
+            close: "Escape".to_string(),
+        }
+    }
+}
+
+/// Zoom levels offered in the interface, in percent
+pub const UI_SCALES: [u16; 6] = [75, 90, 100, 125, 150, 200];
+
+fn default_ui_scale() -> u16 {
+    100
+}
+
+/// Applies `update` to the settings and saves them, a failed save is only logged
+pub fn update(settings: &UseRef<Settings>, update: impl FnOnce(&mut Settings)) {
+    settings.with_mut(|settings| {
+        update(settings);
+        if let Err(err) = settings.save() {
+            error!("settings save error: {err}");
+        }
+    });
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            languages: vec!["en".to_string()],
+            locale: Locale::default(),
+            ui_scale: default_ui_scale(),
+            high_contrast: false,
+            appearance: Appearance::default(),
+            keys: KeyBindings::default(),
+        }
+    }
+}
+
+impl Settings {
+    fn path() -> Option<Utf8PathBuf> {
+        Some(paths::config_dir()?.join("settings.json"))
+    }
+
+    /// Reads the settings from disk, falling back to the default ones if they are missing or invalid
+    #[must_use]
+    pub fn load() -> Self {
+        let Some(path) = Self::path() else {
+            return Self::default();
+        };
+        let Ok(content) = read_to_string(&path) else {
+            return Self::default();
+        };
+        match serde_json::from_str(&content) {
+            Ok(settings) => settings,
+            Err(err) => {
+                warn!("invalid settings in {path}, using the default ones: {err}");
+                Self::default()
+            }
+        }
+    }
+
+    /// Writes the settings to disk, atomically so that a crash never leaves a truncated settings file
+    ///
+    /// # Errors
+    ///
+    /// Fails if the config directory can't be found, or if the settings file can't be written
+    pub fn save(&self) -> Result<()> {
+        let path =
+            Self::path().ok_or_else(|| Error::Unknown("config directory not found".to_string()))?;
+        if let Some(parent) = path.parent() {
+            create_dir_all(parent)?;
+        }
+        write_bytes_atomically(
+            serde_json::to_string_pretty(self)?,
+            &path,
+            WritePolicy::Overwrite,
+        )?;
+        Ok(())
+    }
+
+    /// The most preferred language
+    #[must_use]
+    pub fn language(&self) -> &str {
+        self.languages.first().map_or("en", String::as_str)
+    }
+
+    /// Moves `language` first in the preferred languages
+    pub fn prefer_language(&mut self, language: impl Into<String>) {
+        let language = language.into();
+        self.languages.retain(|preferred| *preferred != language);
+        self.languages.insert(0, language);
+    }
+}
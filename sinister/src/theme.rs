@@ -0,0 +1,112 @@
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{i18n::Text, settings::Settings};
+
+/// Dark or light interface, following the OS preference by default
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Appearance {
+    #[default]
+    System,
+    Dark,
+    Light,
+}
+
+impl Appearance {
+    pub const ALL: [Self; 3] = [Self::System, Self::Dark, Self::Light];
+
+    /// Value stored in the settings, and used by the appearance select
+    #[must_use]
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::System => "system",
+            Self::Dark => "dark",
+            Self::Light => "light",
+        }
+    }
+
+    #[must_use]
+    pub fn from_code(code: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|appearance| appearance.code() == code)
+    }
+
+    #[must_use]
+    pub fn label(self) -> Text {
+        match self {
+            Self::System => Text::System,
+            Self::Dark => Text::Dark,
+            Self::Light => Text::Light,
+        }
+    }
+
+    /// The OS preference is detected once, and the dark appearance is used when it's unknown
+    #[must_use]
+    pub fn is_dark(self) -> bool {
+        static SYSTEM_IS_DARK: OnceLock<bool> = OnceLock::new();
+
+        match self {
+            Self::System => {
+                *SYSTEM_IS_DARK.get_or_init(|| dark_light::detect() != dark_light::Mode::Light)
+            }
+            Self::Dark => true,
+            Self::Light => false,
+        }
+    }
+}
+
+/// Tailwind classes switched at runtime according to the settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Theme {
+    /// Text color of the whole window
+    pub text: &'static str,
+    /// Background of the window and the panels
+    pub background: &'static str,
+    /// Color of the separators and the borders
+    pub border: &'static str,
+    /// Main buttons, hover state included
+    pub button: &'static str,
+    /// Pagination buttons, hover state included
+    pub secondary_button: &'static str,
+    /// Hover state of the list items
+    pub hover: &'static str,
+}
+
+impl Theme {
+    #[must_use]
+    pub fn new(settings: &Settings) -> Self {
+        if settings.high_contrast {
+            Self {
+                text: "text-white",
+                background: "bg-black",
+                border: "border-white",
+                button: "bg-black border border-white hover:bg-white hover:text-black",
+                secondary_button: "bg-black hover:bg-white hover:text-black",
+                hover: "hover:bg-white hover:text-black",
+            }
+        } else if settings.appearance.is_dark() {
+            Self {
+                text: "text-slate-400",
+                background: "bg-slate-800",
+                border: "border-slate-900",
+                button: "bg-slate-900 hover:bg-slate-600",
+                secondary_button: "bg-slate-700 hover:bg-slate-500",
+                hover: "hover:bg-slate-600",
+            }
+        } else {
+            Self {
+                text: "text-slate-700",
+                background: "bg-slate-100",
+                border: "border-slate-300",
+                button: "bg-slate-300 hover:bg-slate-400",
+                secondary_button: "bg-slate-200 hover:bg-slate-300",
+                hover: "hover:bg-slate-300",
+            }
+        }
+    }
+}